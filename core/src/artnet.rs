@@ -0,0 +1,488 @@
+//! Encoders and decoders for Art-Net packets.
+//!
+//! These don't depend on any port type, so they can be used to build or
+//! inspect Art-Net traffic directly, including over an embedded network stack.
+//! Every encoder writes into a caller-provided buffer and returns the number of
+//! bytes used, or None if the buffer is too small.
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The UDP port Art-Net nodes listen on.
+pub const ARTNET_PORT: u16 = 6454;
+
+/// Every Art-Net packet starts with this identifier.
+pub const ARTNET_ID: &[u8; 8] = b"Art-Net\0";
+
+/// The Art-Net protocol revision these packets declare.
+pub const PROTOCOL_VERSION: u16 = 14;
+
+/// Opcode of a poll for nodes on the network.
+pub const OP_POLL: u16 = 0x2000;
+/// Opcode of a node's reply to a poll.
+pub const OP_POLL_REPLY: u16 = 0x2100;
+/// Opcode of a packet of DMX levels.
+pub const OP_DMX: u16 = 0x5000;
+/// Opcode of a packet telling nodes to output the frames they have buffered.
+pub const OP_SYNC: u16 = 0x5200;
+/// Opcode of a packet that renames or readdresses a node.
+pub const OP_ADDRESS: u16 = 0x6000;
+/// Opcode of a request for the RDM devices a gateway has discovered.
+pub const OP_TOD_REQUEST: u16 = 0x8000;
+/// Opcode of a gateway's list of discovered RDM devices.
+pub const OP_TOD_DATA: u16 = 0x8100;
+/// Opcode of a packet carrying an RDM message.
+pub const OP_RDM: u16 = 0x8300;
+
+/// Size of an ArtDmx packet before its levels.
+pub const DMX_HEADER_SIZE: usize = 18;
+
+/// A buffer of this size holds any ArtDmx packet.
+pub const MAX_DMX_PACKET_SIZE: usize = DMX_HEADER_SIZE + crate::DMX_UNIVERSE_SIZE;
+
+/// Size of an ArtPoll packet.
+pub const POLL_PACKET_SIZE: usize = 14;
+
+/// Size of an ArtSync packet.
+pub const SYNC_PACKET_SIZE: usize = 14;
+
+/// Size of an ArtAddress packet.
+pub const ADDRESS_PACKET_SIZE: usize = 107;
+
+/// An ArtAddress switch value that leaves the setting unchanged.
+const NO_CHANGE: u8 = 0x7F;
+
+/// The bit set in an ArtAddress switch value to program it.
+const PROGRAM: u8 = 0x80;
+
+/// Size of the header of ArtTodRequest, ArtTodData, and ArtRdm packets.
+pub const RDM_HEADER_SIZE: usize = 24;
+
+/// Size of an ArtTodRequest for a single port address.
+pub const TOD_REQUEST_SIZE: usize = RDM_HEADER_SIZE + 1;
+
+/// Size of an ArtTodData header, before its UIDs.
+const TOD_DATA_HEADER_SIZE: usize = 28;
+
+/// The RDM standard revision ArtTodData and ArtRdm packets declare.
+const RDM_VERSION: u8 = 1;
+
+/// Size of an RDM UID.
+pub const UID_SIZE: usize = 6;
+
+/// Size of an ArtPollReply up to and including its bind index. Older nodes
+/// send shorter replies that end before the bind index.
+pub const POLL_REPLY_SIZE: usize = 212;
+
+/// The shortest ArtPollReply that describes a node's ports.
+const MIN_POLL_REPLY_SIZE: usize = 200;
+
+/// Port type flag of a port that outputs DMX received over Art-Net.
+pub const PORT_TYPE_OUTPUT: u8 = 0x80;
+
+/// Protocol bits of a port type.
+const PORT_TYPE_PROTOCOL: u8 = 0x3F;
+
+/// ArtPoll flag asking nodes to send a reply whenever their state changes.
+pub const POLL_REPLY_ON_CHANGE: u8 = 0x02;
+
+/// Write the identifier, opcode, and protocol version shared by every packet.
+fn write_header(opcode: u16, buf: &mut [u8]) {
+    buf[..8].copy_from_slice(ARTNET_ID);
+    buf[8..10].copy_from_slice(&opcode.to_le_bytes());
+    buf[10..12].copy_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+}
+
+/// Encode an ArtDmx packet of levels for a 15-bit port address (net, subnet,
+/// and universe).
+///
+/// A sequence of 0 disables reordering on the node; otherwise it should count
+/// from 1 to 255 and wrap. Physical is informational and identifies the
+/// input the levels came from. Levels beyond a full universe are dropped, and
+/// the levels are padded with zeros to the even length of at least 2 that the
+/// protocol requires.
+pub fn encode_dmx(
+    sequence: u8,
+    physical: u8,
+    port_address: u16,
+    levels: &[u8],
+    buf: &mut [u8],
+) -> Option<usize> {
+    let levels = &levels[..levels.len().min(crate::DMX_UNIVERSE_SIZE)];
+    let len = (levels.len() + levels.len() % 2).max(2);
+    let packet = buf.get_mut(..DMX_HEADER_SIZE + len)?;
+    write_header(OP_DMX, packet);
+    let [net, sub_uni] = (port_address & 0x7FFF).to_be_bytes();
+    packet[12..16].copy_from_slice(&[sequence, physical, sub_uni, net]);
+    packet[16..18].copy_from_slice(&(len as u16).to_be_bytes());
+    let data = &mut packet[DMX_HEADER_SIZE..];
+    data[..levels.len()].copy_from_slice(levels);
+    data[levels.len()..].fill(0);
+    Some(packet.len())
+}
+
+/// Encode an ArtPoll packet with the given flags, such as POLL_REPLY_ON_CHANGE.
+pub fn encode_poll(flags: u8, buf: &mut [u8]) -> Option<usize> {
+    let packet = buf.get_mut(..POLL_PACKET_SIZE)?;
+    write_header(OP_POLL, packet);
+    // Flags, then the lowest diagnostic priority.
+    packet[12..14].copy_from_slice(&[flags, 0x10]);
+    Some(packet.len())
+}
+
+/// Decode an ArtPoll, returning its flags. Return None if buf isn't one.
+pub fn decode_poll(buf: &[u8]) -> Option<u8> {
+    if buf.len() < POLL_PACKET_SIZE || buf[..8] != *ARTNET_ID || buf[8..10] != OP_POLL.to_le_bytes()
+    {
+        return None;
+    }
+    Some(buf[12])
+}
+
+/// Encode an ArtSync packet.
+pub fn encode_sync(buf: &mut [u8]) -> Option<usize> {
+    let packet = buf.get_mut(..SYNC_PACKET_SIZE)?;
+    write_header(OP_SYNC, packet);
+    // Two auxiliary bytes, which must be zero.
+    packet[12..14].fill(0);
+    Some(packet.len())
+}
+
+/// The fields of a received ArtDmx packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtDmx<'a> {
+    pub sequence: u8,
+    pub physical: u8,
+    /// The 15-bit port address the levels are for.
+    pub port_address: u16,
+    pub levels: &'a [u8],
+}
+
+/// Decode an ArtDmx packet. Return None if buf isn't a well-formed one.
+pub fn decode_dmx(buf: &[u8]) -> Option<ArtDmx<'_>> {
+    if buf.get(..8)? != ARTNET_ID || buf.get(8..10)? != OP_DMX.to_le_bytes() {
+        return None;
+    }
+    let header = buf.get(..DMX_HEADER_SIZE)?;
+    let len = u16::from_be_bytes([header[16], header[17]]) as usize;
+    if len > crate::DMX_UNIVERSE_SIZE {
+        return None;
+    }
+    Some(ArtDmx {
+        sequence: header[12],
+        physical: header[13],
+        port_address: u16::from_le_bytes([header[14], header[15] & 0x7F]),
+        levels: buf.get(DMX_HEADER_SIZE..DMX_HEADER_SIZE + len)?,
+    })
+}
+
+/// Changes to make to a node with an ArtAddress packet. Anything left as None
+/// is unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtAddress {
+    /// Which of the node's bind indexes, as reported in its poll replies, to change.
+    pub bind_index: u8,
+    /// A new short name of up to 17 characters.
+    pub short_name: Option<String>,
+    /// A new long name of up to 63 characters.
+    pub long_name: Option<String>,
+    /// A new net, from 0 to 127.
+    pub net_switch: Option<u8>,
+    /// A new subnet, from 0 to 15.
+    pub sub_switch: Option<u8>,
+    /// A new universe for each output, from 0 to 15.
+    pub sw_out: [Option<u8>; 4],
+}
+
+/// Write a string into a null-terminated field, truncating it to fit.
+fn encode_string(s: &str, field: &mut [u8]) {
+    let len = s.len().min(field.len() - 1);
+    field[..len].copy_from_slice(&s.as_bytes()[..len]);
+    field[len..].fill(0);
+}
+
+/// Encode an ArtAddress packet.
+pub fn encode_address(address: &ArtAddress, buf: &mut [u8]) -> Option<usize> {
+    let packet = buf.get_mut(..ADDRESS_PACKET_SIZE)?;
+    packet.fill(0);
+    write_header(OP_ADDRESS, packet);
+    let switch = |value: Option<u8>, mask: u8| value.map_or(NO_CHANGE, |v| PROGRAM | (v & mask));
+    packet[12] = switch(address.net_switch, 0x7F);
+    packet[13] = address.bind_index;
+    // An empty name leaves the node's name unchanged.
+    if let Some(name) = &address.short_name {
+        encode_string(name, &mut packet[14..32]);
+    }
+    if let Some(name) = &address.long_name {
+        encode_string(name, &mut packet[32..96]);
+    }
+    packet[96..100].fill(NO_CHANGE);
+    for (out, &value) in packet[100..104].iter_mut().zip(&address.sw_out) {
+        *out = switch(value, 0xF);
+    }
+    packet[104] = switch(address.sub_switch, 0xF);
+    // Leave the sACN priority alone, and send no command.
+    packet[105] = 0xFF;
+    packet[106] = 0;
+    Some(packet.len())
+}
+
+/// Decode a field holding a null-terminated string.
+fn decode_string(field: &[u8]) -> String {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..len]).into_owned()
+}
+
+/// The fields of an ArtPollReply that a controller needs to address a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtPollReply {
+    pub ip: [u8; 4],
+    pub short_name: String,
+    pub long_name: String,
+    /// The net shared by every port the reply describes.
+    pub net_switch: u8,
+    /// The subnet shared by every port the reply describes.
+    pub sub_switch: u8,
+    /// How many of the four ports described are present.
+    pub num_ports: u8,
+    pub port_types: [u8; 4],
+    /// The universe of each port's output, in the low nibble.
+    pub sw_out: [u8; 4],
+    /// The node's MAC address, or all zeros if it doesn't report one.
+    pub mac: [u8; 6],
+    /// Which of several replies from one node this is, counting from 1.
+    pub bind_index: u8,
+}
+
+impl ArtPollReply {
+    /// Iterate over the DMX outputs this reply describes, as (port index,
+    /// 15-bit port address).
+    pub fn outputs(&self) -> impl Iterator<Item = (usize, u16)> + '_ {
+        let prefix = (self.net_switch as u16 & 0x7F) << 8 | (self.sub_switch as u16 & 0xF) << 4;
+        (0..(self.num_ports as usize).min(4)).filter_map(move |i| {
+            let port_type = self.port_types[i];
+            (port_type & PORT_TYPE_OUTPUT != 0 && port_type & PORT_TYPE_PROTOCOL == 0)
+                .then_some((i, prefix | (self.sw_out[i] & 0xF) as u16))
+        })
+    }
+}
+
+/// Encode an ArtPollReply describing a node.
+pub fn encode_poll_reply(reply: &ArtPollReply, buf: &mut [u8]) -> Option<usize> {
+    let packet = buf.get_mut(..POLL_REPLY_SIZE)?;
+    packet.fill(0);
+    write_header(OP_POLL_REPLY, packet);
+    // Unlike other packets, a reply carries the node's address in place of
+    // the protocol version.
+    packet[10..14].copy_from_slice(&reply.ip);
+    packet[14..16].copy_from_slice(&ARTNET_PORT.to_le_bytes());
+    packet[18] = reply.net_switch;
+    packet[19] = reply.sub_switch;
+    encode_string(&reply.short_name, &mut packet[26..44]);
+    encode_string(&reply.long_name, &mut packet[44..108]);
+    packet[173] = reply.num_ports;
+    packet[174..178].copy_from_slice(&reply.port_types);
+    packet[190..194].copy_from_slice(&reply.sw_out);
+    packet[201..207].copy_from_slice(&reply.mac);
+    packet[207..211].copy_from_slice(&reply.ip);
+    packet[211] = reply.bind_index;
+    Some(packet.len())
+}
+
+/// Decode an ArtPollReply. Return None if buf isn't a well-formed one.
+pub fn decode_poll_reply(buf: &[u8]) -> Option<ArtPollReply> {
+    if buf.len() < MIN_POLL_REPLY_SIZE
+        || buf[..8] != *ARTNET_ID
+        || buf[8..10] != OP_POLL_REPLY.to_le_bytes()
+    {
+        return None;
+    }
+    Some(ArtPollReply {
+        ip: buf[10..14].try_into().ok()?,
+        short_name: decode_string(&buf[26..44]),
+        long_name: decode_string(&buf[44..108]),
+        net_switch: buf[18],
+        sub_switch: buf[19],
+        num_ports: buf[173],
+        port_types: buf[174..178].try_into().ok()?,
+        sw_out: buf[190..194].try_into().ok()?,
+        mac: buf
+            .get(201..207)
+            .and_then(|mac| mac.try_into().ok())
+            .unwrap_or_default(),
+        // Nodes that predate bind indexes send a single reply.
+        bind_index: buf.get(211).copied().filter(|&i| i > 0).unwrap_or(1),
+    })
+}
+
+/// Write the header shared by ArtTodRequest and ArtRdm packets, which
+/// addresses a 15-bit port address as a net and an address within it.
+fn write_rdm_header(opcode: u16, port_address: u16, buf: &mut [u8]) {
+    buf[..RDM_HEADER_SIZE].fill(0);
+    write_header(opcode, buf);
+    buf[12] = RDM_VERSION;
+    let [address, net] = port_address.to_le_bytes();
+    buf[21] = net & 0x7F;
+    buf[23] = address;
+}
+
+/// Encode a request for the full table of RDM devices a gateway has
+/// discovered on one port address.
+pub fn encode_tod_request(port_address: u16, buf: &mut [u8]) -> Option<usize> {
+    let packet = buf.get_mut(..TOD_REQUEST_SIZE)?;
+    write_rdm_header(OP_TOD_REQUEST, port_address, packet);
+    // A request lists the addresses it's for after a count, in place of the
+    // single address that other packets carry.
+    packet[22] = 0;
+    packet[23] = 1;
+    packet[24] = port_address as u8;
+    Some(packet.len())
+}
+
+/// The fields of a received ArtTodData packet. A gateway with many devices
+/// splits its table across several packets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtTodData {
+    /// The 15-bit port address the devices are attached to.
+    pub port_address: u16,
+    /// The number of devices in the whole table.
+    pub uid_total: u16,
+    /// Which packet of the table this is, counting from 0.
+    pub block_count: u8,
+    pub uids: Vec<[u8; UID_SIZE]>,
+}
+
+/// Decode an ArtTodData packet. Return None if buf isn't a well-formed one.
+pub fn decode_tod_data(buf: &[u8]) -> Option<ArtTodData> {
+    if buf.get(..8)? != ARTNET_ID || buf.get(8..10)? != OP_TOD_DATA.to_le_bytes() {
+        return None;
+    }
+    let header = buf.get(..TOD_DATA_HEADER_SIZE)?;
+    let uid_count = header[27] as usize;
+    let uids = buf.get(TOD_DATA_HEADER_SIZE..TOD_DATA_HEADER_SIZE + uid_count * UID_SIZE)?;
+    Some(ArtTodData {
+        port_address: u16::from_le_bytes([header[23], header[21] & 0x7F]),
+        uid_total: u16::from_be_bytes([header[24], header[25]]),
+        block_count: header[26],
+        uids: uids
+            .chunks_exact(UID_SIZE)
+            .map(|uid| uid.try_into().unwrap())
+            .collect(),
+    })
+}
+
+/// Encode an ArtRdm packet carrying an RDM message to or from a port address.
+/// The message starts after its start code, with the sub start code.
+pub fn encode_rdm(port_address: u16, message: &[u8], buf: &mut [u8]) -> Option<usize> {
+    let packet = buf.get_mut(..RDM_HEADER_SIZE + message.len())?;
+    write_rdm_header(OP_RDM, port_address, packet);
+    packet[RDM_HEADER_SIZE..].copy_from_slice(message);
+    Some(packet.len())
+}
+
+/// The fields of a received ArtRdm packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtRdm<'a> {
+    /// The 15-bit port address the message is to or from.
+    pub port_address: u16,
+    /// The RDM message, starting after its start code.
+    pub message: &'a [u8],
+}
+
+/// Decode an ArtRdm packet. Return None if buf isn't a well-formed one.
+pub fn decode_rdm(buf: &[u8]) -> Option<ArtRdm<'_>> {
+    if buf.get(..8)? != ARTNET_ID || buf.get(8..10)? != OP_RDM.to_le_bytes() {
+        return None;
+    }
+    let header = buf.get(..RDM_HEADER_SIZE)?;
+    Some(ArtRdm {
+        port_address: u16::from_le_bytes([header[23], header[21] & 0x7F]),
+        message: &buf[RDM_HEADER_SIZE..],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_dmx() {
+        let mut buf = [0; MAX_DMX_PACKET_SIZE];
+        let len = encode_dmx(7, 1, 0x0123, &[10, 20, 30], &mut buf).unwrap();
+        assert_eq!(
+            b"Art-Net\0\x00\x50\x00\x0e\x07\x01\x23\x01\x00\x04\x0a\x14\x1e\x00",
+            &buf[..len]
+        );
+        let packet = decode_dmx(&buf[..len]).unwrap();
+        assert_eq!(
+            (0x0123, &[10, 20, 30, 0][..]),
+            (packet.port_address, packet.levels)
+        );
+        assert_eq!(None, encode_dmx(0, 0, 0, &[0; 12], &mut buf[..20]));
+        assert_eq!(Some(SYNC_PACKET_SIZE), encode_sync(&mut buf));
+    }
+
+    #[test]
+    fn test_decode_poll_reply() {
+        let mut buf = [0; POLL_REPLY_SIZE];
+        buf[..8].copy_from_slice(ARTNET_ID);
+        buf[8..10].copy_from_slice(&OP_POLL_REPLY.to_le_bytes());
+        buf[10..14].copy_from_slice(&[10, 0, 0, 5]);
+        buf[18..20].copy_from_slice(&[1, 2]);
+        buf[26..30].copy_from_slice(b"node");
+        buf[173] = 3;
+        // A DMX output, a DMX input, and a DMX output.
+        buf[174..178].copy_from_slice(&[0x80, 0x40, 0x80, 0x80]);
+        buf[190..194].copy_from_slice(&[3, 0, 4, 5]);
+        buf[201..207].copy_from_slice(&[2, 0, 0, 0, 0, 9]);
+        buf[211] = 2;
+        let reply = decode_poll_reply(&buf).unwrap();
+        assert_eq!(("node", 2), (reply.short_name.as_str(), reply.bind_index));
+        assert_eq!([2, 0, 0, 0, 0, 9], reply.mac);
+        // Replies too short to hold a MAC report none.
+        assert_eq!([0; 6], decode_poll_reply(&buf[..200]).unwrap().mac);
+        assert_eq!(
+            vec![(0, 0x0123), (2, 0x0124)],
+            reply.outputs().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_encode_address() {
+        let mut buf = [0; ADDRESS_PACKET_SIZE];
+        let address = ArtAddress {
+            bind_index: 1,
+            short_name: Some("stage left".to_string()),
+            sub_switch: Some(2),
+            sw_out: [Some(3), None, None, None],
+            ..Default::default()
+        };
+        let len = encode_address(&address, &mut buf).unwrap();
+        assert_eq!(ADDRESS_PACKET_SIZE, len);
+        assert_eq!([0x7F, 1], buf[12..14]);
+        assert_eq!(b"stage left\0", &buf[14..25]);
+        assert_eq!([0x83, 0x7F, 0x7F, 0x7F, 0x82], buf[100..105]);
+    }
+
+    #[test]
+    fn test_rdm() {
+        let mut buf = [0; 64];
+        let len = encode_rdm(0x0123, &[1, 2, 3], &mut buf).unwrap();
+        let packet = decode_rdm(&buf[..len]).unwrap();
+        assert_eq!(
+            (0x0123, &[1, 2, 3][..]),
+            (packet.port_address, packet.message)
+        );
+
+        let len = encode_tod_request(0x0123, &mut buf).unwrap();
+        assert_eq!([1, 0, 1, 0x23], [buf[21], buf[22], buf[23], buf[24]]);
+        // Turn the request into a reply, which has the address where the
+        // request has its count.
+        buf[8..10].copy_from_slice(&OP_TOD_DATA.to_le_bytes());
+        buf[23] = 0x23;
+        buf[24..28].copy_from_slice(&[0, 1, 0, 1]);
+        buf[28..34].copy_from_slice(&[0x45, 0x4E, 0, 0, 0, 9]);
+        let tod = decode_tod_data(&buf[..len + 9]).unwrap();
+        assert_eq!(0x0123, tod.port_address);
+        assert_eq!(vec![[0x45, 0x4E, 0, 0, 0, 9]], tod.uids);
+    }
+}
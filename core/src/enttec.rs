@@ -0,0 +1,121 @@
+//! Message framing for the Enttec USB DMX Pro protocol.
+//!
+//! These are the encoders the Enttec port itself uses, exposed so that tools,
+//! tests, and widget simulators can produce byte-identical widget traffic.
+//! Every encoder writes into a caller-provided buffer and returns the number
+//! of bytes used, or None if the buffer is too small. They only depend on core,
+//! so they are available on every target, including wasm32 and
+//! microcontrollers.
+
+/// First byte of every message.
+pub const START_VAL: u8 = 0x7E;
+/// Last byte of every message.
+pub const END_VAL: u8 = 0xE7;
+
+/// Request the widget's parameters and user configuration.
+pub const GET_PARAMETERS: u8 = 3;
+/// Set the widget's output parameters and user configuration.
+pub const SET_PARAMETERS: u8 = 4;
+/// A DMX packet received by the widget.
+pub const RECEIVE_DMX_PACKET: u8 = 5;
+/// Send a DMX packet from the widget's output.
+pub const SEND_DMX_PACKET: u8 = 6;
+/// Send an RDM packet from the widget's output, and listen for a response.
+pub const SEND_RDM_PACKET: u8 = 7;
+/// Choose whether the widget reports every received packet or only changes.
+pub const RECEIVE_DMX_ON_CHANGE: u8 = 8;
+/// Request the widget's serial number.
+pub const GET_WIDGET_SERIAL: u8 = 10;
+/// Send an RDM discovery request, whose responses may collide.
+pub const SEND_RDM_DISCOVERY: u8 = 11;
+/// DMXKing extension: send a DMX packet from output A of a dual-output widget.
+pub const SEND_DMX_PORT_A: u8 = 100;
+/// DMXKing extension: send a DMX packet from output B of a dual-output widget.
+pub const SEND_DMX_PORT_B: u8 = 101;
+
+/// Maximum valid size for a message payload.
+pub const MAX_PAYLOAD_SIZE: usize = 600;
+
+/// Messages are the size of the payload plus 5 bytes for type, length, and framing.
+pub const FRAMING_SIZE: usize = 5;
+
+/// A buffer of this size holds any valid message.
+pub const MAX_PACKET_SIZE: usize = MAX_PAYLOAD_SIZE + FRAMING_SIZE;
+
+/// Encode a message whose payload is the concatenation of parts.
+fn encode_parts(message_type: u8, parts: &[&[u8]], buf: &mut [u8]) -> Option<usize> {
+    let payload_size: usize = parts.iter().map(|part| part.len()).sum();
+    let packet_size = payload_size + FRAMING_SIZE;
+    let packet = buf.get_mut(..packet_size)?;
+    let [len_lsb, len_msb] = u16::try_from(payload_size).ok()?.to_le_bytes();
+    packet[..4].copy_from_slice(&[START_VAL, message_type, len_lsb, len_msb]);
+    let mut offset = 4;
+    for part in parts {
+        packet[offset..offset + part.len()].copy_from_slice(part);
+        offset += part.len();
+    }
+    packet[offset] = END_VAL;
+    Some(packet_size)
+}
+
+/// Encode a message into buf. If add_payload_pad_byte is set, a zero byte is
+/// inserted before the payload, such as the start code of a DMX packet.
+pub fn encode_packet(
+    message_type: u8,
+    payload: &[u8],
+    add_payload_pad_byte: bool,
+    buf: &mut [u8],
+) -> Option<usize> {
+    let pad: &[u8] = if add_payload_pad_byte { &[0] } else { &[] };
+    encode_parts(message_type, &[pad, payload], buf)
+}
+
+/// Encode a request for the widget's parameters, followed by up to
+/// user_config_size bytes of its stored user configuration.
+pub fn encode_get_parameters(user_config_size: u16, buf: &mut [u8]) -> Option<usize> {
+    encode_parts(GET_PARAMETERS, &[&user_config_size.to_le_bytes()], buf)
+}
+
+/// Encode a message setting the widget's output timing and storing a user
+/// configuration blob. Break and mark after break are in 10.67 microsecond
+/// units, and an output rate of 0 means as fast as possible. An empty user
+/// configuration leaves the stored one alone.
+pub fn encode_set_parameters(
+    break_time: u8,
+    mark_after_break_time: u8,
+    output_rate: u8,
+    user_config: &[u8],
+    buf: &mut [u8],
+) -> Option<usize> {
+    let user_config_size = u16::try_from(user_config.len()).ok()?;
+    encode_parts(
+        SET_PARAMETERS,
+        &[
+            &user_config_size.to_le_bytes(),
+            &[break_time, mark_after_break_time, output_rate],
+            user_config,
+        ],
+        buf,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let mut buf = [0; 16];
+        let len = encode_packet(SEND_DMX_PACKET, &[1, 2, 3], true, &mut buf).unwrap();
+        assert_eq!(&[START_VAL, 6, 4, 0, 0, 1, 2, 3, END_VAL], &buf[..len]);
+        assert_eq!(
+            None,
+            encode_packet(SEND_DMX_PACKET, &[0; 12], false, &mut buf)
+        );
+        let len = encode_set_parameters(9, 1, 40, &[7], &mut buf).unwrap();
+        assert_eq!(
+            &[START_VAL, 4, 6, 0, 1, 0, 9, 1, 40, 7, END_VAL],
+            &buf[..len]
+        );
+    }
+}
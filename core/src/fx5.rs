@@ -0,0 +1,66 @@
+//! Reports for the USB DMX interfaces of the Digital Enlightenment FX5
+//! family, such as the Nodle U1, which are HID devices.
+//!
+//! The interface holds a universe as 16 blocks of 32 channels, and each
+//! report sets one block or the interface's mode. Reports start with the
+//! report ID, which is always 0, as HID libraries expect.
+
+/// The USB vendor and product IDs of the interfaces.
+pub const FX5_USB_IDS: [(u16, u16); 2] = [(0x04B4, 0x0F1F), (0x16C0, 0x088B)];
+
+/// The size of every report, including the report ID.
+pub const REPORT_SIZE: usize = 34;
+
+/// The channels in each block.
+pub const BLOCK_SIZE: usize = 32;
+
+/// The blocks that make up a universe.
+pub const BLOCK_COUNT: usize = 16;
+
+/// The block number that sets the mode instead of channels.
+const MODE_BLOCK: u8 = 16;
+
+/// Output nothing.
+pub const MODE_STANDBY: u8 = 0;
+/// Output the levels the computer sends.
+pub const MODE_PC_OUT: u8 = 2;
+
+/// Encode a report setting up to BLOCK_SIZE channels of a block, starting
+/// with its first. Channels left out are set to 0.
+pub fn encode_block(block: u8, levels: &[u8], buf: &mut [u8]) -> Option<usize> {
+    if block as usize >= BLOCK_COUNT || levels.len() > BLOCK_SIZE {
+        return None;
+    }
+    let report = buf.get_mut(..REPORT_SIZE)?;
+    report.fill(0);
+    report[1] = block;
+    report[2..2 + levels.len()].copy_from_slice(levels);
+    Some(REPORT_SIZE)
+}
+
+/// Encode a report setting the interface's mode.
+pub fn encode_mode(mode: u8, buf: &mut [u8]) -> Option<usize> {
+    let report = buf.get_mut(..REPORT_SIZE)?;
+    report.fill(0);
+    report[1] = MODE_BLOCK;
+    report[2] = mode;
+    Some(REPORT_SIZE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let mut buf = [0xFF; REPORT_SIZE];
+        assert_eq!(Some(REPORT_SIZE), encode_block(15, &[1, 2], &mut buf));
+        assert_eq!([0, 15, 1, 2, 0], buf[..5]);
+        assert_eq!(0, buf[REPORT_SIZE - 1]);
+        assert_eq!(None, encode_block(16, &[], &mut buf));
+        assert_eq!(None, encode_block(0, &[0; 33], &mut buf));
+        assert_eq!(None, encode_mode(0, &mut buf[..10]));
+        encode_mode(MODE_PC_OUT, &mut buf).unwrap();
+        assert_eq!([0, 16, 2, 0], buf[..4]);
+    }
+}
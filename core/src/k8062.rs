@@ -0,0 +1,125 @@
+//! Reports for the Velleman K8062 (also sold as the HQ-Power VM116) USB DMX
+//! interface, which is a HID device.
+//!
+//! A frame doesn't fit in one report, so it is sent as a run of 8-byte
+//! reports, each starting with a command: the first carries the number of
+//! leading zero channels and the next 6 levels, and later ones carry 7 levels,
+//! or a run of zero channels and the next 6 levels, or a single level. Reports
+//! start with the report ID, which is always 0, as HID libraries expect.
+
+/// The USB vendor and product IDs of the interface.
+pub const K8062_USB_ID: (u16, u16) = (0x10CF, 0x8062);
+
+/// The size of every report, including the report ID.
+pub const REPORT_SIZE: usize = 9;
+
+/// The fewest channels a frame can carry, since the first report holds 6
+/// levels and every frame must end on a whole report.
+pub const MIN_CHANNELS: usize = 8;
+
+/// A buffer of this size holds the reports for any frame of up to 512
+/// channels: the first report, at most 71 of 7 or more channels, and up to 6
+/// of one.
+pub const MAX_FRAME_SIZE: usize = 78 * REPORT_SIZE;
+
+/// Start a frame: a count of zero channels, plus one for the start code,
+/// then 6 levels.
+const START: u8 = 4;
+/// 7 levels.
+const LEVELS: u8 = 2;
+/// A single level.
+const LEVEL: u8 = 3;
+/// A count of zero channels, then 6 levels.
+const ZEROS: u8 = 5;
+
+/// The longest run of zero channels one report may skip.
+const MAX_ZEROS: usize = 100;
+
+/// Return the number of zero levels at the start of levels, up to max.
+fn zero_run(levels: &[u8], max: usize) -> usize {
+    levels
+        .iter()
+        .take(max)
+        .take_while(|&&level| level == 0)
+        .count()
+}
+
+/// Encode the reports for a frame of MIN_CHANNELS to 512 levels into buf,
+/// one after another, and return the bytes used.
+pub fn encode_frame(levels: &[u8], buf: &mut [u8]) -> Option<usize> {
+    if !(MIN_CHANNELS..=crate::DMX_UNIVERSE_SIZE).contains(&levels.len()) {
+        return None;
+    }
+    let mut reports = buf.chunks_exact_mut(REPORT_SIZE);
+    let mut used = 0;
+    let mut next = |command: u8, header: &[u8], data: &[u8]| {
+        let report = reports.next()?;
+        report.fill(0);
+        report[1] = command;
+        report[2..2 + header.len()].copy_from_slice(header);
+        report[2 + header.len()..2 + header.len() + data.len()].copy_from_slice(data);
+        used += REPORT_SIZE;
+        Some(())
+    };
+    let zeros = zero_run(levels, (levels.len() - 6).min(MAX_ZEROS));
+    next(START, &[zeros as u8 + 1], &levels[zeros..zeros + 6])?;
+    let mut i = zeros + 6;
+    while levels.len() - i >= 7 {
+        let zeros = zero_run(&levels[i..], (levels.len() - i - 6).min(MAX_ZEROS));
+        if zeros > 0 {
+            next(ZEROS, &[zeros as u8], &levels[i + zeros..i + zeros + 6])?;
+            i += zeros + 6;
+        } else {
+            next(LEVELS, &[], &levels[i..i + 7])?;
+            i += 7;
+        }
+    }
+    for &level in &levels[i..] {
+        next(LEVEL, &[], &[level])?;
+    }
+    Some(used)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_frame() {
+        let mut levels = [0; 24];
+        levels[3..9].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+        levels[9..16].copy_from_slice(&[7, 8, 9, 10, 11, 12, 13]);
+        levels[19..24].copy_from_slice(&[14, 15, 16, 17, 18]);
+        let mut buf = [0xFF; MAX_FRAME_SIZE];
+        let len = encode_frame(&levels, &mut buf).unwrap();
+        let reports: Vec<_> = buf[..len].chunks(REPORT_SIZE).collect();
+        assert_eq!(
+            vec![
+                &[0, 4, 4, 1, 2, 3, 4, 5, 6][..],
+                &[0, 2, 7, 8, 9, 10, 11, 12, 13],
+                // The zero run stops short so 6 levels are left to send.
+                &[0, 5, 2, 0, 14, 15, 16, 17, 18],
+            ],
+            reports
+        );
+    }
+
+    #[test]
+    fn test_encode_tail_and_limits() {
+        let mut buf = [0; MAX_FRAME_SIZE];
+        let len = encode_frame(&[9; 10], &mut buf).unwrap();
+        let commands: Vec<_> = buf[..len].chunks(REPORT_SIZE).map(|r| r[1]).collect();
+        assert_eq!(vec![START, LEVEL, LEVEL, LEVEL, LEVEL], commands);
+        // A blackout skips zeros 100 at a time.
+        let len = encode_frame(&[0; 512], &mut buf).unwrap();
+        assert_eq!([0, 4, 101], buf[..3]);
+        assert!(len <= MAX_FRAME_SIZE);
+        // The busiest frame fills the buffer: one short zero run leaves 6
+        // single channels at the end.
+        let mut levels = [1; 512];
+        levels[6..10].fill(0);
+        assert_eq!(Some(MAX_FRAME_SIZE), encode_frame(&levels, &mut buf));
+        assert_eq!(None, encode_frame(&[0; 7], &mut buf));
+        assert_eq!(None, encode_frame(&[1; 512], &mut buf[..REPORT_SIZE * 10]));
+    }
+}
@@ -0,0 +1,22 @@
+//! The protocol code of rust_dmx that doesn't need an operating system: the
+//! packet encoders and decoders for the Enttec USB DMX Pro, the FX5 family and
+//! Velleman K8062 HID interfaces, Art-Net, and sACN, and a backend that drives
+//! an Enttec widget from a microcontroller's UART.
+//!
+//! The crate is no_std, and only needs `alloc` for the names and device
+//! tables some packets carry. rust_dmx re-exports the codecs for use with its
+//! ports.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod artnet;
+pub mod enttec;
+pub mod fx5;
+pub mod k8062;
+pub mod sacn;
+#[cfg(feature = "embedded-hal")]
+pub mod uart;
+
+/// The number of channels in a full DMX universe.
+pub const DMX_UNIVERSE_SIZE: usize = 512;
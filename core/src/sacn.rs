@@ -0,0 +1,120 @@
+//! Encoder and decoder for sACN (ANSI E1.31) data packets.
+//!
+//! Like the other codecs, the encoder writes into a caller-provided buffer and
+//! returns the number of bytes used, or None if the buffer is too small.
+use alloc::string::String;
+
+use crate::DMX_UNIVERSE_SIZE;
+
+/// The UDP port sACN is sent to.
+pub const SACN_PORT: u16 = 5568;
+
+const ACN_PACKET_IDENTIFIER: [u8; 12] = *b"ASC-E1.17\0\0\0";
+
+// Layer vectors of a data packet.
+const VECTOR_ROOT_E131_DATA: u32 = 0x04;
+const VECTOR_E131_DATA_PACKET: u32 = 0x02;
+const VECTOR_DMP_SET_PROPERTY: u8 = 0x02;
+
+/// Flags in the high nibble of every PDU's flags and length field.
+const PDU_FLAGS: u16 = 0x7000;
+
+// Offsets of the flags and length fields of each layer of a data packet.
+const ROOT_LAYER_OFFSET: usize = 16;
+const FRAMING_LAYER_OFFSET: usize = 38;
+const DMP_LAYER_OFFSET: usize = 115;
+
+/// DMP address and data type of a data packet.
+const DMP_ADDRESS_AND_DATA_TYPE: u8 = 0xA1;
+
+/// Offset of the first property value, the start code, in a data packet.
+const PROPERTY_VALUES_OFFSET: usize = 125;
+
+/// A buffer of this size holds any data packet.
+pub const MAX_DATA_PACKET_SIZE: usize = PROPERTY_VALUES_OFFSET + 1 + DMX_UNIVERSE_SIZE;
+
+/// Size of the source name field, including its null terminator.
+const SOURCE_NAME_SIZE: usize = 64;
+
+// Framing layer option flags.
+pub const PREVIEW_DATA: u8 = 0x40;
+pub const STREAM_TERMINATED: u8 = 0x20;
+
+/// The fields of a data packet that vary between packets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataPacket<'a> {
+    /// The sending component's identifier, a UUID.
+    pub cid: [u8; 16],
+    /// Longer names are truncated to 63 bytes when encoded.
+    pub source_name: String,
+    /// Receivers only merge the sources sending at the highest priority.
+    pub priority: u8,
+    pub sequence: u8,
+    /// Framing layer option flags, such as STREAM_TERMINATED.
+    pub options: u8,
+    pub universe: u16,
+    pub start_code: u8,
+    pub levels: &'a [u8],
+}
+
+/// Parse a data packet. Return None if buf isn't a well-formed one.
+pub fn parse_data_packet(buf: &[u8]) -> Option<DataPacket<'_>> {
+    let u16_at = |i: usize| Some(u16::from_be_bytes(buf.get(i..i + 2)?.try_into().ok()?));
+    let u32_at = |i: usize| Some(u32::from_be_bytes(buf.get(i..i + 4)?.try_into().ok()?));
+    if buf.get(4..16)? != ACN_PACKET_IDENTIFIER
+        || u32_at(18)? != VECTOR_ROOT_E131_DATA
+        || u32_at(40)? != VECTOR_E131_DATA_PACKET
+        || *buf.get(117)? != VECTOR_DMP_SET_PROPERTY
+    {
+        return None;
+    }
+    let name = &buf[44..44 + SOURCE_NAME_SIZE];
+    let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+    // The value count includes the start code.
+    let count = u16_at(123)? as usize;
+    let values = buf.get(PROPERTY_VALUES_OFFSET..PROPERTY_VALUES_OFFSET + count)?;
+    let (&start_code, levels) = values.split_first()?;
+    Some(DataPacket {
+        cid: buf[22..38].try_into().ok()?,
+        source_name: String::from_utf8_lossy(name).into_owned(),
+        priority: buf[108],
+        sequence: buf[111],
+        options: buf[112],
+        universe: u16_at(113)?,
+        start_code,
+        levels: &levels[..levels.len().min(DMX_UNIVERSE_SIZE)],
+    })
+}
+
+/// Encode a data packet into buf, returning the number of bytes used.
+/// Return None if buf is too small.
+pub fn encode_data_packet(packet: &DataPacket, buf: &mut [u8]) -> Option<usize> {
+    let len = PROPERTY_VALUES_OFFSET + 1 + packet.levels.len();
+    let buf = buf.get_mut(..len)?;
+    buf.fill(0);
+    let flags_and_length = |offset: usize| (PDU_FLAGS | (len - offset) as u16).to_be_bytes();
+    buf[..4].copy_from_slice(&[0x00, 0x10, 0x00, 0x00]);
+    buf[4..16].copy_from_slice(&ACN_PACKET_IDENTIFIER);
+    buf[16..18].copy_from_slice(&flags_and_length(ROOT_LAYER_OFFSET));
+    buf[18..22].copy_from_slice(&VECTOR_ROOT_E131_DATA.to_be_bytes());
+    buf[22..38].copy_from_slice(&packet.cid);
+    buf[38..40].copy_from_slice(&flags_and_length(FRAMING_LAYER_OFFSET));
+    buf[40..44].copy_from_slice(&VECTOR_E131_DATA_PACKET.to_be_bytes());
+    // Leave room for the null terminator.
+    let name = packet.source_name.as_bytes();
+    let name = &name[..name.len().min(SOURCE_NAME_SIZE - 1)];
+    buf[44..44 + name.len()].copy_from_slice(name);
+    buf[108] = packet.priority;
+    buf[111] = packet.sequence;
+    buf[112] = packet.options;
+    buf[113..115].copy_from_slice(&packet.universe.to_be_bytes());
+    buf[115..117].copy_from_slice(&flags_and_length(DMP_LAYER_OFFSET));
+    buf[117] = VECTOR_DMP_SET_PROPERTY;
+    buf[118] = DMP_ADDRESS_AND_DATA_TYPE;
+    // First property address 0, address increment 1.
+    buf[119..123].copy_from_slice(&[0, 0, 0, 1]);
+    buf[123..125].copy_from_slice(&(packet.levels.len() as u16 + 1).to_be_bytes());
+    buf[PROPERTY_VALUES_OFFSET] = packet.start_code;
+    buf[PROPERTY_VALUES_OFFSET + 1..].copy_from_slice(packet.levels);
+    Some(len)
+}
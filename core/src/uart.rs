@@ -0,0 +1,184 @@
+//! An Enttec USB DMX Pro compatible widget driven from a UART through the
+//! `embedded-hal` serial traits, such as from a microcontroller wired to the
+//! widget's serial interface.
+use embedded_hal_nb::nb::block;
+use embedded_hal_nb::serial::{Read, Write};
+
+use crate::enttec::{
+    encode_packet, END_VAL, MAX_PACKET_SIZE, MAX_PAYLOAD_SIZE, SEND_DMX_PACKET, START_VAL,
+};
+use crate::DMX_UNIVERSE_SIZE;
+
+/// An error talking to a widget over a UART.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UartError<E> {
+    /// A message to send is too large for the protocol, or one received is
+    /// too large for the buffer it was read into.
+    TooLong,
+    /// A received message didn't end where its length said it would.
+    Malformed,
+    /// The UART failed.
+    Serial(E),
+}
+
+/// Send and receive Enttec widget messages over a UART. Every call blocks
+/// until the UART has taken or delivered the whole message.
+#[derive(Debug)]
+pub struct EnttecUart<S> {
+    serial: S,
+    buf: [u8; MAX_PACKET_SIZE],
+}
+
+impl<S> EnttecUart<S> {
+    pub fn new(serial: S) -> Self {
+        Self {
+            serial,
+            buf: [0; MAX_PACKET_SIZE],
+        }
+    }
+
+    /// Unwrap this backend into the UART.
+    pub fn release(self) -> S {
+        self.serial
+    }
+}
+
+impl<S: Write> EnttecUart<S> {
+    /// Send a DMX frame with a start code of 0 from the widget's output.
+    pub fn write(&mut self, frame: &[u8]) -> Result<(), UartError<S::Error>> {
+        if frame.len() > DMX_UNIVERSE_SIZE {
+            return Err(UartError::TooLong);
+        }
+        self.write_message(SEND_DMX_PACKET, frame, true)
+    }
+
+    /// Send a message, as with `enttec::encode_packet`, and flush the UART.
+    pub fn write_message(
+        &mut self,
+        message_type: u8,
+        payload: &[u8],
+        add_payload_pad_byte: bool,
+    ) -> Result<(), UartError<S::Error>> {
+        let len = encode_packet(message_type, payload, add_payload_pad_byte, &mut self.buf)
+            .ok_or(UartError::TooLong)?;
+        for &byte in &self.buf[..len] {
+            block!(self.serial.write(byte)).map_err(UartError::Serial)?;
+        }
+        block!(self.serial.flush()).map_err(UartError::Serial)
+    }
+}
+
+impl<S: Read> EnttecUart<S> {
+    /// Wait for the next message from the widget, skipping anything before
+    /// its start, and copy its payload into buf. Return the message type and
+    /// the payload's length.
+    pub fn read_message(&mut self, buf: &mut [u8]) -> Result<(u8, usize), UartError<S::Error>> {
+        let serial = &mut self.serial;
+        let mut read = || block!(serial.read()).map_err(UartError::Serial);
+        while read()? != START_VAL {}
+        let message_type = read()?;
+        let len = u16::from_le_bytes([read()?, read()?]) as usize;
+        if len > MAX_PAYLOAD_SIZE {
+            return Err(UartError::Malformed);
+        }
+        for byte in &mut self.buf[..len] {
+            *byte = read()?;
+        }
+        if read()? != END_VAL {
+            return Err(UartError::Malformed);
+        }
+        buf.get_mut(..len)
+            .ok_or(UartError::TooLong)?
+            .copy_from_slice(&self.buf[..len]);
+        Ok((message_type, len))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::enttec::GET_WIDGET_SERIAL;
+    use core::convert::Infallible;
+    use embedded_hal_nb::nb;
+    use embedded_hal_nb::serial::ErrorType;
+    use std::collections::VecDeque;
+
+    /// A UART that delivers received bytes one at a time, and is sometimes
+    /// busy, as a real one is.
+    #[derive(Default)]
+    struct MockSerial {
+        received: VecDeque<u8>,
+        sent: Vec<u8>,
+        busy: bool,
+    }
+
+    impl MockSerial {
+        fn poll<T>(&mut self, ready: impl FnOnce(&mut Self) -> T) -> nb::Result<T, Infallible> {
+            self.busy = !self.busy;
+            if self.busy {
+                return Err(nb::Error::WouldBlock);
+            }
+            Ok(ready(self))
+        }
+    }
+
+    impl ErrorType for MockSerial {
+        type Error = Infallible;
+    }
+
+    impl Write for MockSerial {
+        fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+            self.poll(|serial| serial.sent.push(byte))
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Infallible> {
+            self.poll(|_| ())
+        }
+    }
+
+    impl Read for MockSerial {
+        fn read(&mut self) -> nb::Result<u8, Infallible> {
+            self.poll(|serial| serial.received.pop_front())?
+                .ok_or(nb::Error::WouldBlock)
+        }
+    }
+
+    #[test]
+    fn test_writes_frames() {
+        let mut uart = EnttecUart::new(MockSerial::default());
+        uart.write(&[1, 2, 3]).unwrap();
+        assert_eq!(Err(UartError::TooLong), uart.write(&[0; 513]));
+        assert_eq!(
+            vec![START_VAL, SEND_DMX_PACKET, 4, 0, 0, 1, 2, 3, END_VAL],
+            uart.release().sent
+        );
+    }
+
+    #[test]
+    fn test_reads_messages() {
+        let serial = MockSerial {
+            received: [
+                0,
+                0xFF,
+                START_VAL,
+                GET_WIDGET_SERIAL,
+                4,
+                0,
+                1,
+                2,
+                3,
+                4,
+                END_VAL,
+            ]
+            .into_iter()
+            .chain([START_VAL, GET_WIDGET_SERIAL, 1, 0, 1, 0])
+            .collect(),
+            ..Default::default()
+        };
+        let mut uart = EnttecUart::new(serial);
+        let mut buf = [0; 8];
+        assert_eq!(Ok((GET_WIDGET_SERIAL, 4)), uart.read_message(&mut buf));
+        assert_eq!([1, 2, 3, 4], buf[..4]);
+        assert_eq!(Err(UartError::Malformed), uart.read_message(&mut buf));
+    }
+}
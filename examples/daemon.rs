@@ -0,0 +1,18 @@
+use rust_dmx::daemon::Daemon;
+use rust_dmx::{select_port, SenderConfig};
+
+/// Serve the ports of a saved JSON port config given on the command line, or
+/// else a port picked at the prompt.
+fn main() {
+    let daemon = match std::env::args().nth(1) {
+        Some(path) => Daemon::from_config_file(path).expect("failed to load port config"),
+        None => {
+            let port = select_port().expect("failed to open port");
+            println!("Serving \"{}\" as universe 0", port);
+            let config = SenderConfig::for_port(&*port);
+            Daemon::new(vec![port], config)
+        }
+    };
+    println!("Listening on http://127.0.0.1:8080");
+    daemon.serve("127.0.0.1:8080").expect("daemon failed");
+}
@@ -0,0 +1,81 @@
+//! Show the levels arriving on an input in real time.
+//!
+//! Usage: `monitor sacn <universe>`, `monitor artnet <net:subnet:universe>`, or
+//! `monitor enttec <serial port path>`.
+use std::env;
+use std::fmt;
+use std::io::{self, Write};
+use std::process;
+use std::time::Duration;
+
+use rust_dmx::{
+    ArtnetInputPort, DmxInputPort, EnttecDmxPort, InputMonitor, SacnInputPort, SacnMergeMode,
+};
+use serialport::{SerialPortInfo, SerialPortType};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: monitor sacn <universe> | monitor artnet <net:subnet:universe> | monitor enttec <serial port path>"
+    );
+    process::exit(1);
+}
+
+/// Channels shown on each row.
+const CHANNELS_PER_ROW: usize = 16;
+
+/// The monitor's grid of levels, with changed channels in reverse video.
+struct Colored<'a>(&'a InputMonitor);
+
+impl fmt::Display for Colored<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let monitor = self.0;
+        writeln!(f, "{} - {:.1} fps", monitor.port(), monitor.fps())?;
+        for (row, levels) in monitor.levels().chunks(CHANNELS_PER_ROW).enumerate() {
+            let first = row * CHANNELS_PER_ROW;
+            write!(f, "{:>3}:", first + 1)?;
+            for (i, level) in levels.iter().enumerate() {
+                if monitor.changed(first + i) {
+                    write!(f, " \x1b[7m{:>3}\x1b[0m", level)?;
+                } else {
+                    write!(f, " {:>3}", level)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+    let mut port: Box<dyn DmxInputPort> = match args.as_slice() {
+        [kind, universe] if kind == "sacn" => {
+            let universe = universe.parse().unwrap_or_else(|_| usage());
+            Box::new(SacnInputPort::new(vec![universe], SacnMergeMode::Merged))
+        }
+        [kind, port_address] if kind == "artnet" => {
+            let port_address = port_address.parse().unwrap_or_else(|_| usage());
+            Box::new(ArtnetInputPort::new(vec![port_address]))
+        }
+        [kind, path] if kind == "enttec" => Box::new(EnttecDmxPort::new(SerialPortInfo {
+            port_name: path.clone(),
+            port_type: SerialPortType::Unknown,
+        })),
+        _ => usage(),
+    };
+    port.open().expect("failed to open port");
+    let mut monitor = InputMonitor::new(port);
+    loop {
+        match monitor.poll(Duration::from_millis(100)) {
+            Ok(_) => {
+                // Clear the screen and redraw from the top.
+                print!("\x1b[2J\x1b[H{}", Colored(&monitor));
+                io::stdout().flush().unwrap();
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+    }
+}
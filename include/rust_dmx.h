@@ -0,0 +1,36 @@
+/* C interface to rust_dmx. Build the library with:
+ *   cargo rustc --release --features ffi --crate-type cdylib
+ */
+#ifndef RUST_DMX_H
+#define RUST_DMX_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define DMX_OK 0
+#define DMX_ERR_DISCONNECTED -1
+#define DMX_ERR_OVERRUN -2
+#define DMX_ERR_OTHER -3
+#define DMX_ERR_NULL -4
+
+typedef struct DmxPortListing DmxPortListing;
+typedef struct DmxPortHandle DmxPortHandle;
+
+DmxPortListing *dmx_list_ports(void);
+size_t dmx_listing_len(const DmxPortListing *listing);
+size_t dmx_listing_name(const DmxPortListing *listing, size_t index, char *buf, size_t buf_len);
+void dmx_listing_free(DmxPortListing *listing);
+
+DmxPortHandle *dmx_open(DmxPortListing *listing, size_t index);
+int dmx_write(DmxPortHandle *port, const uint8_t *frame, size_t len);
+void dmx_close(DmxPortHandle *port);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,1913 @@
+//! Support for the Art-Net protocol.
+use anyhow::{anyhow, bail};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::iter;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::keep_alive::KeepAlive;
+use crate::net::{bind_reusable, interface_addresses};
+use crate::rdm::{self, RdmTransport, Request, Response, Uid};
+use crate::{
+    system_clock, Clock, DmxInputPort, DmxPort, InputFrame, OpenError, PortListing, ReadError,
+    Wakeup, WriteError,
+};
+
+pub use rust_dmx_core::artnet as codec;
+
+use codec::{
+    decode_dmx, decode_poll, decode_poll_reply, decode_rdm, decode_tod_data, encode_address,
+    encode_dmx, encode_poll, encode_poll_reply, encode_rdm, encode_sync, encode_tod_request,
+    ArtAddress, ArtPollReply, ADDRESS_PACKET_SIZE, ARTNET_PORT, MAX_DMX_PACKET_SIZE,
+    POLL_PACKET_SIZE, POLL_REPLY_SIZE, PORT_TYPE_OUTPUT, RDM_HEADER_SIZE, SYNC_PACKET_SIZE,
+    TOD_REQUEST_SIZE,
+};
+
+/// The address of one universe on an Art-Net network: a net from 0 to 127,
+/// a subnet from 0 to 15, and a universe from 0 to 15.
+///
+/// It is displayed and parsed as "net:subnet:universe", and serialized as the
+/// 15-bit number that ArtDmx packets carry.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "u16", into = "u16")]
+pub struct PortAddress(u16);
+
+impl PortAddress {
+    /// Return an error if any part is out of range.
+    pub fn new(net: u8, subnet: u8, universe: u8) -> anyhow::Result<Self> {
+        if net > 127 || subnet > 15 || universe > 15 {
+            bail!("Art-Net port address {net}:{subnet}:{universe} is out of range");
+        }
+        Ok(Self(
+            (net as u16) << 8 | (subnet as u16) << 4 | universe as u16,
+        ))
+    }
+
+    /// Return the net part of the address.
+    pub fn net(self) -> u8 {
+        (self.0 >> 8) as u8
+    }
+
+    /// Return the subnet part of the address.
+    pub fn subnet(self) -> u8 {
+        (self.0 >> 4) as u8 & 0xF
+    }
+
+    /// Return the universe part of the address.
+    pub fn universe(self) -> u8 {
+        self.0 as u8 & 0xF
+    }
+}
+
+impl TryFrom<u16> for PortAddress {
+    type Error = anyhow::Error;
+
+    /// Unpack a 15-bit port address.
+    fn try_from(packed: u16) -> anyhow::Result<Self> {
+        if packed > 0x7FFF {
+            bail!("Art-Net port address {packed} is out of range");
+        }
+        Ok(Self(packed))
+    }
+}
+
+impl From<PortAddress> for u16 {
+    fn from(address: PortAddress) -> Self {
+        address.0
+    }
+}
+
+impl FromStr for PortAddress {
+    type Err = anyhow::Error;
+
+    /// Parse "net:subnet:universe", or a packed 15-bit port address.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let parts = s
+            .split(':')
+            .map(str::parse)
+            .collect::<Result<Vec<u16>, _>>()
+            .map_err(|err| anyhow!("invalid Art-Net port address {s:?}: {err}"))?;
+        match parts[..] {
+            [packed] => Self::try_from(packed),
+            [net, subnet, universe] => Self::new(
+                u8::try_from(net)?,
+                u8::try_from(subnet)?,
+                u8::try_from(universe)?,
+            ),
+            _ => bail!("invalid Art-Net port address {s:?}; expected net:subnet:universe"),
+        }
+    }
+}
+
+impl fmt::Display for PortAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.net(), self.subnet(), self.universe())
+    }
+}
+
+/// The UDP socket Art-Net packets are sent and received through.
+///
+/// This is a socket in normal use; tests and simulators can provide their own
+/// to script the nodes on a network and check exactly what is sent.
+pub trait UdpTransport: Send + Sync {
+    /// Send packet to dest.
+    fn send_to(&self, packet: &[u8], dest: SocketAddrV4) -> io::Result<()>;
+
+    /// Wait up to timeout, which is never zero, for a packet. Return its
+    /// length and sender, or None if none arrived in time.
+    fn recv_from(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> io::Result<Option<(usize, SocketAddr)>>;
+}
+
+impl UdpTransport for UdpSocket {
+    fn send_to(&self, packet: &[u8], dest: SocketAddrV4) -> io::Result<()> {
+        UdpSocket::send_to(self, packet, dest)?;
+        Ok(())
+    }
+
+    fn recv_from(
+        &self,
+        buf: &mut [u8],
+        timeout: Duration,
+    ) -> io::Result<Option<(usize, SocketAddr)>> {
+        self.set_read_timeout(Some(timeout))?;
+        match UdpSocket::recv_from(self, buf) {
+            Ok(received) => Ok(Some(received)),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl fmt::Debug for dyn UdpTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UdpTransport")
+    }
+}
+
+/// Opens a transport bound to an address: the local interface to send from,
+/// and the UDP port to receive on, or 0 for any. Called whenever a port is
+/// opened or discovery exchanges packets with nodes.
+pub type UdpTransportOpener =
+    Arc<dyn Fn(SocketAddrV4) -> io::Result<Arc<dyn UdpTransport>> + Send + Sync>;
+
+/// A transport opener, which Debug shows without its closure.
+#[derive(Clone)]
+struct Opener(UdpTransportOpener);
+
+impl fmt::Debug for Opener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UdpTransportOpener")
+    }
+}
+
+/// Open a transport on the Art-Net port of the interface with address
+/// interface, where nodes send their replies, through opener if given.
+fn open_exchange(
+    opener: Option<&Opener>,
+    interface: Ipv4Addr,
+    udp_port: u16,
+) -> anyhow::Result<Arc<dyn UdpTransport>> {
+    let addr = SocketAddrV4::new(interface, udp_port);
+    let transport = match opener {
+        Some(opener) => (opener.0)(addr),
+        None => bind_reusable(addr).and_then(|socket| {
+            socket.set_broadcast(true)?;
+            Ok(Arc::new(socket) as Arc<dyn UdpTransport>)
+        }),
+    };
+    transport.map_err(|err| anyhow!("failed to bind Art-Net port {udp_port}: {err}"))
+}
+
+/// Bind a socket for sending Art-Net from the interface with address interface.
+fn bind_output_socket(interface: Ipv4Addr) -> io::Result<Arc<UdpSocket>> {
+    let socket = UdpSocket::bind((interface, 0))?;
+    socket.set_broadcast(true)?;
+    Ok(Arc::new(socket))
+}
+
+/// The sockets shared by open Art-Net output ports, one for each interface
+/// address they send from. Each is closed once no port using it is open.
+static ARTNET_SOCKETS: Mutex<BTreeMap<Ipv4Addr, Weak<UdpSocket>>> = Mutex::new(BTreeMap::new());
+
+/// Return the shared Art-Net output socket for the interface with address
+/// interface, binding it if no port has it open. The unspecified address
+/// leaves the choice of interface to the OS.
+fn get_socket(interface: Ipv4Addr) -> io::Result<Arc<UdpSocket>> {
+    let mut shared = ARTNET_SOCKETS.lock().unwrap();
+    if let Some(socket) = shared.get(&interface).and_then(Weak::upgrade) {
+        return Ok(socket);
+    }
+    let socket = bind_output_socket(interface)?;
+    shared.retain(|_, socket| socket.strong_count() > 0);
+    shared.insert(interface, Arc::downgrade(&socket));
+    Ok(socket)
+}
+
+/// Stop sharing socket with ports that open from now on, so they bind a new one.
+fn forget_socket(socket: &Arc<dyn UdpTransport>) {
+    ARTNET_SOCKETS
+        .lock()
+        .unwrap()
+        .retain(|_, shared| !std::ptr::addr_eq(shared.as_ptr(), Arc::as_ptr(socket)));
+}
+
+/// Counts the changes to the host's interfaces seen by watchers that follow
+/// them. Ports opened before a change rebind their sockets on their next send.
+static HOST_INTERFACE_CHANGES: LazyLock<Arc<AtomicU64>> = LazyLock::new(Default::default);
+
+fn host_interface_changes() -> Arc<AtomicU64> {
+    HOST_INTERFACE_CHANGES.clone()
+}
+
+/// Lists the addresses of the host's interfaces, which Debug shows without
+/// its closure.
+#[derive(Clone)]
+struct InterfaceLister(Arc<dyn Fn() -> io::Result<Vec<Ipv4Addr>> + Send + Sync>);
+
+impl fmt::Debug for InterfaceLister {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InterfaceLister")
+    }
+}
+
+/// How often a watcher following the host's interfaces lists them.
+const INTERFACE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Large enough for any Art-Net packet.
+const RECEIVE_BUFFER_SIZE: usize = 1024;
+
+/// How long `available_ports` waits for nodes to answer a poll.
+const DISCOVERY_WAIT: Duration = Duration::from_secs(1);
+
+/// Options for polling the network for Art-Net nodes.
+#[derive(Debug, Clone)]
+pub struct ArtnetDiscovery {
+    interface: Option<Ipv4Addr>,
+    udp_port: u16,
+    target: Ipv4Addr,
+    /// The first and last addresses to poll one at a time, if any.
+    sweep: Option<(Ipv4Addr, Ipv4Addr)>,
+    opener: Option<Opener>,
+    /// Lists the host's interfaces, if watching follows them.
+    interfaces: Option<InterfaceLister>,
+    /// Counts the interface changes the watcher has seen, shared with the
+    /// ports it returns.
+    interface_changes: Arc<AtomicU64>,
+}
+
+impl Default for ArtnetDiscovery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArtnetDiscovery {
+    /// Broadcast polls from every interface on the standard Art-Net port.
+    pub fn new() -> Self {
+        Self {
+            interface: None,
+            udp_port: ARTNET_PORT,
+            target: Ipv4Addr::BROADCAST,
+            sweep: None,
+            opener: None,
+            interfaces: None,
+            interface_changes: host_interface_changes(),
+        }
+    }
+
+    /// Send the poll to target rather than broadcasting it to the local
+    /// network. Target may be one node or a subnet's broadcast address, such
+    /// as 10.1.255.255, for nodes that a global broadcast doesn't reach
+    /// across a router.
+    pub fn to(mut self, target: Ipv4Addr) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Also poll every address from first to last, one at a time, for nodes
+    /// that never answer a broadcast poll. Nodes that answer either poll are
+    /// listed once.
+    pub fn sweep(mut self, first: Ipv4Addr, last: Ipv4Addr) -> Self {
+        self.sweep = Some((first, last));
+        self
+    }
+
+    /// Sweep the host addresses of the subnet containing addr with a prefix
+    /// of prefix_len bits, such as 10.1.0.0 and 24 for 10.1.0.1 to 10.1.0.254.
+    pub fn sweep_subnet(self, addr: Ipv4Addr, prefix_len: u8) -> Self {
+        let mask = u32::MAX
+            .checked_shl(32 - u32::from(prefix_len.min(32)))
+            .unwrap_or(0);
+        let network = u32::from(addr) & mask;
+        let broadcast = network | !mask;
+        // Leave out the network and broadcast addresses, which /31 and /32
+        // subnets don't have.
+        let (first, last) = if broadcast - network > 1 {
+            (network + 1, broadcast - 1)
+        } else {
+            (network, broadcast)
+        };
+        self.sweep(first.into(), last.into())
+    }
+
+    /// Poll from the local interface with address interface, and return
+    /// ports that send from it.
+    pub fn on_interface(mut self, interface: Ipv4Addr) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    /// Poll and listen on udp_port instead of the standard Art-Net port, and
+    /// return ports that send to it.
+    pub fn with_udp_port(mut self, udp_port: u16) -> Self {
+        self.udp_port = udp_port;
+        self
+    }
+
+    /// Poll through transports from opener instead of sockets, and return
+    /// ports that send through it too.
+    pub fn with_transport(
+        mut self,
+        opener: impl Fn(SocketAddrV4) -> io::Result<Arc<dyn UdpTransport>> + Send + Sync + 'static,
+    ) -> Self {
+        self.opener = Some(Opener(Arc::new(opener)));
+        self
+    }
+
+    /// While watching, list the host's interfaces every
+    /// INTERFACE_CHECK_INTERVAL. When an address comes or goes, report it,
+    /// poll at once so nodes on the new network appear, and have open ports
+    /// rebind their sockets on their next send.
+    pub fn follow_interfaces(mut self) -> Self {
+        self.interfaces = Some(InterfaceLister(Arc::new(interface_addresses)));
+        self
+    }
+
+    /// Poll, and return a port for each DMX output of every node that answers
+    /// within wait.
+    pub fn run(&self, wait: Duration) -> anyhow::Result<Vec<ArtnetDmxPort>> {
+        let replies = self.poll(wait)?;
+        Ok(ArtnetDmxPort::from_replies(&replies)
+            .into_iter()
+            .map(|port| ArtnetDmxPort {
+                interface: self.interface,
+                udp_port: self.udp_port,
+                opener: self.opener.clone(),
+                interface_changes: self.interface_changes.clone(),
+                ..port
+            })
+            .collect())
+    }
+
+    /// Poll every interval on a background thread, and report outputs as
+    /// they appear and disappear. An output disappears once it has been
+    /// missing from MISSED_POLLS_BEFORE_GONE polls in a row. With
+    /// `follow_interfaces`, also report changes to the host's interfaces.
+    /// Polling stops when the watcher is dropped or the receiver hangs up.
+    pub fn watch(self, interval: Duration) -> (ArtnetWatcher, Receiver<ArtnetEvent>) {
+        self.watch_with_clock(interval, system_clock())
+    }
+
+    /// Like `watch`, but schedule polls using clock.
+    pub fn watch_with_clock(
+        self,
+        interval: Duration,
+        clock: Arc<dyn Clock>,
+    ) -> (ArtnetWatcher, Receiver<ArtnetEvent>) {
+        let (events, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let wakeup = Wakeup::new();
+        let thread = {
+            let stop = stop.clone();
+            let wakeup = wakeup.clone();
+            thread::spawn(move || watch(self, interval, &events, &stop, &wakeup, &*clock))
+        };
+        let watcher = ArtnetWatcher {
+            stop,
+            wakeup,
+            thread: Some(thread),
+        };
+        (watcher, receiver)
+    }
+
+    /// Send an ArtPoll and collect the replies that arrive within wait, each
+    /// with the address it arrived from.
+    fn poll(&self, wait: Duration) -> anyhow::Result<Vec<(Ipv4Addr, ArtPollReply)>> {
+        let mut buf = [0; POLL_PACKET_SIZE];
+        let len = encode_poll(0, &mut buf).expect("buffer holds a poll");
+        let mut replies = Vec::new();
+        let interface = self.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        let transport = open_exchange(self.opener.as_ref(), interface, self.udp_port)?;
+        let dests =
+            iter::once(self.target)
+                .chain(self.sweep.into_iter().flat_map(|(first, last)| {
+                    (u32::from(first)..=u32::from(last)).map(Ipv4Addr::from)
+                }))
+                .map(|ip| SocketAddrV4::new(ip, self.udp_port));
+        exchange(&*transport, dests, &buf[..len], wait, |packet, sender| {
+            // Our own poll comes back too, and is skipped as not being a reply.
+            if let Some(reply) = decode_poll_reply(packet) {
+                let from = match sender {
+                    SocketAddr::V4(sender) => *sender.ip(),
+                    SocketAddr::V6(_) => Ipv4Addr::from(reply.ip),
+                };
+                replies.push((from, reply));
+            }
+            false
+        })?;
+        Ok(replies)
+    }
+
+    /// If following the host's interfaces, list them, and return the change
+    /// from the last listing, if any. The first listing is taken as is.
+    fn check_interfaces(&self, last: &mut Option<Vec<Ipv4Addr>>) -> Option<ArtnetEvent> {
+        let listed = match (self.interfaces.as_ref()?.0)() {
+            Ok(listed) => listed,
+            Err(err) => {
+                warn!("Failed to list network interfaces: {err}.");
+                return None;
+            }
+        };
+        let before = last.replace(listed.clone())?;
+        let added: Vec<_> = listed
+            .iter()
+            .filter(|addr| !before.contains(addr))
+            .copied()
+            .collect();
+        let removed: Vec<_> = before
+            .iter()
+            .filter(|addr| !listed.contains(addr))
+            .copied()
+            .collect();
+        if added.is_empty() && removed.is_empty() {
+            return None;
+        }
+        // Shared sockets may be bound to an address the host no longer has.
+        ARTNET_SOCKETS.lock().unwrap().clear();
+        self.interface_changes.fetch_add(1, Ordering::Relaxed);
+        Some(ArtnetEvent::InterfacesChanged { added, removed })
+    }
+}
+
+/// Send request to each of dests through transport, which should be bound to
+/// the Art-Net port where nodes send their replies, and pass each packet that
+/// arrives, with its sender, to on_reply until it returns true or wait passes. Failing to send
+/// to some destinations is only an error if sending to all of them failed.
+fn exchange(
+    transport: &dyn UdpTransport,
+    dests: impl IntoIterator<Item = SocketAddrV4>,
+    request: &[u8],
+    wait: Duration,
+    mut on_reply: impl FnMut(&[u8], SocketAddr) -> bool,
+) -> anyhow::Result<()> {
+    let mut error = None;
+    let mut sent = false;
+    for dest in dests {
+        match transport.send_to(request, dest) {
+            Ok(()) => sent = true,
+            Err(err) => error = Some(err),
+        }
+    }
+    if let (false, Some(err)) = (sent, error) {
+        return Err(err.into());
+    }
+    let deadline = Instant::now() + wait;
+    let mut buf = [0; RECEIVE_BUFFER_SIZE];
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(());
+        }
+        match transport.recv_from(&mut buf, deadline - now)? {
+            Some((len, sender)) => {
+                if on_reply(&buf[..len], sender) {
+                    return Ok(());
+                }
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+/// How a node that answered a poll is told apart from other nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NodeId {
+    Mac([u8; 6]),
+    /// The address the node's reply arrived from, if it reports no MAC.
+    Addr(Ipv4Addr),
+}
+
+/// How many polls in a row an output must miss before it is reported gone.
+/// Polls travel over UDP, so a single missed reply proves little.
+pub const MISSED_POLLS_BEFORE_GONE: u32 = 3;
+
+/// A change in the Art-Net outputs seen by an `ArtnetWatcher`.
+#[derive(Debug, Clone)]
+pub enum ArtnetEvent {
+    /// An output answered a poll for the first time, or again after disappearing.
+    Appeared(ArtnetDmxPort),
+    /// An output stopped answering polls.
+    Disappeared(ArtnetDmxPort),
+    /// The host's interface addresses changed. Open ports rebind their
+    /// sockets on their next send, and the watcher polls again at once.
+    InterfacesChanged {
+        added: Vec<Ipv4Addr>,
+        removed: Vec<Ipv4Addr>,
+    },
+}
+
+/// Polls for Art-Net nodes on a background thread. See `ArtnetDiscovery::watch`.
+pub struct ArtnetWatcher {
+    stop: Arc<AtomicBool>,
+    wakeup: Wakeup,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for ArtnetWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wakeup.wake();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The outputs seen by recent polls, and how many polls each has missed since.
+#[derive(Default)]
+struct OutputTracker {
+    outputs: BTreeMap<(Ipv4Addr, PortAddress), (ArtnetDmxPort, u32)>,
+}
+
+impl OutputTracker {
+    /// Record the outputs found by one poll, and return what changed.
+    fn update(&mut self, found: Vec<ArtnetDmxPort>) -> Vec<ArtnetEvent> {
+        let mut events = Vec::new();
+        for (_, missed) in self.outputs.values_mut() {
+            *missed += 1;
+        }
+        for port in found {
+            let key = (port.addr, port.port_address);
+            if self.outputs.insert(key, (port.clone(), 0)).is_none() {
+                events.push(ArtnetEvent::Appeared(port));
+            }
+        }
+        self.outputs.retain(|_, (port, missed)| {
+            if *missed < MISSED_POLLS_BEFORE_GONE {
+                return true;
+            }
+            events.push(ArtnetEvent::Disappeared(port.clone()));
+            false
+        });
+        events
+    }
+}
+
+fn watch(
+    discovery: ArtnetDiscovery,
+    interval: Duration,
+    events: &Sender<ArtnetEvent>,
+    stop: &AtomicBool,
+    wakeup: &Wakeup,
+    clock: &dyn Clock,
+) {
+    let mut tracker = OutputTracker::default();
+    let mut interfaces = None;
+    let mut next_poll = clock.now();
+    while !stop.load(Ordering::Relaxed) {
+        if let Some(change) = discovery.check_interfaces(&mut interfaces) {
+            if events.send(change).is_err() {
+                return;
+            }
+            next_poll = clock.now();
+        }
+        if clock.now() >= next_poll {
+            next_poll = clock.now() + interval;
+            match discovery.run(interval.min(DISCOVERY_WAIT)) {
+                Ok(found) => {
+                    for event in tracker.update(found) {
+                        if events.send(event).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(err) => warn!("Art-Net discovery failed: {err}."),
+            }
+        }
+        let wake = match discovery.interfaces {
+            Some(_) => next_poll.min(clock.now() + INTERFACE_CHECK_INTERVAL),
+            None => next_poll,
+        };
+        while !stop.load(Ordering::Relaxed) && clock.now() < wake {
+            clock.wait_until(Some(wake), wakeup);
+        }
+    }
+}
+
+fn default_sequenced() -> bool {
+    true
+}
+
+fn default_udp_port() -> u16 {
+    ARTNET_PORT
+}
+
+/// Rebind a port's socket once this many sends in a row have failed.
+const FAILURES_BEFORE_REBIND: u32 = 3;
+
+/// While sends keep failing, rebind a port's socket at most this often.
+const REBIND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to poll for a node that may have moved, when rebinding.
+const RESOLVE_WAIT: Duration = Duration::from_millis(250);
+
+/// The fastest a node can output a full universe of DMX512 at standard
+/// timing: a 92 us break, a 12 us mark after break, and 513 slots of 44 us.
+/// Nodes don't report their output rate in ArtPollReply, so this is assumed.
+const DMX_LINE_FPS: f64 = 44.0;
+
+fn default_max_fps() -> f64 {
+    DMX_LINE_FPS
+}
+
+/// Send DMX to one port of an Art-Net node.
+///
+/// Packets carry sequence numbers by default, so nodes can drop packets that
+/// arrive out of order.
+///
+/// If sends keep failing, such as when the interface the port sends from
+/// goes away, or a watcher following the host's interfaces sees them change,
+/// the port rebinds its socket. A port discovered with a node name
+/// also polls for that node, and follows it if its address changed along
+/// with the network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtnetDmxPort {
+    /// The node's IP address.
+    addr: Ipv4Addr,
+    /// The port address the node outputs.
+    port_address: PortAddress,
+    /// A name for the node, shown in the port's Display form.
+    #[serde(default)]
+    name: Option<String>,
+    /// If false, send a sequence of 0, which tells nodes not to reorder.
+    #[serde(default = "default_sequenced")]
+    sequenced: bool,
+    /// The address of the local interface to send from, if not left to the OS.
+    #[serde(default)]
+    interface: Option<Ipv4Addr>,
+    /// If true, send from a socket of this port's own instead of the shared one.
+    #[serde(default)]
+    own_socket: bool,
+    /// The UDP port the node listens on.
+    #[serde(default = "default_udp_port")]
+    udp_port: u16,
+    /// If set, resend the last frame whenever this long passes without a write.
+    #[serde(default)]
+    keep_alive: Option<Duration>,
+    /// The fastest rate the node outputs frames at.
+    #[serde(default = "default_max_fps")]
+    max_fps: f64,
+    #[serde(skip)]
+    opener: Option<Opener>,
+    #[serde(skip)]
+    socket: Option<Arc<dyn UdpTransport>>,
+    #[serde(skip)]
+    sequence: u8,
+    #[serde(skip)]
+    rdm_transaction_number: u8,
+    #[serde(skip)]
+    keep_alive_thread: Option<Arc<KeepAlive>>,
+    /// How many sends in a row have failed.
+    #[serde(skip)]
+    failures: u32,
+    /// When the socket was last rebound, if it has been since the port was
+    /// opened.
+    #[serde(skip)]
+    last_rebind: Option<Instant>,
+    /// Counts the changes to the host's interfaces seen by watchers.
+    #[serde(skip, default = "host_interface_changes")]
+    interface_changes: Arc<AtomicU64>,
+    /// The count of interface changes when the socket was bound.
+    #[serde(skip)]
+    bound_at_change: u64,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+impl ArtnetDmxPort {
+    /// Create a port that sends to a port address of the node at addr.
+    pub fn new(addr: Ipv4Addr, port_address: PortAddress) -> Self {
+        Self {
+            addr,
+            port_address,
+            name: None,
+            sequenced: true,
+            interface: None,
+            own_socket: false,
+            udp_port: ARTNET_PORT,
+            keep_alive: None,
+            max_fps: DMX_LINE_FPS,
+            opener: None,
+            socket: None,
+            sequence: 0,
+            rdm_transaction_number: 0,
+            keep_alive_thread: None,
+            failures: 0,
+            last_rebind: None,
+            interface_changes: host_interface_changes(),
+            bound_at_change: 0,
+            clock: system_clock(),
+        }
+    }
+
+    /// Identify the node by name instead of by IP address.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Send from the local interface with address interface, such as to keep
+    /// Art-Net on a show network when the machine is also on another LAN.
+    pub fn with_interface(mut self, interface: Ipv4Addr) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    /// Send from a socket of this port's own rather than one shared by every
+    /// port on the same interface. Each port then sends from its own source
+    /// port, and senders on different threads never share a socket's send
+    /// buffer.
+    pub fn with_own_socket(mut self) -> Self {
+        self.own_socket = true;
+        self
+    }
+
+    /// Poll the network for nodes, and return a port for each DMX output of
+    /// every node that answers within wait. A node with several outputs
+    /// yields several ports.
+    /// Use `ArtnetDiscovery` for more control over polling.
+    pub fn discover(wait: Duration) -> anyhow::Result<PortListing> {
+        Self::discover_with(ArtnetDiscovery::new(), wait)
+    }
+
+    /// Poll for nodes from the local interface with address interface, and
+    /// return ports that send from it.
+    pub fn discover_on(interface: Ipv4Addr, wait: Duration) -> anyhow::Result<PortListing> {
+        Self::discover_with(ArtnetDiscovery::new().on_interface(interface), wait)
+    }
+
+    /// Send a poll to addr, which may be one node or a subnet's broadcast
+    /// address, and return ports for the nodes that answer within wait.
+    pub fn discover_at(addr: Ipv4Addr, wait: Duration) -> anyhow::Result<PortListing> {
+        Self::discover_with(ArtnetDiscovery::new().to(addr), wait)
+    }
+
+    fn discover_with(discovery: ArtnetDiscovery, wait: Duration) -> anyhow::Result<PortListing> {
+        Ok(discovery
+            .run(wait)?
+            .into_iter()
+            .map(|port| Box::new(port) as Box<dyn DmxPort>)
+            .collect())
+    }
+
+    /// Send to a node listening on udp_port instead of the standard Art-Net port.
+    pub fn with_udp_port(mut self, udp_port: u16) -> Self {
+        self.udp_port = udp_port;
+        self
+    }
+
+    /// Send through transports from opener instead of sockets. The port is
+    /// not opened yet.
+    pub fn with_transport(
+        mut self,
+        opener: impl Fn(SocketAddrV4) -> io::Result<Arc<dyn UdpTransport>> + Send + Sync + 'static,
+    ) -> Self {
+        self.opener = Some(Opener(Arc::new(opener)));
+        self
+    }
+
+    /// Return where packets for the node are sent.
+    fn dest(&self) -> SocketAddrV4 {
+        SocketAddrV4::new(self.addr, self.udp_port)
+    }
+
+    /// Open a transport to send to the node from this port's interface.
+    fn open_transport(&self) -> io::Result<Arc<dyn UdpTransport>> {
+        let interface = self.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        if let Some(opener) = &self.opener {
+            return (opener.0)(SocketAddrV4::new(interface, 0));
+        }
+        if self.own_socket {
+            Ok(bind_output_socket(interface)?)
+        } else {
+            Ok(get_socket(interface)?)
+        }
+    }
+
+    /// Rename or readdress this port's node by sending it an ArtAddress
+    /// packet from the port's interface to the port's UDP port. The node
+    /// reports its new settings in its next poll reply.
+    pub fn address_node(&self, address: &ArtAddress) -> anyhow::Result<()> {
+        let mut buf = [0; ADDRESS_PACKET_SIZE];
+        let len = encode_address(address, &mut buf).expect("buffer holds an ArtAddress");
+        let socket = match &self.socket {
+            Some(socket) => socket.clone(),
+            None => self.open_transport()?,
+        };
+        socket.send_to(&buf[..len], self.dest())?;
+        Ok(())
+    }
+
+    /// Report that the node can output up to max_fps frames per second rather
+    /// than the DMX512 line rate, such as for a pixel controller.
+    pub fn with_max_fps(mut self, max_fps: f64) -> Self {
+        self.max_fps = max_fps;
+        self
+    }
+
+    /// Return a port for each output described by the replies, each given
+    /// with the address it arrived from. Nodes often answer a poll more than
+    /// once, and a node on several networks answers on each, so outputs are
+    /// identified by node MAC, or by address for nodes that don't report one,
+    /// then by bind index and port index. Ports are sent to the address the
+    /// node's first reply arrived from, since the IP a node reports may not
+    /// be one this host can reach, and are listed in order of that address,
+    /// bind index, and port index.
+    fn from_replies(replies: &[(Ipv4Addr, ArtPollReply)]) -> Vec<Self> {
+        let mut outputs = BTreeMap::new();
+        for (from, reply) in replies {
+            let node = if reply.mac == [0; 6] {
+                NodeId::Addr(*from)
+            } else {
+                NodeId::Mac(reply.mac)
+            };
+            for (index, port_address) in reply.outputs() {
+                let port_address =
+                    PortAddress::try_from(port_address).expect("outputs are 15-bit addresses");
+                outputs
+                    .entry((node, reply.bind_index, index))
+                    .or_insert_with(|| {
+                        let port = Self::new(*from, port_address);
+                        if reply.short_name.is_empty() {
+                            port
+                        } else {
+                            port.with_name(reply.short_name.as_str())
+                        }
+                    });
+            }
+        }
+        let mut outputs: Vec<_> = outputs.into_iter().collect();
+        outputs.sort_by_key(|((_, bind_index, index), port)| (port.addr, *bind_index, *index));
+        outputs.into_iter().map(|(_, port)| port).collect()
+    }
+
+    /// Resend the last frame whenever interval passes without a write, so
+    /// nodes don't time out their outputs while the application is idle.
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Time the keep-alive interval using clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Ask the node for the UIDs of the RDM devices it has discovered on this
+    /// port's port address, waiting up to wait for the whole table.
+    pub fn rdm_devices(&self, wait: Duration) -> anyhow::Result<Vec<Uid>> {
+        let mut buf = [0; TOD_REQUEST_SIZE];
+        let len =
+            encode_tod_request(self.port_address.into(), &mut buf).expect("buffer holds a request");
+        // Large tables arrive in several blocks, possibly more than once.
+        let mut blocks = BTreeMap::new();
+        self.exchange(&buf[..len], wait, |packet, _| {
+            let Some(tod) = decode_tod_data(packet) else {
+                return false;
+            };
+            if tod.port_address != u16::from(self.port_address) {
+                return false;
+            }
+            let total = tod.uid_total as usize;
+            blocks.insert(tod.block_count, tod.uids);
+            blocks.values().map(Vec::len).sum::<usize>() >= total
+        })?;
+        Ok(blocks
+            .into_values()
+            .flatten()
+            .map(Uid::from_bytes)
+            .collect())
+    }
+
+    /// Send an RDM request to a device through the node, and return its
+    /// response, or None if none arrives within wait. Messages start after
+    /// their start code, with the sub start code.
+    pub fn rdm_transaction(
+        &self,
+        request: &[u8],
+        wait: Duration,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        let mut buf = vec![0; RDM_HEADER_SIZE + request.len()];
+        let len = encode_rdm(self.port_address.into(), request, &mut buf)
+            .expect("buffer holds the request");
+        let mut response = None;
+        self.exchange(&buf[..len], wait, |packet, _| {
+            // Our own request can come back too, and is skipped.
+            response = decode_rdm(packet)
+                .filter(|rdm| rdm.port_address == u16::from(self.port_address))
+                .filter(|rdm| rdm.message != request)
+                .map(|rdm| rdm.message.to_vec());
+            response.is_some()
+        })?;
+        Ok(response)
+    }
+
+    /// Send request to the node, and pass replies to on_reply.
+    fn exchange(
+        &self,
+        request: &[u8],
+        wait: Duration,
+        on_reply: impl FnMut(&[u8], SocketAddr) -> bool,
+    ) -> anyhow::Result<()> {
+        let interface = self.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        let transport = open_exchange(self.opener.as_ref(), interface, self.udp_port)?;
+        exchange(&*transport, [self.dest()], request, wait, on_reply)
+    }
+
+    /// Send a sequence of 0 in every packet instead of counting, for nodes
+    /// that mishandle sequence numbers.
+    pub fn without_sequence(mut self) -> Self {
+        self.sequenced = false;
+        self
+    }
+
+    /// Send packet to the node, rebinding the socket if sends keep failing
+    /// or the host's interfaces changed since it was bound.
+    fn send(&mut self, packet: &[u8]) -> Result<(), WriteError> {
+        if self.socket.is_some()
+            && self.interface_changes.load(Ordering::Relaxed) != self.bound_at_change
+        {
+            info!("The network interfaces changed; rebinding {self}.");
+            self.rebind();
+        } else if self.socket.is_none() && self.last_rebind.is_some() {
+            // The last rebind failed; keep trying while the application writes.
+            self.rebind_after_failures();
+        }
+        let socket = self.socket.as_ref().ok_or(WriteError::Disconnected)?;
+        match socket.send_to(packet, self.dest()) {
+            Ok(()) => {
+                self.failures = 0;
+                Ok(())
+            }
+            Err(err) => {
+                self.failures += 1;
+                if self.failures >= FAILURES_BEFORE_REBIND {
+                    self.rebind_after_failures();
+                }
+                Err(anyhow::Error::from(err).into())
+            }
+        }
+    }
+
+    /// Rebind the socket, unless the last rebind was too recent.
+    fn rebind_after_failures(&mut self) {
+        if self
+            .last_rebind
+            .is_some_and(|last| self.clock.now() - last < REBIND_INTERVAL)
+        {
+            return;
+        }
+        warn!("{self} keeps failing to send; rebinding its socket.");
+        self.rebind();
+    }
+
+    /// Close the socket and bind a new one, first looking for the node in
+    /// case it moved.
+    fn rebind(&mut self) {
+        self.last_rebind = Some(self.clock.now());
+        self.failures = 0;
+        if let Some(socket) = self.socket.take() {
+            forget_socket(&socket);
+        }
+        self.keep_alive_thread = None;
+        self.resolve();
+        if let Err(err) = DmxPort::open(self) {
+            warn!("Failed to rebind {self}: {err}.");
+        }
+    }
+
+    /// Poll for the node by name, and follow it if its address changed.
+    fn resolve(&mut self) {
+        let Some(name) = &self.name else {
+            return;
+        };
+        let discovery = ArtnetDiscovery {
+            interface: self.interface,
+            udp_port: self.udp_port,
+            opener: self.opener.clone(),
+            interface_changes: self.interface_changes.clone(),
+            ..ArtnetDiscovery::new()
+        };
+        let found = match discovery.run(RESOLVE_WAIT) {
+            Ok(found) => found,
+            Err(err) => {
+                warn!("Failed to look for Art-Net node {name}: {err}.");
+                return;
+            }
+        };
+        let moved = found.into_iter().find(|port| {
+            port.name.as_ref() == Some(name) && port.port_address == self.port_address
+        });
+        if let Some(moved) = moved.filter(|port| port.addr != self.addr) {
+            warn!(
+                "Art-Net node {name} moved from {} to {}.",
+                self.addr, moved.addr
+            );
+            self.addr = moved.addr;
+        }
+    }
+
+    /// Return the sequence number for the next packet, which counts from 1 to
+    /// 255 and wraps, skipping the 0 that disables sequencing.
+    fn next_sequence(&mut self) -> u8 {
+        if !self.sequenced {
+            return 0;
+        }
+        self.sequence = self.sequence.checked_add(1).unwrap_or(1);
+        self.sequence
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for ArtnetDmxPort {
+    /// Poll the network for nodes. Discovery is best effort: if the Art-Net
+    /// port can't be bound, such as because another application holds it,
+    /// no ports are listed rather than failing the whole listing.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Self::discover(DISCOVERY_WAIT).or_else(|err| {
+            warn!("Art-Net discovery failed: {err}.");
+            Ok(Vec::new())
+        })
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.socket.is_none() {
+            let socket = self.open_transport().map_err(|err| {
+                let interface = self.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+                anyhow!("failed to bind Art-Net socket on {interface}: {err}")
+            })?;
+            if let Some(interval) = self.keep_alive {
+                let dest = self.dest();
+                let port_address = self.port_address.into();
+                let socket = socket.clone();
+                let mut buf = [0; MAX_DMX_PACKET_SIZE];
+                // Resent packets carry a sequence of 0, so nodes accept them
+                // whatever sequence they last saw.
+                let keep_alive = KeepAlive::start(interval, self.clock.clone(), move |levels| {
+                    let len = encode_dmx(0, 0, port_address, levels, &mut buf)
+                        .expect("levels are limited to a universe");
+                    if let Err(err) = socket.send_to(&buf[..len], dest) {
+                        warn!("Art-Net keep-alive to {} failed: {err}.", dest.ip());
+                    }
+                });
+                self.keep_alive_thread = Some(Arc::new(keep_alive));
+            }
+            self.socket = Some(socket);
+            self.bound_at_change = self.interface_changes.load(Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.keep_alive_thread = None;
+        self.socket = None;
+        self.failures = 0;
+        self.last_rebind = None;
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let sequence = self.next_sequence();
+        let mut buf = [0; MAX_DMX_PACKET_SIZE];
+        let len = encode_dmx(sequence, 0, self.port_address.into(), frame, &mut buf)
+            .expect("levels are limited to a universe");
+        if let Some(keep_alive) = &self.keep_alive_thread {
+            keep_alive.sending(frame);
+        }
+        self.send(&buf[..len])
+    }
+
+    /// Send an ArtSync to the node, so it outputs the frame just written.
+    fn sync(&mut self) -> Result<(), WriteError> {
+        let mut buf = [0; SYNC_PACKET_SIZE];
+        let len = encode_sync(&mut buf).expect("buffer fits an ArtSync packet");
+        self.send(&buf[..len])
+    }
+
+    /// A node can't output frames faster than it sends them down its DMX line.
+    fn max_fps(&self) -> Option<f64> {
+        Some(self.max_fps)
+    }
+}
+
+/// How long to wait for an RDM responder to answer through a node.
+const RDM_RESPONSE_WAIT: Duration = Duration::from_secs(1);
+
+impl RdmTransport for ArtnetDmxPort {
+    fn rdm_request(&mut self, request: &Request) -> anyhow::Result<Option<Response>> {
+        self.rdm_transaction_number = self.rdm_transaction_number.wrapping_add(1);
+        let request = Request {
+            transaction_number: self.rdm_transaction_number,
+            ..request.clone()
+        };
+        let mut buf = [0; rdm::MAX_MESSAGE_SIZE];
+        let len = request
+            .encode(&mut buf)
+            .ok_or_else(|| anyhow!("RDM parameter data is too long"))?;
+        // Art-Net carries messages without their start code.
+        let Some(mut message) = self.rdm_transaction(&buf[1..len], RDM_RESPONSE_WAIT)? else {
+            return Ok(None);
+        };
+        message.insert(0, rdm::START_CODE);
+        Ok(Response::decode(&message))
+    }
+}
+
+impl fmt::Display for ArtnetDmxPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only show the UDP port when it isn't the standard one.
+        let addr = if self.udp_port == ARTNET_PORT {
+            self.addr.to_string()
+        } else {
+            format!("{}:{}", self.addr, self.udp_port)
+        };
+        match &self.name {
+            Some(name) => write!(f, "Art-Net {} ({})", name, addr)?,
+            None => write!(f, "Art-Net {}", addr)?,
+        }
+        write!(f, " port address {}", self.port_address)?;
+        if let Some(interface) = self.interface {
+            write!(f, " via {interface}")?;
+        }
+        Ok(())
+    }
+}
+
+fn default_node_name() -> String {
+    "rust-dmx".to_string()
+}
+
+/// Return the address of the local interface that reaches peer, or the
+/// unspecified address if there is no route to it.
+fn local_ip_towards(peer: SocketAddr) -> Ipv4Addr {
+    let local = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| socket.connect(peer).and(socket.local_addr()));
+    match local {
+        Ok(SocketAddr::V4(local)) => *local.ip(),
+        _ => Ipv4Addr::UNSPECIFIED,
+    }
+}
+
+/// Receive ArtDmx packets for one or more port addresses, acting as a node.
+///
+/// Each packet is delivered as it arrives, with the packed 15-bit port
+/// address as the universe and the sender's IP address as the source.
+/// Polls that arrive while reading are answered with replies listing the
+/// port addresses as DMX outputs, so controllers can discover this node.
+#[derive(Serialize, Deserialize)]
+pub struct ArtnetInputPort {
+    port_addresses: Vec<PortAddress>,
+    /// The name this node reports in its poll replies.
+    #[serde(default = "default_node_name")]
+    name: String,
+    /// The address of the local interface to receive on, if not every one.
+    #[serde(default)]
+    interface: Option<Ipv4Addr>,
+    /// The UDP port to listen on.
+    #[serde(default = "default_udp_port")]
+    udp_port: u16,
+    #[serde(skip)]
+    socket: Option<UdpSocket>,
+}
+
+impl ArtnetInputPort {
+    /// Create a port that receives the given port addresses.
+    pub fn new(port_addresses: Vec<PortAddress>) -> Self {
+        Self {
+            port_addresses,
+            name: default_node_name(),
+            interface: None,
+            udp_port: ARTNET_PORT,
+            socket: None,
+        }
+    }
+
+    /// Report name to controllers that poll for nodes, instead of "rust-dmx".
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Receive only on the local interface with address interface. Some
+    /// platforms, Linux among them, don't deliver broadcast packets to a
+    /// socket bound to a specific address, so this only suits controllers
+    /// that send to this node directly.
+    pub fn with_interface(mut self, interface: Ipv4Addr) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    /// Listen on udp_port instead of the standard Art-Net port.
+    pub fn with_udp_port(mut self, udp_port: u16) -> Self {
+        self.udp_port = udp_port;
+        self
+    }
+
+    /// Describe this node's port addresses as poll replies from ip. A reply
+    /// holds up to four ports that share a net and subnet, so addresses are
+    /// grouped by those, and each group is given its own bind index.
+    fn poll_replies(&self, ip: Ipv4Addr) -> Vec<ArtPollReply> {
+        let mut groups: BTreeMap<_, Vec<PortAddress>> = BTreeMap::new();
+        for &address in &self.port_addresses {
+            groups
+                .entry((address.net(), address.subnet()))
+                .or_default()
+                .push(address);
+        }
+        let mut replies = Vec::new();
+        for ((net, subnet), addresses) in groups {
+            for chunk in addresses.chunks(4) {
+                let mut reply = ArtPollReply {
+                    ip: ip.octets(),
+                    short_name: self.name.clone(),
+                    long_name: self.name.clone(),
+                    net_switch: net,
+                    sub_switch: subnet,
+                    num_ports: chunk.len() as u8,
+                    port_types: [0; 4],
+                    sw_out: [0; 4],
+                    mac: [0; 6],
+                    bind_index: replies.len() as u8 + 1,
+                };
+                for (i, address) in chunk.iter().enumerate() {
+                    reply.port_types[i] = PORT_TYPE_OUTPUT;
+                    reply.sw_out[i] = address.universe();
+                }
+                replies.push(reply);
+            }
+        }
+        replies
+    }
+
+    /// Answer a poll from sender. Failing to reply only affects discovery,
+    /// so errors are logged rather than interrupting reception.
+    fn reply_to_poll(&self, socket: &UdpSocket, sender: SocketAddr) {
+        let mut buf = [0; POLL_REPLY_SIZE];
+        for reply in self.poll_replies(local_ip_towards(sender)) {
+            let len = encode_poll_reply(&reply, &mut buf).expect("buffer holds a reply");
+            if let Err(err) = socket.send_to(&buf[..len], sender) {
+                warn!("{self} failed to answer a poll from {sender}: {err}.");
+                return;
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl DmxInputPort for ArtnetInputPort {
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.socket.is_some() {
+            return Ok(());
+        }
+        let interface = self.interface.unwrap_or(Ipv4Addr::UNSPECIFIED);
+        let udp_port = self.udp_port;
+        let socket = bind_reusable(SocketAddrV4::new(interface, udp_port)).map_err(|err| {
+            anyhow!("failed to bind Art-Net port {udp_port} on {interface}: {err}")
+        })?;
+        self.socket = Some(socket);
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.socket = None;
+    }
+
+    fn read(&mut self, timeout: Duration) -> Result<Option<InputFrame>, ReadError> {
+        let deadline = Instant::now() + timeout;
+        let mut buf = [0; RECEIVE_BUFFER_SIZE];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            let socket = self.socket.as_ref().ok_or(ReadError::Disconnected)?;
+            socket
+                .set_read_timeout(Some(deadline - now))
+                .map_err(anyhow::Error::from)?;
+            let (len, sender) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None);
+                }
+                Err(err) => return Err(anyhow::Error::from(err).into()),
+            };
+            if decode_poll(&buf[..len]).is_some() {
+                self.reply_to_poll(socket, sender);
+                continue;
+            }
+            let Some(packet) = decode_dmx(&buf[..len]) else {
+                continue;
+            };
+            if !self
+                .port_addresses
+                .iter()
+                .any(|&a| u16::from(a) == packet.port_address)
+            {
+                continue;
+            }
+            return Ok(Some(InputFrame {
+                universe: packet.port_address,
+                source: Some(sender.ip().to_string()),
+                levels: packet.levels.to_vec(),
+            }));
+        }
+    }
+}
+
+impl fmt::Display for ArtnetInputPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let addresses: Vec<_> = self
+            .port_addresses
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        write!(f, "Art-Net input port address {}", addresses.join(", "))?;
+        if let Some(interface) = self.interface {
+            write!(f, " via {interface}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ManualClock;
+    use std::collections::VecDeque;
+
+    /// A node on a MockNetwork, which answers polls with reply.
+    struct MockNode {
+        reply: ArtPollReply,
+        /// Whether the node answers broadcast polls, or only unicast ones.
+        answers_broadcast: bool,
+    }
+
+    #[derive(Default)]
+    struct Network {
+        nodes: Vec<MockNode>,
+        /// Every packet sent, and where to.
+        sent: Vec<(SocketAddrV4, Vec<u8>)>,
+        /// The address each transport was opened on.
+        opened: Vec<SocketAddrV4>,
+        /// Sending to any of these fails, as if there were no route to them.
+        unreachable: Vec<Ipv4Addr>,
+    }
+
+    /// A network of scripted nodes. Clones share the same network, so a test
+    /// can inspect what was sent through transports handed to a port.
+    #[derive(Clone, Default)]
+    struct MockNetwork(Arc<Mutex<Network>>);
+
+    impl MockNetwork {
+        fn lock(&self) -> std::sync::MutexGuard<'_, Network> {
+            self.0.lock().unwrap()
+        }
+
+        fn add_node(&self, reply: ArtPollReply, answers_broadcast: bool) {
+            self.lock().nodes.push(MockNode {
+                reply,
+                answers_broadcast,
+            });
+        }
+
+        fn sent(&self) -> Vec<(SocketAddrV4, Vec<u8>)> {
+            self.lock().sent.clone()
+        }
+
+        fn opened(&self) -> Vec<SocketAddrV4> {
+            self.lock().opened.clone()
+        }
+
+        fn opener(
+            &self,
+        ) -> impl Fn(SocketAddrV4) -> io::Result<Arc<dyn UdpTransport>> + Send + Sync + 'static
+        {
+            let network = self.clone();
+            move |addr| {
+                network.lock().opened.push(addr);
+                Ok(Arc::new(MockTransport {
+                    network: network.clone(),
+                    inbox: Mutex::new(VecDeque::new()),
+                }))
+            }
+        }
+    }
+
+    /// A transport on a MockNetwork. Nodes answer polls into its inbox.
+    struct MockTransport {
+        network: MockNetwork,
+        inbox: Mutex<VecDeque<(Vec<u8>, SocketAddr)>>,
+    }
+
+    impl UdpTransport for MockTransport {
+        fn send_to(&self, packet: &[u8], dest: SocketAddrV4) -> io::Result<()> {
+            let mut network = self.network.lock();
+            if network.unreachable.contains(dest.ip()) {
+                return Err(io::Error::other("no route to host"));
+            }
+            network.sent.push((dest, packet.to_vec()));
+            if decode_poll(packet).is_none() {
+                return Ok(());
+            }
+            for node in &network.nodes {
+                let ip = Ipv4Addr::from(node.reply.ip);
+                if *dest.ip() == ip || (dest.ip().is_broadcast() && node.answers_broadcast) {
+                    let mut buf = [0; POLL_REPLY_SIZE];
+                    let len = encode_poll_reply(&node.reply, &mut buf).unwrap();
+                    let from = SocketAddr::from((ip, ARTNET_PORT));
+                    self.inbox
+                        .lock()
+                        .unwrap()
+                        .push_back((buf[..len].to_vec(), from));
+                }
+            }
+            Ok(())
+        }
+
+        fn recv_from(
+            &self,
+            buf: &mut [u8],
+            _timeout: Duration,
+        ) -> io::Result<Option<(usize, SocketAddr)>> {
+            Ok(self
+                .inbox
+                .lock()
+                .unwrap()
+                .pop_front()
+                .map(|(packet, from)| {
+                    buf[..packet.len()].copy_from_slice(&packet);
+                    (packet.len(), from)
+                }))
+        }
+    }
+
+    /// A reply from a node at 10.0.0.ip with one output for each universe.
+    fn node_reply(ip: u8, name: &str, universes: &[u8]) -> ArtPollReply {
+        let mut reply = ArtPollReply {
+            ip: [10, 0, 0, ip],
+            short_name: name.to_string(),
+            long_name: name.to_string(),
+            net_switch: 0,
+            sub_switch: 0,
+            num_ports: universes.len() as u8,
+            port_types: [0; 4],
+            sw_out: [0; 4],
+            mac: [0; 6],
+            bind_index: 1,
+        };
+        for (i, &universe) in universes.iter().enumerate() {
+            reply.port_types[i] = PORT_TYPE_OUTPUT;
+            reply.sw_out[i] = universe;
+        }
+        reply
+    }
+
+    #[test]
+    fn test_discovers_scripted_nodes() -> anyhow::Result<()> {
+        let network = MockNetwork::default();
+        network.add_node(node_reply(2, "left", &[1, 2]), true);
+        network.add_node(node_reply(1, "right", &[3]), true);
+        let ports = ArtnetDiscovery::new()
+            .with_transport(network.opener())
+            .run(Duration::from_secs(1))?;
+        let names: Vec<_> = ports.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "Art-Net right (10.0.0.1) port address 0:0:3",
+                "Art-Net left (10.0.0.2) port address 0:0:1",
+                "Art-Net left (10.0.0.2) port address 0:0:2",
+            ],
+            names
+        );
+        let mut poll = [0; POLL_PACKET_SIZE];
+        let len = encode_poll(0, &mut poll).unwrap();
+        let broadcast = SocketAddrV4::new(Ipv4Addr::BROADCAST, ARTNET_PORT);
+        assert_eq!(vec![(broadcast, poll[..len].to_vec())], network.sent());
+        assert_eq!(
+            vec![SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, ARTNET_PORT)],
+            network.opened()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sweeps_for_silent_nodes() -> anyhow::Result<()> {
+        let network = MockNetwork::default();
+        network.add_node(node_reply(1, "loud", &[1]), true);
+        network.add_node(node_reply(5, "quiet", &[2]), false);
+        network.add_node(node_reply(9, "far", &[3]), false);
+        // No route to one address in the sweep doesn't stop the others.
+        network.lock().unreachable.push(Ipv4Addr::new(10, 0, 0, 4));
+        let ports = ArtnetDiscovery::new()
+            .sweep(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 6))
+            .with_transport(network.opener())
+            .run(Duration::from_secs(1))?;
+        let names: Vec<_> = ports.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "Art-Net loud (10.0.0.1) port address 0:0:1",
+                "Art-Net quiet (10.0.0.5) port address 0:0:2",
+            ],
+            names
+        );
+        let polled: Vec<_> = network.sent().iter().map(|(dest, _)| *dest.ip()).collect();
+        let swept = [1, 2, 3, 5, 6].map(|ip| Ipv4Addr::new(10, 0, 0, ip));
+        assert_eq!([&[Ipv4Addr::BROADCAST][..], &swept].concat(), polled);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sweeps_subnet_hosts() {
+        let sweep = |addr: [u8; 4], prefix_len| {
+            ArtnetDiscovery::new()
+                .sweep_subnet(addr.into(), prefix_len)
+                .sweep
+                .unwrap()
+        };
+        let range = |first: [u8; 4], last: [u8; 4]| (first.into(), last.into());
+        assert_eq!(
+            range([10, 1, 0, 1], [10, 1, 0, 254]),
+            sweep([10, 1, 0, 77], 24)
+        );
+        assert_eq!(
+            range([10, 1, 0, 6], [10, 1, 0, 7]),
+            sweep([10, 1, 0, 7], 31)
+        );
+        assert_eq!(
+            range([10, 1, 0, 7], [10, 1, 0, 7]),
+            sweep([10, 1, 0, 7], 32)
+        );
+        assert_eq!(
+            range([0, 0, 0, 1], [255, 255, 255, 254]),
+            sweep([10, 1, 0, 7], 0)
+        );
+    }
+
+    #[test]
+    fn test_writes_through_transport() -> anyhow::Result<()> {
+        let network = MockNetwork::default();
+        network.add_node(node_reply(1, "node", &[1]), true);
+        let mut port = ArtnetDiscovery::new()
+            .on_interface(Ipv4Addr::new(10, 0, 0, 100))
+            .with_transport(network.opener())
+            .run(Duration::from_secs(1))?
+            .remove(0);
+        port.open()?;
+        port.write(&[1, 2, 3])?;
+        port.sync()?;
+
+        let node = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), ARTNET_PORT);
+        let mut dmx = [0; MAX_DMX_PACKET_SIZE];
+        let len = encode_dmx(1, 0, 1, &[1, 2, 3], &mut dmx).unwrap();
+        let mut sync = [0; SYNC_PACKET_SIZE];
+        let sync_len = encode_sync(&mut sync).unwrap();
+        assert_eq!(
+            vec![
+                (node, dmx[..len].to_vec()),
+                (node, sync[..sync_len].to_vec())
+            ],
+            network.sent()[1..]
+        );
+        assert_eq!(
+            SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 100), 0),
+            network.opened()[1]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_receives_addressed_packets() -> Result<(), Box<dyn std::error::Error>> {
+        // Use a non-standard port to stay out of the way of other Art-Net software.
+        let udp_port = ARTNET_PORT + 1;
+        let mut input =
+            ArtnetInputPort::new(vec![PortAddress::new(0, 0, 3)?]).with_udp_port(udp_port);
+        DmxInputPort::open(&mut input)?;
+        for (universe, level) in [(2, 1), (3, 2)] {
+            let port_address = PortAddress::new(0, 0, universe)?;
+            let mut output = ArtnetDmxPort::new(Ipv4Addr::LOCALHOST, port_address)
+                .with_interface(Ipv4Addr::LOCALHOST)
+                .with_own_socket()
+                .with_udp_port(udp_port);
+            DmxPort::open(&mut output)?;
+            output.write(&[level; 2])?;
+        }
+        let frame = input.read(Duration::from_secs(1))?.unwrap();
+        assert_eq!((3, vec![2, 2]), (frame.universe, frame.levels));
+        assert_eq!(Some("127.0.0.1".to_string()), frame.source);
+
+        // Polls are answered while reading.
+        let controller = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let mut buf = [0; RECEIVE_BUFFER_SIZE];
+        let len = encode_poll(0, &mut buf).unwrap();
+        controller.send_to(&buf[..len], (Ipv4Addr::LOCALHOST, udp_port))?;
+        assert!(input.read(Duration::from_millis(100))?.is_none());
+        let len = controller.recv(&mut buf)?;
+        let reply = decode_poll_reply(&buf[..len]).unwrap();
+        assert_eq!("rust-dmx", reply.short_name);
+        assert_eq!(vec![(0, 3)], reply.outputs().collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_alive() -> Result<(), Box<dyn std::error::Error>> {
+        let udp_port = ARTNET_PORT + 2;
+        let port_address = PortAddress::default();
+        let mut input = ArtnetInputPort::new(vec![port_address]).with_udp_port(udp_port);
+        DmxInputPort::open(&mut input)?;
+        let mut output = ArtnetDmxPort::new(Ipv4Addr::LOCALHOST, port_address)
+            .with_udp_port(udp_port)
+            .with_keep_alive(Duration::from_millis(20));
+        DmxPort::open(&mut output)?;
+        output.write(&[7; 2])?;
+        for _ in 0..2 {
+            let frame = input.read(Duration::from_secs(1))?.unwrap();
+            assert_eq!(vec![7, 7], frame.levels);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequence_skips_zero() {
+        let mut port = ArtnetDmxPort::new(Ipv4Addr::LOCALHOST, PortAddress::default());
+        port.sequence = 254;
+        assert_eq!([255, 1], [port.next_sequence(), port.next_sequence()]);
+        assert_eq!(0, port.without_sequence().next_sequence());
+    }
+
+    #[test]
+    fn test_rebinds_and_follows_moved_node() -> anyhow::Result<()> {
+        let network = MockNetwork::default();
+        network.add_node(node_reply(1, "node", &[1]), true);
+        let clock = Arc::new(ManualClock::new());
+        let mut port = ArtnetDiscovery::new()
+            .with_transport(network.opener())
+            .run(Duration::from_secs(1))?
+            .remove(0)
+            .with_clock(clock.clone());
+        port.open()?;
+        port.write(&[1])?;
+
+        // The network changes, and the node comes back at another address.
+        {
+            let mut network = network.lock();
+            network.unreachable.push(Ipv4Addr::new(10, 0, 0, 1));
+            network.nodes[0].reply.ip = [10, 0, 0, 7];
+        }
+        for _ in 1..FAILURES_BEFORE_REBIND {
+            assert!(port.write(&[2]).is_err());
+        }
+        let opened = network.opened().len();
+        assert!(port.write(&[2]).is_err());
+        // Rebinding polls for the node and opens a new socket.
+        assert_eq!(opened + 2, network.opened().len());
+
+        port.write(&[3, 4])?;
+        let (dest, packet) = network.sent().pop().unwrap();
+        assert_eq!(
+            SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 7), ARTNET_PORT),
+            dest
+        );
+        assert_eq!(Some(&[3, 4][..]), decode_dmx(&packet).map(|dmx| dmx.levels));
+        assert_eq!(
+            "Art-Net node (10.0.0.7) port address 0:0:1",
+            port.to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_follows_interface_changes() -> anyhow::Result<()> {
+        let network = MockNetwork::default();
+        network.add_node(node_reply(1, "node", &[1]), true);
+        let interfaces = Arc::new(Mutex::new(vec![Ipv4Addr::new(10, 0, 0, 100)]));
+        let discovery = ArtnetDiscovery {
+            interfaces: Some(InterfaceLister({
+                let interfaces = interfaces.clone();
+                Arc::new(move || Ok(interfaces.lock().unwrap().clone()))
+            })),
+            // Keep other tests' ports from seeing this test's changes.
+            interface_changes: Default::default(),
+            ..ArtnetDiscovery::new().with_transport(network.opener())
+        };
+        let clock = Arc::new(ManualClock::new());
+        let (_watcher, events) = discovery.watch_with_clock(Duration::from_secs(60), clock.clone());
+        let ArtnetEvent::Appeared(mut port) = events.recv_timeout(Duration::from_secs(1))? else {
+            panic!("expected the node to appear");
+        };
+        port.open()?;
+        port.write(&[1, 2])?;
+
+        // The host moves to another network, where the node has another address.
+        *interfaces.lock().unwrap() = vec![Ipv4Addr::new(10, 0, 1, 100)];
+        network.lock().nodes[0].reply.ip = [10, 0, 0, 7];
+        assert_eq!(
+            clock.now() + INTERFACE_CHECK_INTERVAL,
+            clock.wait_for_deadline()
+        );
+        clock.advance(INTERFACE_CHECK_INTERVAL);
+        match events.recv_timeout(Duration::from_secs(1))? {
+            ArtnetEvent::InterfacesChanged { added, removed } => {
+                assert_eq!(vec![Ipv4Addr::new(10, 0, 1, 100)], added);
+                assert_eq!(vec![Ipv4Addr::new(10, 0, 0, 100)], removed);
+            }
+            event => panic!("unexpected {event:?}"),
+        }
+        // The watcher polls at once rather than waiting out the interval.
+        match events.recv_timeout(Duration::from_secs(1))? {
+            ArtnetEvent::Appeared(port) => assert_eq!(Ipv4Addr::new(10, 0, 0, 7), port.addr),
+            event => panic!("unexpected {event:?}"),
+        }
+        let opened = network.opened().len();
+
+        // The open port rebinds, and follows the node, on its next write.
+        port.write(&[3, 4])?;
+        assert_eq!(opened + 2, network.opened().len());
+        let (dest, _) = network.sent().pop().unwrap();
+        assert_eq!(
+            SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 7), ARTNET_PORT),
+            dest
+        );
+        port.write(&[5, 6])?;
+        assert_eq!(opened + 2, network.opened().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebinds_at_most_once_per_interval() -> anyhow::Result<()> {
+        let network = MockNetwork::default();
+        let clock = Arc::new(ManualClock::new());
+        let node = Ipv4Addr::new(10, 0, 0, 1);
+        let mut port = ArtnetDmxPort::new(node, PortAddress::default())
+            .with_transport(network.opener())
+            .with_clock(clock.clone());
+        port.open()?;
+        network.lock().unreachable.push(node);
+        for _ in 0..2 * FAILURES_BEFORE_REBIND {
+            assert!(port.write(&[1]).is_err());
+        }
+        assert_eq!(2, network.opened().len());
+
+        clock.advance(REBIND_INTERVAL);
+        for _ in 0..FAILURES_BEFORE_REBIND {
+            assert!(port.write(&[1]).is_err());
+        }
+        assert_eq!(3, network.opened().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_address_node_uses_port_settings() -> Result<(), Box<dyn std::error::Error>> {
+        let node = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+        node.set_read_timeout(Some(Duration::from_secs(1)))?;
+        let port = ArtnetDmxPort::new(Ipv4Addr::LOCALHOST, PortAddress::default())
+            .with_interface(Ipv4Addr::LOCALHOST)
+            .with_udp_port(node.local_addr()?.port());
+        let address = ArtAddress {
+            short_name: Some("stage left".to_string()),
+            ..Default::default()
+        };
+        port.address_node(&address)?;
+
+        let mut expected = [0; ADDRESS_PACKET_SIZE];
+        let len = encode_address(&address, &mut expected).unwrap();
+        let mut buf = [0; RECEIVE_BUFFER_SIZE];
+        let (received, from) = node.recv_from(&mut buf)?;
+        assert_eq!(&expected[..len], &buf[..received]);
+        assert_eq!(Ipv4Addr::LOCALHOST, from.ip());
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_fps() -> Result<(), Box<dyn std::error::Error>> {
+        let port = ArtnetDmxPort::new(Ipv4Addr::LOCALHOST, PortAddress::default());
+        assert_eq!(Some(DMX_LINE_FPS), port.max_fps());
+        let port = port.with_max_fps(120.0);
+        let loaded: ArtnetDmxPort = serde_json::from_str(&serde_json::to_string(&port)?)?;
+        assert_eq!(Some(120.0), loaded.max_fps());
+        Ok(())
+    }
+
+    #[test]
+    fn test_port_address() -> anyhow::Result<()> {
+        let address: PortAddress = "1:2:3".parse()?;
+        assert_eq!(0x0123, u16::from(address));
+        assert_eq!(address, "291".parse()?);
+        assert_eq!("1:2:3", address.to_string());
+        assert!("0:16:0".parse::<PortAddress>().is_err());
+        assert!(PortAddress::try_from(0x8000).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tracks_outputs() {
+        let port = |ip: u8| ArtnetDmxPort::new(Ipv4Addr::new(10, 0, 0, ip), PortAddress::default());
+        let names = |events: Vec<ArtnetEvent>| -> Vec<String> {
+            events
+                .iter()
+                .map(|event| match event {
+                    ArtnetEvent::Appeared(port) => format!("+{}", port.addr),
+                    ArtnetEvent::Disappeared(port) => format!("-{}", port.addr),
+                    ArtnetEvent::InterfacesChanged { .. } => unreachable!(),
+                })
+                .collect()
+        };
+        let mut tracker = OutputTracker::default();
+        assert_eq!(
+            vec!["+10.0.0.1", "+10.0.0.2"],
+            names(tracker.update(vec![port(1), port(2)]))
+        );
+        for _ in 1..MISSED_POLLS_BEFORE_GONE {
+            assert!(tracker.update(vec![port(1)]).is_empty());
+        }
+        assert_eq!(vec!["-10.0.0.2"], names(tracker.update(vec![port(1)])));
+    }
+
+    #[test]
+    fn test_deduplicates_replies() {
+        let reply = |ip: u8, mac: u8, bind_index: u8| {
+            let reply = ArtPollReply {
+                ip: [10, 0, 0, ip],
+                short_name: String::new(),
+                long_name: String::new(),
+                net_switch: 0,
+                sub_switch: 0,
+                num_ports: 1,
+                port_types: [0x80, 0, 0, 0],
+                sw_out: [bind_index, 0, 0, 0],
+                mac: [0, 0, 0, 0, 0, mac],
+                bind_index,
+            };
+            (Ipv4Addr::new(10, 0, 0, ip), reply)
+        };
+        let mut replies = vec![
+            reply(2, 0, 1),
+            reply(1, 0, 2),
+            reply(1, 0, 1),
+            reply(2, 0, 1),
+        ];
+        // A node on two networks answers on both, from the address it can be reached at.
+        let (_, mut behind_nat) = reply(5, 9, 1);
+        behind_nat.ip = [192, 168, 0, 5];
+        replies.extend([(Ipv4Addr::new(10, 0, 0, 5), behind_nat), reply(6, 9, 1)]);
+        let ports: Vec<_> = ArtnetDmxPort::from_replies(&replies)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            vec![
+                "Art-Net 10.0.0.1 port address 0:0:1",
+                "Art-Net 10.0.0.1 port address 0:0:2",
+                "Art-Net 10.0.0.2 port address 0:0:1",
+                "Art-Net 10.0.0.5 port address 0:0:1",
+            ],
+            ports
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! An injectable source of time, so timing behavior can be tested deterministically.
+use anyhow::{anyhow, bail};
+use serde::{de, Deserialize, Deserializer};
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time for code that schedules or times out.
+pub trait Clock: Send + Sync {
+    /// Return the current time.
+    fn now(&self) -> Instant;
+
+    /// Block the calling thread until duration has passed on this clock.
+    fn sleep(&self, duration: Duration);
+
+    /// Block the calling thread until deadline on this clock, or until wakeup
+    /// is woken if that happens first. Wait only for wakeup if deadline is
+    /// None.
+    fn wait_until(&self, deadline: Option<Instant>, wakeup: &Wakeup) {
+        let (woken, changed) = &*wakeup.0;
+        let mut woken = woken.lock().unwrap();
+        while !*woken {
+            match deadline {
+                None => woken = changed.wait(woken).unwrap(),
+                Some(deadline) => {
+                    let now = self.now();
+                    if now >= deadline {
+                        break;
+                    }
+                    woken = changed.wait_timeout(woken, deadline - now).unwrap().0;
+                }
+            }
+        }
+        *woken = false;
+    }
+}
+
+/// Wakes a thread waiting in `Clock::wait_until` early.
+///
+/// A wake that arrives while nobody is waiting isn't lost: the next wait
+/// returns immediately, as a thread checks its state before waiting.
+#[derive(Debug, Clone, Default)]
+pub struct Wakeup(Arc<(Mutex<bool>, Condvar)>);
+
+impl Wakeup {
+    /// Create a wakeup that hasn't been woken.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wake the thread waiting on this, or the next one to wait.
+    pub fn wake(&self) {
+        *self.0 .0.lock().unwrap() = true;
+        self.0 .1.notify_all();
+    }
+}
+
+impl fmt::Debug for dyn Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Clock")
+    }
+}
+
+/// The real clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Return a shared handle to the real clock.
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Return the time between frames written at fps frames per second. Fail
+/// unless fps is positive and slow enough to have an interval.
+pub(crate) fn frame_interval(fps: f64) -> anyhow::Result<Duration> {
+    if !(fps.is_finite() && fps > 0.0) {
+        bail!("{fps} is not a valid frame rate");
+    }
+    Duration::try_from_secs_f64(1.0 / fps).map_err(|_| anyhow!("{fps} fps is too slow"))
+}
+
+/// Load a frame rate, rejecting any that `frame_interval` would.
+pub(crate) fn deserialize_fps<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let fps = f64::deserialize(deserializer)?;
+    frame_interval(fps).map_err(de::Error::custom)?;
+    Ok(fps)
+}
+
+/// A clock that only moves when it is advanced, for tests.
+///
+/// Threads sleeping on this clock wake up once another thread advances it past
+/// the end of their sleep.
+pub struct ManualClock {
+    now: Mutex<Instant>,
+    /// The deadline and wakeup of every thread waiting on this clock.
+    waiting: Mutex<Vec<(Option<Instant>, Wakeup)>>,
+    waiting_changed: Condvar,
+}
+
+impl ManualClock {
+    /// Create a clock stopped at the current time.
+    pub fn new() -> Self {
+        Self {
+            now: Mutex::new(Instant::now()),
+            waiting: Mutex::new(Vec::new()),
+            waiting_changed: Condvar::new(),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+        for (_, wakeup) in self.waiting.lock().unwrap().iter() {
+            // Take the lock a waiter holds between checking the time and
+            // waiting, so it can't miss this notification.
+            let (woken, changed) = &*wakeup.0;
+            let _woken = woken.lock().unwrap();
+            changed.notify_all();
+        }
+    }
+
+    /// Block until some thread is waiting on this clock for a deadline that
+    /// hasn't passed, and return the earliest such deadline. Tests use this
+    /// to know that a thread has scheduled its next step before advancing
+    /// the clock.
+    pub fn wait_for_deadline(&self) -> Instant {
+        let upcoming = |waiting: &[(Option<Instant>, Wakeup)]| {
+            let now = self.now();
+            waiting
+                .iter()
+                .filter_map(|(deadline, _)| *deadline)
+                .filter(|deadline| *deadline > now)
+                .min()
+        };
+        let waiting = self.waiting.lock().unwrap();
+        let waiting = self
+            .waiting_changed
+            .wait_while(waiting, |waiting| upcoming(waiting).is_none())
+            .unwrap();
+        upcoming(&waiting).expect("a thread is waiting for a deadline")
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.wait_until(Some(self.now() + duration), &Wakeup::new());
+    }
+
+    fn wait_until(&self, deadline: Option<Instant>, wakeup: &Wakeup) {
+        self.waiting
+            .lock()
+            .unwrap()
+            .push((deadline, wakeup.clone()));
+        self.waiting_changed.notify_all();
+        let (woken, changed) = &*wakeup.0;
+        let mut woken = woken.lock().unwrap();
+        while !*woken && deadline.is_none_or(|deadline| self.now() < deadline) {
+            woken = changed.wait(woken).unwrap();
+        }
+        *woken = false;
+        drop(woken);
+        let mut waiting = self.waiting.lock().unwrap();
+        if let Some(i) = waiting
+            .iter()
+            .position(|(_, waiter)| Arc::ptr_eq(&waiter.0, &wakeup.0))
+        {
+            waiting.remove(i);
+        }
+        self.waiting_changed.notify_all();
+    }
+}
@@ -0,0 +1,108 @@
+//! Versioned serialization of ports, so saved configs survive crate upgrades.
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::DmxPort;
+
+/// The current version of the serialized port schema.
+///
+/// Bump this when a change to a port's serialized form needs more than serde
+/// defaults to load old configs, and handle the old version in that port's
+/// `DmxPort::migrate`.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// A port serialized along with the schema version it was written with.
+///
+/// Save ports wrapped in this type. It also loads bare ports saved before
+/// configs were versioned, treating them as version 0.
+#[derive(Serialize)]
+pub struct VersionedPort {
+    version: u32,
+    port: Box<dyn DmxPort>,
+}
+
+impl VersionedPort {
+    /// Wrap a port for saving at the current schema version.
+    pub fn new(port: Box<dyn DmxPort>) -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            port,
+        }
+    }
+
+    /// Return the schema version this port was written with.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Return the port, migrated to the current schema version.
+    pub fn into_port(self) -> Box<dyn DmxPort> {
+        let mut port = self.port;
+        if self.version < CONFIG_VERSION {
+            port.migrate(self.version);
+        }
+        port
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionedPort {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u32,
+            port: Box<dyn DmxPort>,
+        }
+        // Anything with a version is a versioned config, so its own error is
+        // reported rather than a guess at which form it was meant to be.
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let (version, port) = if value.get("version").is_some() {
+            let Versioned { version, port } =
+                Versioned::deserialize(value).map_err(de::Error::custom)?;
+            (version, port)
+        } else {
+            let port = Box::<dyn DmxPort>::deserialize(value).map_err(de::Error::custom)?;
+            (0, port)
+        };
+        if version > CONFIG_VERSION {
+            return Err(de::Error::custom(format!(
+                "port config version {version} was written by a newer version of this crate \
+                 (this version supports up to {CONFIG_VERSION})"
+            )));
+        }
+        Ok(Self { version, port })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+
+    #[test]
+    fn test_loads_versioned_and_legacy_configs() -> Result<(), serde_json::Error> {
+        let saved = serde_json::to_string(&VersionedPort::new(Box::new(TestPort::named("a"))))?;
+        assert_eq!(
+            r#"{"version":1,"port":{"type":"TestPort","name":"a"}}"#,
+            saved
+        );
+        let loaded: VersionedPort = serde_json::from_str(&saved)?;
+        assert_eq!(CONFIG_VERSION, loaded.version());
+        assert_eq!("a", loaded.into_port().to_string());
+
+        // A bare port saved before configs were versioned.
+        let port: Box<dyn DmxPort> = Box::new(TestPort::named("b"));
+        let legacy: VersionedPort = serde_json::from_str(&serde_json::to_string(&port)?)?;
+        assert_eq!(0, legacy.version());
+        assert_eq!("b", legacy.into_port().to_string());
+
+        let newer = r#"{"version":2,"port":{"type":"TestPort","name":"c"}}"#;
+        assert!(serde_json::from_str::<VersionedPort>(newer).is_err());
+
+        // A broken port inside a versioned config reports what is wrong with it.
+        let broken = r#"{"version":1,"port":{"type":"NoSuchPort"}}"#;
+        let Err(err) = serde_json::from_str::<VersionedPort>(broken) else {
+            panic!("loaded a config with an unknown port type");
+        };
+        assert!(err.to_string().contains("NoSuchPort"), "{err}");
+        Ok(())
+    }
+}
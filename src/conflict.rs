@@ -0,0 +1,208 @@
+//! Warn when other sources send to the universes this process is sourcing.
+use log::warn;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+use crate::net::interface_addresses;
+use crate::{DmxInputPort, InputFrame, ReadError};
+
+/// How long a conflicting source may go quiet before its conflict is over.
+/// This matches the time sACN receivers wait before dropping a source.
+pub const CONFLICT_TIMEOUT: Duration = Duration::from_millis(2500);
+
+/// Another source sending to a universe.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Conflict {
+    /// The universe, as reported by the input port.
+    pub universe: u16,
+    /// The source, as reported by the input port.
+    pub source: String,
+}
+
+/// A change in the conflicts seen by a `ConflictListener`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConflictEvent {
+    /// A source started sending to a universe, or came back after going quiet.
+    Started(Conflict),
+    /// A source hasn't sent to a universe for CONFLICT_TIMEOUT.
+    Ended(Conflict),
+}
+
+/// Watch an input port for sources other than this process.
+///
+/// The input should receive the universes this process sends: an
+/// `ArtnetInputPort` with the output's port addresses, or a `SacnInputPort`
+/// in `SacnMergeMode::PerSource` mode. Frames that don't name their source,
+/// such as merged sACN frames, are skipped. This process's own packets come
+/// back through the input too, so its sources must be ignored: sACN outputs
+/// by their `SacnDmxPort::source_id`, Art-Net outputs by this host's
+/// addresses with `ignoring_host`. Ignoring the host also hides other
+/// programs on it, since Art-Net packets carry nothing else to tell them apart.
+pub struct ConflictListener {
+    port: Box<dyn DmxInputPort>,
+    ignored: BTreeSet<String>,
+    last_seen: BTreeMap<Conflict, Instant>,
+}
+
+impl ConflictListener {
+    /// Listen on an open input port.
+    pub fn new(port: Box<dyn DmxInputPort>) -> Self {
+        Self {
+            port,
+            ignored: BTreeSet::new(),
+            last_seen: BTreeMap::new(),
+        }
+    }
+
+    /// Don't report frames from source.
+    pub fn ignoring(mut self, source: impl Into<String>) -> Self {
+        self.ignored.insert(source.into());
+        self
+    }
+
+    /// Don't report frames sent from any of this host's interface addresses.
+    pub fn ignoring_host(mut self) -> Self {
+        match interface_addresses() {
+            Ok(addrs) => self
+                .ignored
+                .extend(addrs.into_iter().map(|addr| addr.to_string())),
+            Err(err) => warn!("Failed to list network interfaces: {err}."),
+        }
+        self
+    }
+
+    /// Return the conflicts that are currently going on.
+    pub fn conflicts(&self) -> impl Iterator<Item = &Conflict> {
+        self.last_seen.keys()
+    }
+
+    /// Wait up to timeout for a conflict to start, and return what changed.
+    /// Each conflict that starts is also logged as a warning.
+    pub fn poll(&mut self, timeout: Duration) -> Result<Vec<ConflictEvent>, ReadError> {
+        let deadline = Instant::now() + timeout;
+        let mut events = Vec::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(frame) = self.port.read(remaining)? else {
+                break;
+            };
+            if let Some(event) = self.update(frame, Instant::now()) {
+                events.push(event);
+                break;
+            }
+        }
+        events.extend(self.expire(Instant::now()));
+        Ok(events)
+    }
+
+    /// Record a received frame, and return the event if it starts a conflict.
+    fn update(&mut self, frame: InputFrame, now: Instant) -> Option<ConflictEvent> {
+        let source = frame.source?;
+        if self.ignored.contains(&source) {
+            return None;
+        }
+        let conflict = Conflict {
+            universe: frame.universe,
+            source,
+        };
+        if self.last_seen.insert(conflict.clone(), now).is_some() {
+            return None;
+        }
+        warn!(
+            "{} is also sending universe {} on {}.",
+            conflict.source, conflict.universe, self.port
+        );
+        Some(ConflictEvent::Started(conflict))
+    }
+
+    /// Forget the sources that have gone quiet, and return their events.
+    fn expire(&mut self, now: Instant) -> Vec<ConflictEvent> {
+        let mut events = Vec::new();
+        self.last_seen.retain(|conflict, last_seen| {
+            if now - *last_seen < CONFLICT_TIMEOUT {
+                return true;
+            }
+            events.push(ConflictEvent::Ended(conflict.clone()));
+            false
+        });
+        events
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DmxPort, SacnDmxPort, SacnInputPort, SacnMergeMode};
+    use std::net::Ipv4Addr;
+
+    fn frame(universe: u16, source: Option<&str>) -> InputFrame {
+        InputFrame {
+            universe,
+            source: source.map(str::to_string),
+            levels: vec![0; 2],
+        }
+    }
+
+    fn conflict(universe: u16, source: &str) -> Conflict {
+        Conflict {
+            universe,
+            source: source.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tracks_conflicts() {
+        let mut listener = ConflictListener::new(Box::new(SacnInputPort::new(
+            vec![1],
+            SacnMergeMode::PerSource,
+        )))
+        .ignoring("us");
+        let start = Instant::now();
+        assert_eq!(None, listener.update(frame(1, Some("us")), start));
+        assert_eq!(None, listener.update(frame(1, None), start));
+        assert_eq!(
+            Some(ConflictEvent::Started(conflict(1, "them"))),
+            listener.update(frame(1, Some("them")), start)
+        );
+        // The same source on another universe is another conflict.
+        assert!(listener.update(frame(2, Some("them")), start).is_some());
+        let later = start + CONFLICT_TIMEOUT / 2;
+        assert_eq!(None, listener.update(frame(1, Some("them")), later));
+
+        assert_eq!(
+            vec![ConflictEvent::Ended(conflict(2, "them"))],
+            listener.expire(start + CONFLICT_TIMEOUT)
+        );
+        assert_eq!(
+            vec![&conflict(1, "them")],
+            listener.conflicts().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_hears_other_sources() -> Result<(), Box<dyn std::error::Error>> {
+        // Use a non-standard port to stay out of the way of other sACN software.
+        let udp_port = rust_dmx_core::sacn::SACN_PORT + 3;
+        let mut input =
+            SacnInputPort::new(vec![1], SacnMergeMode::PerSource).with_udp_port(udp_port);
+        DmxInputPort::open(&mut input)?;
+        let mut ours = SacnDmxPort::new(1)
+            .with_unicast(vec![Ipv4Addr::LOCALHOST])
+            .with_udp_port(udp_port);
+        let mut theirs = SacnDmxPort::new(1)
+            .with_unicast(vec![Ipv4Addr::LOCALHOST])
+            .with_udp_port(udp_port);
+        let mut listener = ConflictListener::new(Box::new(input)).ignoring(ours.source_id());
+        DmxPort::open(&mut ours)?;
+        ours.write(&[1; 2])?;
+        assert!(listener.poll(Duration::from_millis(100))?.is_empty());
+
+        DmxPort::open(&mut theirs)?;
+        theirs.write(&[2; 2])?;
+        assert_eq!(
+            vec![ConflictEvent::Started(conflict(1, &theirs.source_id()))],
+            listener.poll(Duration::from_secs(1))?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! A headless output daemon that exposes ports over a minimal HTTP API.
+//!
+//! Endpoints:
+//! - `GET /ports` lists the ports as a JSON array of names, in universe order.
+//! - `PUT /universe/{n}` sets the levels of universe n from a body of up to 512 bytes.
+//! - `POST /blackout` sets every universe to zero.
+//!
+//! Each port is driven by a `BackgroundSender`, so the last levels set are
+//! continuously refreshed without clients needing to stream frames. The ports
+//! can be handed over already open, or loaded from a saved `PortConfig`.
+use anyhow::anyhow;
+use log::{debug, info, warn};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::http::{read_head, HeadError};
+use crate::{BackgroundSender, DmxPort, PortConfig, SenderConfig, DMX_UNIVERSE_SIZE};
+
+/// Give up on a client that stalls for this long.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serve DMX output for a collection of ports over HTTP.
+pub struct Daemon {
+    universes: Vec<Universe>,
+}
+
+struct Universe {
+    name: String,
+    sender: BackgroundSender,
+}
+
+impl Daemon {
+    /// Create a daemon driving the provided ports, which should already be open.
+    /// Universe numbers are indices into this list.
+    pub fn new(ports: Vec<Box<dyn DmxPort>>, config: SenderConfig) -> Self {
+        let universes = ports
+            .into_iter()
+            .map(|port| Universe {
+                name: port.to_string(),
+                sender: BackgroundSender::spawn(port, config.clone()),
+            })
+            .collect();
+        Self { universes }
+    }
+
+    /// Create a daemon driving the ports of a `PortConfig` saved as JSON at
+    /// path. Universe numbers follow the order of the config's names, which
+    /// are what `GET /ports` lists. Each port is opened, and driven at the
+    /// rate `SenderConfig::for_port` picks for it. A port that fails to open
+    /// is logged and left for its sender to reopen.
+    pub fn from_config_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| anyhow!("failed to open port config {}: {err}", path.display()))?;
+        let config: PortConfig = serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| anyhow!("failed to load port config {}: {err}", path.display()))?;
+        let universes = config
+            .into_iter()
+            .map(|(name, mut port)| {
+                if let Err(err) = port.open() {
+                    warn!("Failed to open DMX port {name} ({port}): {err}.");
+                }
+                let config = SenderConfig::for_port(&*port);
+                Universe {
+                    name,
+                    sender: BackgroundSender::spawn(port, config),
+                }
+            })
+            .collect();
+        Ok(Self { universes })
+    }
+
+    /// Listen on the provided address and serve requests until an error occurs.
+    /// Requests are handled one at a time.
+    pub fn serve<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        info!("DMX daemon listening on {}.", listener.local_addr()?);
+        for stream in listener.incoming() {
+            if let Err(err) = self.handle_connection(stream?) {
+                debug!("Error handling DMX daemon request: {}.", err);
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let response = match read_request(&mut reader)? {
+            Ok(request) => self.route(&request.method, &request.path, &request.body),
+            Err(response) => response,
+        };
+        response.write_into(&stream)
+    }
+
+    /// Produce a response for a single request.
+    fn route(&self, method: &str, path: &str, body: &[u8]) -> Response {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            ("GET", ["ports"]) => Response::json(self.ports_json()),
+            ("PUT", ["universe", index]) => {
+                let Some(universe) = index
+                    .parse()
+                    .ok()
+                    .and_then(|i: usize| self.universes.get(i))
+                else {
+                    return Response::text(404, "no such universe");
+                };
+                if body.len() > DMX_UNIVERSE_SIZE {
+                    return Response::text(413, "frame is larger than a DMX universe");
+                }
+                match universe.sender.send(body) {
+                    Ok(()) => Response::empty(204),
+                    Err(err) => Response::text(503, &err.to_string()),
+                }
+            }
+            ("POST", ["blackout"]) => {
+                let mut failed = false;
+                for universe in &self.universes {
+                    failed |= universe.sender.send(&[0; DMX_UNIVERSE_SIZE]).is_err();
+                }
+                if failed {
+                    return Response::text(503, "some universes could not be blacked out");
+                }
+                Response::empty(204)
+            }
+            (_, ["ports"] | ["universe", _] | ["blackout"]) => {
+                Response::text(405, "method not allowed")
+            }
+            _ => Response::text(404, "not found"),
+        }
+    }
+
+    fn ports_json(&self) -> String {
+        let names: Vec<String> = self
+            .universes
+            .iter()
+            .map(|u| json_string(&u.name))
+            .collect();
+        format!("[{}]", names.join(","))
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// Read a single HTTP/1.x request.
+/// Malformed requests produce an error response rather than an io error.
+fn read_request<R: BufRead>(reader: &mut R) -> io::Result<Result<Request, Response>> {
+    let head = match read_head(reader) {
+        Ok(head) => head,
+        Err(HeadError::TooLarge) => {
+            return Ok(Err(Response::text(431, "request header too large")))
+        }
+        Err(HeadError::Malformed) => return Ok(Err(Response::text(400, "malformed request line"))),
+        Err(HeadError::Io(err)) => return Err(err),
+    };
+    let content_length = match head.header("content-length").map(str::parse) {
+        None => 0,
+        Some(Ok(len)) => len,
+        Some(Err(_)) => return Ok(Err(Response::text(400, "invalid content length"))),
+    };
+    if content_length > DMX_UNIVERSE_SIZE {
+        return Ok(Err(Response::text(
+            413,
+            "frame is larger than a DMX universe",
+        )));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Ok(Request {
+        method: head.method,
+        path: head.path,
+        body,
+    }))
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn empty(status: u16) -> Self {
+        Self::text(status, "")
+    }
+
+    fn text(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: body.to_string(),
+        }
+    }
+
+    fn json(body: String) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn write_into<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(
+            w,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason_phrase(self.status),
+            self.content_type,
+            self.body.len(),
+            self.body
+        )?;
+        w.flush()
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+/// Render a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::OfflineDmxPort;
+
+    fn daemon() -> Daemon {
+        Daemon::new(vec![Box::new(OfflineDmxPort)], SenderConfig::default())
+    }
+
+    #[test]
+    fn test_routes() {
+        let daemon = daemon();
+        assert_eq!(r#"["offline"]"#, daemon.route("GET", "/ports", &[]).body);
+        assert_eq!(204, daemon.route("PUT", "/universe/0", &[255; 512]).status);
+        assert_eq!(404, daemon.route("PUT", "/universe/1", &[255]).status);
+        assert_eq!(413, daemon.route("PUT", "/universe/0", &[0; 513]).status);
+        assert_eq!(405, daemon.route("GET", "/blackout", &[]).status);
+        assert_eq!(204, daemon.route("POST", "/blackout", &[]).status);
+    }
+
+    #[test]
+    fn test_writes_reason_phrases() -> io::Result<()> {
+        let mut written = Vec::new();
+        Response::text(503, "busy").write_into(&mut written)?;
+        assert!(written.starts_with(b"HTTP/1.1 503 Service Unavailable\r\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_loads_config_file() -> anyhow::Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("rust_dmx_daemon_{}.json", std::process::id()));
+        let mut config = PortConfig::new();
+        config.insert("stage".to_string(), Box::new(OfflineDmxPort));
+        config.insert("house".to_string(), Box::new(OfflineDmxPort));
+        std::fs::write(&path, serde_json::to_string(&config)?)?;
+        let daemon = Daemon::from_config_file(&path);
+        std::fs::remove_file(&path)?;
+        let daemon = daemon?;
+        assert_eq!(
+            r#"["house","stage"]"#,
+            daemon.route("GET", "/ports", &[]).body
+        );
+        assert_eq!(204, daemon.route("PUT", "/universe/1", &[255]).status);
+        assert!(Daemon::from_config_file(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = b"PUT /universe/0 HTTP/1.1\r\nContent-Length: 3\r\n\r\n\x01\x02\x03";
+        let request = read_request(&mut &raw[..]).unwrap().ok().unwrap();
+        assert_eq!("PUT", request.method);
+        assert_eq!("/universe/0", request.path);
+        assert_eq!(vec![1, 2, 3], request.body);
+    }
+}
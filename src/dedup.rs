@@ -0,0 +1,155 @@
+//! A port that skips writing frames that haven't changed.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{
+    system_clock, Clock, DmxPort, Frame, FrameSizeLimits, OpenError, PortListing, WriteError,
+};
+
+/// Skip writing a frame to the inner port when it is the same as the last one
+/// written, unless max_idle has passed since then.
+///
+/// This cuts network traffic dramatically for applications that send at a
+/// fixed rate but rarely change levels, such as Art-Net over Wi-Fi. The forced
+/// refresh after max_idle keeps receivers that time out on a quiet line alive.
+#[derive(Serialize, Deserialize)]
+pub struct DedupPort {
+    inner: Box<dyn DmxPort>,
+    /// Write an unchanged frame anyway once this long has passed.
+    max_idle: Duration,
+    /// The last frame written, and when.
+    #[serde(skip)]
+    last: Option<(Frame, Instant)>,
+    #[serde(skip)]
+    skipped: u64,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+impl DedupPort {
+    /// Wrap inner, skipping unchanged frames for up to max_idle.
+    pub fn new(inner: Box<dyn DmxPort>, max_idle: Duration) -> Self {
+        Self {
+            inner,
+            max_idle,
+            last: None,
+            skipped: 0,
+            clock: system_clock(),
+        }
+    }
+
+    /// Time the idle interval using clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Return the number of frames skipped because they hadn't changed.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Unwrap this port into the inner port.
+    pub fn into_inner(self) -> Box<dyn DmxPort> {
+        self.inner
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for DedupPort {
+    /// Dedup ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.inner.open()
+    }
+
+    /// Forget the last frame, so the first frame after reopening is written.
+    fn close(&mut self) {
+        self.last = None;
+        self.inner.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let now = self.clock.now();
+        if let Some((last, written)) = &self.last {
+            if **last == *frame && now - *written < self.max_idle {
+                self.skipped += 1;
+                return Ok(());
+            }
+        }
+        // Forget the last frame if this write fails, so the next one retries.
+        self.last = None;
+        self.inner.write(frame)?;
+        self.last = Some((frame.into(), now));
+        Ok(())
+    }
+
+    /// Always written, and forgotten, so the first frame after the blackout
+    /// is released goes out even if it matches the last one.
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.last = None;
+        self.inner.write_blackout(frame)
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.inner.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.inner.max_fps()
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.migrate(from_version);
+    }
+}
+
+impl fmt::Display for DedupPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (skipping unchanged frames)", self.inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+    use crate::ManualClock;
+
+    #[test]
+    fn test_skips_unchanged_frames_until_idle() {
+        let inner = TestPort::default();
+        let clock = Arc::new(ManualClock::new());
+        let mut port = DedupPort::new(Box::new(inner.clone()), Duration::from_secs(1))
+            .with_clock(clock.clone());
+
+        port.write(&[1, 2, 3]).unwrap();
+        port.write(&[1, 2, 3]).unwrap();
+        assert_eq!(1, inner.writes());
+        assert_eq!(1, port.skipped());
+
+        port.write(&[1, 2, 4]).unwrap();
+        assert_eq!(2, inner.writes());
+
+        clock.advance(Duration::from_secs(1));
+        port.write(&[1, 2, 4]).unwrap();
+        assert_eq!(3, inner.writes());
+    }
+}
@@ -0,0 +1,297 @@
+//! A port that writes every frame to two ports and reports when they disagree.
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError};
+
+/// Called with the Display form of the port that failed and its error when
+/// exactly one of the two ports fails a write.
+type OnDivergence = Box<dyn FnMut(&str, &WriteError) + Send>;
+
+/// Called with the frames the primary and secondary ports captured when both
+/// writes succeeded but what they sent differs.
+type OnMismatch = Box<dyn FnMut(&[u8], &[u8]) + Send>;
+
+/// Write each frame to a primary and a secondary port, and report any divergence
+/// in whether the writes succeeded or, when both ports can capture their output,
+/// in the levels they sent.
+///
+/// This is intended for migrating a rig between transports: run the old and new
+/// paths side by side and watch for disagreement. Each divergence is logged and
+/// counted in `divergences`; failures are passed to the `on_divergence`
+/// callback and mismatched levels to the `on_mismatch` one. Captured frames
+/// that differ only in trailing zeros match, since ports pad frames
+/// differently. The primary port's result is what `write` returns; the
+/// secondary never affects the caller.
+#[derive(Serialize, Deserialize)]
+pub struct DualWritePort {
+    primary: Box<dyn DmxPort>,
+    secondary: Box<dyn DmxPort>,
+    #[serde(skip)]
+    divergences: usize,
+    #[serde(skip)]
+    on_divergence: Option<OnDivergence>,
+    #[serde(skip)]
+    on_mismatch: Option<OnMismatch>,
+}
+
+/// Which side of a dual write failed when the other succeeded.
+#[derive(Debug, Clone, Copy)]
+enum Divergence {
+    PrimaryFailed,
+    SecondaryFailed,
+}
+
+impl DualWritePort {
+    /// Write to both primary and secondary, returning the primary's result.
+    pub fn new(primary: Box<dyn DmxPort>, secondary: Box<dyn DmxPort>) -> Self {
+        Self {
+            primary,
+            secondary,
+            divergences: 0,
+            on_divergence: None,
+            on_mismatch: None,
+        }
+    }
+
+    /// Call on_divergence with the Display form of the failed port and its
+    /// error whenever exactly one of the two ports fails a write.
+    pub fn on_divergence(
+        mut self,
+        on_divergence: impl FnMut(&str, &WriteError) + Send + 'static,
+    ) -> Self {
+        self.on_divergence = Some(Box::new(on_divergence));
+        self
+    }
+
+    /// Call on_mismatch with the frames the primary and secondary ports captured
+    /// whenever both writes succeed but the levels they sent differ.
+    pub fn on_mismatch(mut self, on_mismatch: impl FnMut(&[u8], &[u8]) + Send + 'static) -> Self {
+        self.on_mismatch = Some(Box::new(on_mismatch));
+        self
+    }
+
+    /// Return the number of writes where exactly one of the two ports failed,
+    /// or where the levels they sent differ.
+    pub fn divergences(&self) -> usize {
+        self.divergences
+    }
+
+    /// Unwrap this port into the primary and secondary ports.
+    pub fn into_parts(self) -> (Box<dyn DmxPort>, Box<dyn DmxPort>) {
+        (self.primary, self.secondary)
+    }
+
+    fn report(&mut self, divergence: Divergence, err: &WriteError) {
+        self.divergences += 1;
+        let (failed, ok) = match divergence {
+            Divergence::PrimaryFailed => (&self.primary, &self.secondary),
+            Divergence::SecondaryFailed => (&self.secondary, &self.primary),
+        };
+        warn!(
+            "Dual write diverged: {} failed ({}) while {} succeeded.",
+            failed, err, ok
+        );
+        if let Some(on_divergence) = &mut self.on_divergence {
+            on_divergence(&failed.to_string(), err);
+        }
+    }
+
+    /// Compare the frames both ports captured, if they both can.
+    fn compare_captured(&mut self) {
+        let (Some(primary), Some(secondary)) = (self.primary.captured(), self.secondary.captured())
+        else {
+            return;
+        };
+        let levels = |frame: &[u8]| -> usize {
+            frame
+                .iter()
+                .rposition(|&level| level != 0)
+                .map_or(0, |last| last + 1)
+        };
+        if primary[..levels(&primary)] == secondary[..levels(&secondary)] {
+            return;
+        }
+        self.divergences += 1;
+        match primary.iter().zip(&secondary).position(|(p, s)| p != s) {
+            Some(channel) => warn!(
+                "Dual write diverged: {} sent {} on channel {} while {} sent {}.",
+                self.primary,
+                primary[channel],
+                channel + 1,
+                self.secondary,
+                secondary[channel]
+            ),
+            None => warn!(
+                "Dual write diverged: {} sent {} channels while {} sent {}.",
+                self.primary,
+                primary.len(),
+                self.secondary,
+                secondary.len()
+            ),
+        }
+        if let Some(on_mismatch) = &mut self.on_mismatch {
+            on_mismatch(&primary, &secondary);
+        }
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for DualWritePort {
+    /// Dual-write ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        if let Err(err) = self.secondary.open() {
+            warn!("Failed to open secondary port {}: {}.", self.secondary, err);
+        }
+        self.primary.open()
+    }
+
+    fn close(&mut self) {
+        self.primary.close();
+        self.secondary.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let primary = self.primary.write(frame);
+        let secondary = self.secondary.write(frame);
+        match (&primary, &secondary) {
+            (Err(err), Ok(())) => self.report(Divergence::PrimaryFailed, err),
+            (Ok(()), Err(err)) => self.report(Divergence::SecondaryFailed, err),
+            (Ok(()), Ok(())) => self.compare_captured(),
+            _ => (),
+        }
+        primary
+    }
+
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        if let Err(err) = self.secondary.write_blackout(frame) {
+            warn!(
+                "Failed to black out secondary port {}: {}.",
+                self.secondary, err
+            );
+        }
+        self.primary.write_blackout(frame)
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        let primary = self.primary.write_alternate(start_code, data);
+        let secondary = self.secondary.write_alternate(start_code, data);
+        match (&primary, &secondary) {
+            (Err(err), Ok(())) => self.report(Divergence::PrimaryFailed, err),
+            (Ok(()), Err(err)) => self.report(Divergence::SecondaryFailed, err),
+            _ => (),
+        }
+        primary
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        if let Err(err) = self.secondary.sync() {
+            warn!("Failed to sync secondary port {}: {}.", self.secondary, err);
+        }
+        self.primary.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.primary.frame_size_limits()
+    }
+
+    /// Both ports get every frame, so the slower one sets the pace.
+    fn max_fps(&self) -> Option<f64> {
+        match (self.primary.max_fps(), self.secondary.max_fps()) {
+            (Some(primary), Some(secondary)) => Some(primary.min(secondary)),
+            (primary, secondary) => primary.or(secondary),
+        }
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.primary.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.primary.migrate(from_version);
+        self.secondary.migrate(from_version);
+    }
+}
+
+impl fmt::Display for DualWritePort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (verified against {})", self.primary, self.secondary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+    use crate::MasterPort;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_reports_divergence() {
+        let primary = TestPort::named("primary");
+        let secondary = TestPort::named("secondary");
+        let (diverged, divergences) = mpsc::channel();
+        let mut port = DualWritePort::new(Box::new(primary.clone()), Box::new(secondary.clone()))
+            .on_divergence(move |failed, err| {
+                diverged
+                    .send((failed.to_string(), err.to_string()))
+                    .unwrap();
+            });
+        port.write(&[1]).unwrap();
+        secondary.set_broken(true);
+        port.write(&[2]).unwrap();
+        primary.set_broken(true);
+        assert!(port.write(&[3]).is_err());
+
+        assert_eq!(1, port.divergences());
+        assert_eq!(
+            vec![(
+                "secondary".to_string(),
+                WriteError::Disconnected.to_string()
+            )],
+            divergences.try_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_reports_mismatched_levels() {
+        let primary = TestPort::named("primary");
+        let secondary = TestPort::named("secondary");
+        let (mismatched, mismatches) = mpsc::channel();
+        let mut port = DualWritePort::new(
+            Box::new(primary.clone()),
+            Box::new(MasterPort::new(Box::new(secondary.clone()), 0.5)),
+        )
+        .on_mismatch(move |primary, secondary| {
+            mismatched
+                .send((primary.to_vec(), secondary.to_vec()))
+                .unwrap();
+        });
+        // Trailing zeros don't count as a difference.
+        port.write(&[0, 0]).unwrap();
+        assert_eq!(0, port.divergences());
+        port.write(&[0, 255]).unwrap();
+        assert_eq!(1, port.divergences());
+        assert_eq!(
+            vec![(vec![0, 255], vec![0, 128])],
+            mismatches.try_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_max_fps_is_the_slower_port() {
+        let primary = TestPort::default();
+        let secondary = TestPort::default();
+        let port = DualWritePort::new(Box::new(primary.clone()), Box::new(secondary.clone()));
+        assert_eq!(None, port.max_fps());
+        secondary.set_max_fps(Some(30.0));
+        assert_eq!(Some(30.0), port.max_fps());
+        primary.set_max_fps(Some(20.0));
+        assert_eq!(Some(20.0), port.max_fps());
+    }
+}
@@ -1,55 +1,161 @@
 //! Implementation of support for the Enttec USB DMX Pro dongle.
-use log::debug;
+use anyhow::{anyhow, bail};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
-use std::io::Write;
-use std::time::Duration;
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
 use std::{cmp::min, fmt};
 use thiserror::Error;
 
-use crate::{OpenError, PortListing, WriteError};
+use crate::{
+    system_clock, Clock, DmxInputPort, FrameSizeLimits, InputFrame, OpenError, PortListing,
+    ReadError, WriteError, DMX_UNIVERSE_SIZE, MIN_FRAME_SIZE,
+};
 
 use super::DmxPort;
-use serialport::{SerialPort, SerialPortInfo, SerialPortType, UsbPortInfo};
+use crate::enttec_codec::{
+    encode_packet, encode_set_parameters, END_VAL, FRAMING_SIZE, GET_PARAMETERS, GET_WIDGET_SERIAL,
+    MAX_PACKET_SIZE, MAX_PAYLOAD_SIZE, RECEIVE_DMX_ON_CHANGE, RECEIVE_DMX_PACKET, SEND_DMX_PACKET,
+    SEND_DMX_PORT_A, SEND_DMX_PORT_B, SEND_RDM_DISCOVERY, SEND_RDM_PACKET, START_VAL,
+};
+use crate::rdm::{
+    self, decode_discovery_response, Branch, CommandClass, RdmDiscovery, RdmTransport, Request,
+    Response, Uid, DISC_MUTE, DISC_UN_MUTE,
+};
+use serialport::{ClearBuffer, SerialPort, SerialPortInfo, SerialPortType, UsbPortInfo};
 
-// Some constants used for enttec message framing.
-const START_VAL: u8 = 0x7E;
-const END_VAL: u8 = 0xE7;
+// USB IDs of DMXKing widgets.
+const DMXKING_VID: u16 = 0x16C0;
+const DMXKING_PID: u16 = 0x05DC;
 
-// Universe size constraints.
-const MIN_UNIVERSE_SIZE: usize = 24;
-const MAX_UNIVERSE_SIZE: usize = 512;
+// Status flags of a received DMX packet.
+const RECEIVE_QUEUE_OVERFLOW: u8 = 0x01;
+const RECEIVE_OVERRUN: u8 = 0x02;
 
-// Port action flags.
-const SET_PARAMETERS: u8 = 4;
-//const RECEIVE_DMX_PACKET: u8 = 5;
-const SEND_DMX_PACKET: u8 = 6;
+/// A frame write taking longer than this suggests the widget's buffer is full.
+const SLOW_WRITE: Duration = Duration::from_millis(5);
+
+/// This many consecutive slow writes are reported as an overrun.
+const SLOW_WRITES_BEFORE_OVERRUN: usize = 5;
+
+/// Maximum size of the user configuration blob stored on the widget.
+const MAX_USER_CONFIG_SIZE: usize = 508;
+
+/// Size of the parameters that precede the user configuration in a
+/// GetParameters reply: firmware version, break, mark after break, and rate.
+const PARAMETERS_REPLY_SIZE: usize = 5;
+
+/// The unit of the break and mark after break parameters.
+const TIMING_UNIT_SECS: f64 = 10.67e-6;
+
+/// Time to transmit one DMX slot: 11 bits at 250 kbaud.
+const SLOT_TIME_SECS: f64 = 44e-6;
+
+/// Give up on a widget that doesn't reply to a request in this time.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Receive modes of the widget: send every received packet to the host, which
+/// is the widget's power-up default, or only packets that changed.
+const RECEIVE_ALWAYS: u8 = 0;
+const RECEIVE_CHANGES_ONLY: u8 = 1;
+
+/// Give up on an RDM responder that doesn't answer through the widget in this
+/// time. Responders have a few milliseconds; the rest allows for USB latency.
+const RDM_RESPONSE_TIMEOUT: Duration = Duration::from_millis(100);
 
 /// Format a byte buffer as an enttec message into the provided writer.
-/// Maximum valid size for payload is 600; no check is made here that the payload is within this range.
+/// Payloads larger than the maximum valid size of 600 bytes will be truncated.
 fn write_packet<W: Write>(
     message_type: u8,
     payload: &[u8],
     add_payload_pad_byte: bool,
     mut w: W,
 ) -> Result<(), WriteError> {
-    // Enttec messages are the size of the payload plus 5 bytes for type, length, and framing.
-    let payload_size = payload.len() + add_payload_pad_byte as usize;
-    let (len_lsb, len_msb) = (payload_size as u8, (payload_size >> 8) as u8);
-    let header = [START_VAL, message_type, len_lsb, len_msb];
-    let mut write_all = |buf| -> Result<(), EnttecWriteError> {
-        w.write_all(buf)?;
-        Ok(())
-    };
-    write_all(&header)?;
-    if add_payload_pad_byte {
-        write_all(&[0][..])?;
-    }
-    write_all(payload)?;
-    write_all(&[END_VAL][..])?;
+    let mut buf = [0; MAX_PACKET_SIZE];
+    let max_len = MAX_PAYLOAD_SIZE - add_payload_pad_byte as usize;
+    let payload = &payload[..min(payload.len(), max_len)];
+    let len = encode_packet(message_type, payload, add_payload_pad_byte, &mut buf)
+        .expect("payload was truncated to fit the buffer");
+    w.write_all(&buf[..len]).map_err(EnttecWriteError)?;
     Ok(())
 }
 
+/// Read the next message of the given type from r and return its payload.
+/// Any other messages the widget sends in the meantime are skipped.
+/// Return None if no such message arrives before the deadline.
+///
+/// pending holds the bytes read from r that haven't been decoded yet. A
+/// message only partly read by the deadline stays there for the next call, so
+/// its remainder isn't misread as the start of a new message.
+pub(crate) fn read_packet<R: Read>(
+    message_type: u8,
+    mut r: R,
+    pending: &mut Vec<u8>,
+    deadline: Instant,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut buf = [0; MAX_PACKET_SIZE];
+    loop {
+        while let Some((packet_type, payload)) = take_packet(pending) {
+            if packet_type == message_type {
+                return Ok(Some(payload));
+            }
+            debug!("Skipping enttec message of type {}.", packet_type);
+        }
+        match r.read(&mut buf) {
+            Ok(0) => bail!("the widget closed the connection"),
+            Ok(n) => pending.extend_from_slice(&buf[..n]),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::TimedOut
+                        | io::ErrorKind::WouldBlock
+                        | io::ErrorKind::Interrupted
+                ) =>
+            {
+                if Instant::now() >= deadline {
+                    return Ok(None);
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Remove the first complete message from pending, along with anything before
+/// it that isn't a message, and return its type and payload. Return None if
+/// pending doesn't hold a complete message yet.
+fn take_packet(pending: &mut Vec<u8>) -> Option<(u8, Vec<u8>)> {
+    loop {
+        let start = pending
+            .iter()
+            .position(|&byte| byte == START_VAL)
+            .unwrap_or(pending.len());
+        pending.drain(..start);
+        let header = pending.get(1..4)?;
+        let (packet_type, len) = (
+            header[0],
+            u16::from_le_bytes([header[1], header[2]]) as usize,
+        );
+        if len > MAX_PAYLOAD_SIZE {
+            pending.remove(0);
+            continue;
+        }
+        if *pending.get(4 + len)? != END_VAL {
+            pending.remove(0);
+            continue;
+        }
+        let payload = pending[4..][..len].to_vec();
+        pending.drain(..len + FRAMING_SIZE);
+        return Some((packet_type, payload));
+    }
+}
+
+/// Missing fields take their default values, so configs saved before a
+/// parameter was added still load.
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EnttecParams {
     /// DMX output break time in 10.67 microsecond units. Valid range is 9 to 127.
     break_time: u8,
@@ -73,53 +179,749 @@ impl Default for EnttecParams {
 }
 
 impl EnttecParams {
-    fn write_into<W: Write>(&self, w: W) -> Result<(), WriteError> {
-        let payload = [
-            0, // user size lsb?
-            0, // user size msb?
+    /// Return the fastest rate a full universe can be output at with these
+    /// parameters: the line rate of break, mark after break, and 513 slots,
+    /// capped by the fixed output rate if one is set.
+    fn max_fps(&self) -> f64 {
+        let frame_time = (self.break_time as f64 + self.mark_after_break_time as f64)
+            * TIMING_UNIT_SECS
+            + (DMX_UNIVERSE_SIZE + 1) as f64 * SLOT_TIME_SECS;
+        let line_rate = 1.0 / frame_time;
+        match self.output_rate {
+            0 => line_rate,
+            rate => line_rate.min(rate as f64),
+        }
+    }
+
+    /// Write these parameters to the widget, along with a user configuration
+    /// blob to store. An empty user configuration leaves the stored one alone.
+    fn write_into<W: Write>(&self, user_config: &[u8], mut w: W) -> Result<(), WriteError> {
+        let mut buf = [0; MAX_PACKET_SIZE];
+        let len = encode_set_parameters(
             self.break_time,
             self.mark_after_break_time,
             self.output_rate,
-        ];
-        write_packet(SET_PARAMETERS, &payload, false, w)
+            user_config,
+            &mut buf,
+        )
+        .expect("user configuration is limited to fit the buffer");
+        w.write_all(&buf[..len]).map_err(EnttecWriteError)?;
+        Ok(())
+    }
+}
+
+/// The parameters stored on a widget, as reported by a GetParameters reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnttecStoredParams {
+    pub firmware_version: u16,
+    /// DMX output break time in 10.67 microsecond units.
+    pub break_time: u8,
+    /// DMX output Mark After Break time in 10.67 microsecond units.
+    pub mark_after_break_time: u8,
+    /// DMX output rate in packets per second, or 0 for as fast as possible.
+    pub output_rate: u8,
+}
+
+impl EnttecStoredParams {
+    /// Decode the parameters at the start of a GetParameters reply.
+    fn decode(reply: &[u8]) -> Option<Self> {
+        let params = reply.get(..PARAMETERS_REPLY_SIZE)?;
+        Some(Self {
+            firmware_version: u16::from_le_bytes([params[0], params[1]]),
+            break_time: params[2],
+            mark_after_break_time: params[3],
+            output_rate: params[4],
+        })
+    }
+}
+
+/// Which output of the widget a port sends to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WidgetOutput {
+    /// The widget's standard output. On a DMXKing dual-output widget this
+    /// drives both outputs with the same frame.
+    #[default]
+    Standard,
+    /// Only output A of a DMXKing dual-output widget.
+    A,
+    /// Only output B of a DMXKing dual-output widget.
+    B,
+    /// The second universe of a DMX USB Pro Mk2, which is unlocked with
+    /// Enttec's Mk2 API. The standard output is the first universe.
+    Mk2Port2(Mk2Api),
+}
+
+impl WidgetOutput {
+    fn send_label(self) -> u8 {
+        match self {
+            Self::Standard => SEND_DMX_PACKET,
+            Self::A => SEND_DMX_PORT_A,
+            Self::B => SEND_DMX_PORT_B,
+            Self::Mk2Port2(api) => api.send_dmx_port2_label,
+        }
+    }
+
+    /// Return the labels of the messages that send an RDM request and an RDM
+    /// discovery request from this output, or None if it doesn't support RDM.
+    fn rdm_labels(self) -> Option<(u8, u8)> {
+        match self {
+            Self::Standard => Some((SEND_RDM_PACKET, SEND_RDM_DISCOVERY)),
+            Self::A | Self::B | Self::Mk2Port2(_) => None,
+        }
+    }
+}
+
+/// The key and message labels of the DMX USB Pro Mk2 API, which Enttec gives
+/// to developers on request rather than publishing, so they must be supplied
+/// here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mk2Api {
+    /// The key that unlocks the Mk2 API. It is licensed to the developer, so
+    /// it is left out of saved configs; supply it again after loading one
+    /// with `EnttecDmxPort::set_mk2_api_key`.
+    #[serde(skip)]
+    pub api_key: u32,
+    /// The label of the message that sends the key.
+    pub set_api_key_label: u8,
+    /// The label of the message that enables the widget's two outputs.
+    pub port_assignment_label: u8,
+    /// The label of the message that sends DMX from the second output.
+    pub send_dmx_port2_label: u8,
+}
+
+/// Counts of received packets that the widget flagged as corrupt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EnttecReceiveErrors {
+    /// The widget's receive queue overflowed, so earlier packets were lost.
+    pub queue_overflows: usize,
+    /// The widget's receiver overran, so the packet's data is unreliable.
+    pub overruns: usize,
+}
+
+/// The byte stream to a widget.
+///
+/// This is a serial port in normal use; tests and simulators can provide their
+/// own to check exactly what the port sends. Flushing should block until all
+/// written bytes have been transmitted.
+pub trait SerialTransport: Read + Write + Send {
+    /// Discard any received bytes that haven't been read yet.
+    fn clear_input(&mut self) -> io::Result<()>;
+}
+
+impl<T: SerialPort + ?Sized> SerialTransport for Box<T> {
+    fn clear_input(&mut self) -> io::Result<()> {
+        Ok(self.clear(ClearBuffer::Input)?)
+    }
+}
+
+/// Opens the transport to the widget at a port path. Called whenever the port
+/// is opened or reopened.
+pub type TransportOpener =
+    Box<dyn FnMut(&str) -> Result<Box<dyn SerialTransport>, OpenError> + Send>;
+
+/// A widget's open transport, shared by the ports for each of its outputs.
+struct Widget {
+    transport: Box<dyn SerialTransport>,
+    /// The receive mode last set, assumed to be the power-up default until
+    /// then.
+    receive_mode: u8,
+    /// Bytes read from the widget that don't make up a whole message yet.
+    pending: Vec<u8>,
+}
+
+impl Widget {
+    /// Discard everything the widget sent that hasn't been read, so it isn't
+    /// mistaken for the reply to a request sent next.
+    fn clear_input(&mut self) -> io::Result<()> {
+        self.pending.clear();
+        self.transport.clear_input()
+    }
+
+    /// Read the next message of the given type that arrives before deadline.
+    fn read_packet(
+        &mut self,
+        message_type: u8,
+        deadline: Instant,
+    ) -> anyhow::Result<Option<Vec<u8>>> {
+        read_packet(
+            message_type,
+            &mut self.transport,
+            &mut self.pending,
+            deadline,
+        )
+    }
+
+    /// Set whether the widget sends every packet it receives to the host, or
+    /// only those that changed.
+    fn set_receive_mode(&mut self, mode: u8) -> Result<(), WriteError> {
+        write_packet(RECEIVE_DMX_ON_CHANGE, &[mode], false, &mut self.transport)?;
+        self.receive_mode = mode;
+        Ok(())
+    }
+}
+
+/// The widgets open at each path. Serial ports open exclusively, so the ports
+/// for each output of a multi-output widget share one transport, which is
+/// closed once none of them is using it.
+static OPEN_WIDGETS: Mutex<Vec<(String, Weak<Mutex<Widget>>)>> = Mutex::new(Vec::new());
+
+/// Return the widget open at path, if any.
+fn find_open_widget(
+    open: &[(String, Weak<Mutex<Widget>>)],
+    path: &str,
+) -> Option<Arc<Mutex<Widget>>> {
+    open.iter()
+        .filter(|(open_path, _)| open_path == path)
+        .find_map(|(_, widget)| widget.upgrade())
+}
+
+/// Open the serial port at path.
+fn open_serial_port(path: &str) -> Result<Box<dyn SerialTransport>, OpenError> {
+    // Opening the tty device on macOS blocks waiting for carrier detect, so
+    // always use the callout device, even for ports saved with a tty path.
+    #[cfg(target_os = "macos")]
+    let path = &callout_path(path);
+    // baud rate is not used on FTDI
+    // serialport adds the \\.\ prefix that Windows needs to open COM10 and above.
+    match serialport::new(path, 57600)
+        .timeout(Duration::from_millis(1))
+        .open()
+    {
+        Ok(port) => Ok(Box::new(port)),
+        Err(err) => Err(open_error(err)),
+    }
+}
+
+/// Classify an error opening a serial port, so a widget that is missing is
+/// reported as such and can be looked for at another path.
+fn open_error(err: serialport::Error) -> OpenError {
+    match err.kind() {
+        serialport::ErrorKind::Io(io::ErrorKind::NotFound) => OpenError::NotConnected,
+        // serialport reports a COM port that doesn't exist as NoDevice on
+        // Windows. Elsewhere NoDevice means the port is busy.
+        #[cfg(windows)]
+        serialport::ErrorKind::NoDevice => OpenError::NotConnected,
+        _ => OpenError::Other(err.into()),
     }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct EnttecDmxPort {
+    #[serde(default)]
     params: EnttecParams,
+    #[serde(default)]
+    output: WidgetOutput,
+    #[serde(skip)]
+    port: Option<Arc<Mutex<Widget>>>,
+    /// Opens the transport in place of the serial port, if set.
     #[serde(skip)]
-    port: Option<Box<dyn SerialPort>>,
+    opener: Option<TransportOpener>,
     #[serde(with = "SerialPortInfoDef")]
     info: SerialPortInfo,
+    /// FTDI latency timer in milliseconds to set when opening, if any.
+    #[serde(default)]
+    latency_timer: Option<u8>,
+    /// Frame to send before closing, if any.
+    #[serde(default)]
+    close_frame: Option<Vec<u8>>,
+    /// Number of consecutive frame writes slower than SLOW_WRITE.
+    #[serde(skip)]
+    slow_writes: usize,
+    #[serde(skip)]
+    receive_errors: EnttecReceiveErrors,
+    #[serde(skip)]
+    rdm_transaction: u8,
+    /// The serial number the widget reported, once queried.
+    #[serde(skip)]
+    widget_serial: Option<u32>,
+    /// Set when the parameters have changed since they were last sent.
+    #[serde(skip)]
+    params_dirty: bool,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
 }
 
 impl EnttecDmxPort {
     /// Create an enttec port.
     /// The port is not opened yet.
     pub fn new(info: SerialPortInfo) -> Self {
+        Self::with_output(info, WidgetOutput::Standard)
+    }
+
+    /// Create a port that sends to one output of a multi-output widget.
+    /// The port is not opened yet. Open ports for the outputs of one widget
+    /// share its serial port.
+    pub fn with_output(info: SerialPortInfo, output: WidgetOutput) -> Self {
         let params = EnttecParams::default();
 
         Self {
             params,
+            output,
             port: None,
+            opener: None,
             info,
+            latency_timer: None,
+            close_frame: None,
+            slow_writes: 0,
+            receive_errors: EnttecReceiveErrors::default(),
+            rdm_transaction: 0,
+            widget_serial: None,
+            params_dirty: false,
+            clock: system_clock(),
+        }
+    }
+
+    /// Create a port that talks to the widget through transports from opener
+    /// instead of the serial port. The port is not opened yet.
+    pub fn with_transport(
+        info: SerialPortInfo,
+        opener: impl FnMut(&str) -> Result<Box<dyn SerialTransport>, OpenError> + Send + 'static,
+    ) -> Self {
+        let mut port = Self::new(info);
+        port.opener = Some(Box::new(opener));
+        port
+    }
+
+    /// Set the widget's FTDI latency timer to this many milliseconds, from 1
+    /// to 255, when opening. The driver default of 16ms adds visible lag to
+    /// incoming DMX and RDM replies.
+    ///
+    /// This is only supported on Linux, where it needs write access to the
+    /// device's sysfs attributes; elsewhere, or if it fails, the port still
+    /// opens and a warning is logged. Windows and macOS aren't supported: the
+    /// FTDI drivers there only take the setting from the driver configuration,
+    /// such as the advanced settings of the port in Windows Device Manager.
+    pub fn with_latency_timer(mut self, millis: u8) -> anyhow::Result<Self> {
+        if millis == 0 {
+            bail!("latency timer of {millis}ms is out of range; expected 1 to 255");
         }
+        self.latency_timer = Some(millis);
+        Ok(self)
+    }
+
+    /// Send this frame when closing the port, such as all zeros to black out
+    /// the universe rather than leave the last levels on stage.
+    pub fn with_close_frame(mut self, frame: Vec<u8>) -> Self {
+        self.close_frame = Some(frame);
+        self
+    }
+
+    /// Time frame writes using clock instead of the system clock, to tell
+    /// when the widget's buffer is overrun.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
 
     /// Create an enttec port and open it.
     pub fn opened(info: SerialPortInfo) -> anyhow::Result<Self> {
         let mut port = Self::new(info);
-        port.open()?;
+        DmxPort::open(&mut port)?;
         Ok(port)
     }
 
+    /// Send a packet with the given start code, reopening the port if needed.
+    fn send(&mut self, start_code: u8, frame: &[u8]) -> Result<(), WriteError> {
+        // If the port isn't open, try opening it.
+        // Quick profiling shows that a disconnected port only takes about
+        // 100us to poll and fail, so this is acceptable to do inside an
+        // application's render loop.
+        if self.port.is_none() {
+            if let Err(err) = DmxPort::open(self) {
+                debug!("Failed to reopen DMX port {}: {:#?}.", self, err);
+                return Err(WriteError::Disconnected);
+            }
+        }
+        if self.params_dirty {
+            if let Err(err) = self.write_params() {
+                if let WriteError::Disconnected = err {
+                    self.disconnect();
+                }
+                return Err(err);
+            }
+        }
+        let mut widget = self.widget().ok_or(WriteError::Disconnected)?;
+        let port = &mut widget.transport;
+        let start = self.clock.now();
+        let frame = &frame[..min(frame.len(), DMX_UNIVERSE_SIZE)];
+        let write_result = if start_code == 0 && frame.len() >= MIN_FRAME_SIZE {
+            write_packet(self.output.send_label(), frame, true, port)
+        } else {
+            let mut payload = Vec::with_capacity(1 + MIN_FRAME_SIZE.max(frame.len()));
+            payload.push(start_code);
+            payload.extend_from_slice(frame);
+            payload.resize(1 + MIN_FRAME_SIZE.max(frame.len()), 0);
+            write_packet(self.output.send_label(), &payload, false, port)
+        };
+        drop(widget);
+        if let Err(WriteError::Disconnected) = write_result {
+            self.disconnect();
+        }
+        write_result?;
+        self.check_for_overrun(self.clock.now() - start)
+    }
+
+    /// Lock the open widget, if any.
+    fn widget(&self) -> Option<MutexGuard<'_, Widget>> {
+        self.port.as_ref().map(|widget| widget.lock().unwrap())
+    }
+
+    /// Lock the open widget, or return an error if the port isn't open.
+    fn open_widget(&self) -> anyhow::Result<MutexGuard<'_, Widget>> {
+        self.widget()
+            .ok_or_else(|| anyhow!("{} is not open", self.info.port_name))
+    }
+
+    /// Drop a transport that failed, so that no other output of the widget
+    /// reuses it either.
+    fn disconnect(&mut self) {
+        if let Some(widget) = self.port.take() {
+            OPEN_WIDGETS.lock().unwrap().retain(|(_, open)| {
+                open.strong_count() > 0 && !open.ptr_eq(&Arc::downgrade(&widget))
+            });
+        }
+    }
+
+    /// Return the widget already open at this port's path, or open it.
+    /// Return true along with the widget if it was newly opened.
+    fn connect(&mut self) -> Result<(Arc<Mutex<Widget>>, bool), OpenError> {
+        let mut open = OPEN_WIDGETS.lock().unwrap();
+        open.retain(|(_, widget)| widget.strong_count() > 0);
+        if let Some(widget) = find_open_widget(&open, &self.info.port_name) {
+            return Ok((widget, false));
+        }
+        let transport = match &mut self.opener {
+            Some(open) => open(&self.info.port_name)?,
+            None => match open_serial_port(&self.info.port_name) {
+                Err(OpenError::NotConnected) => {
+                    self.relocate()?;
+                    if let Some(widget) = find_open_widget(&open, &self.info.port_name) {
+                        return Ok((widget, false));
+                    }
+                    open_serial_port(&self.info.port_name)?
+                }
+                result => result?,
+            },
+        };
+        let widget = Arc::new(Mutex::new(Widget {
+            transport,
+            receive_mode: RECEIVE_ALWAYS,
+            pending: Vec::new(),
+        }));
+        open.push((self.info.port_name.clone(), Arc::downgrade(&widget)));
+        Ok((widget, true))
+    }
+
+    /// Track the time taken by a successful frame write.
+    /// Writes that are consistently slow mean we're overrunning the widget's
+    /// buffer, even though the serial port hasn't timed out yet.
+    fn check_for_overrun(&mut self, elapsed: Duration) -> Result<(), WriteError> {
+        if elapsed < SLOW_WRITE {
+            self.slow_writes = 0;
+            return Ok(());
+        }
+        self.slow_writes += 1;
+        if self.slow_writes >= SLOW_WRITES_BEFORE_OVERRUN {
+            self.slow_writes = 0;
+            return Err(WriteError::Overrun);
+        }
+        Ok(())
+    }
+
+    /// Return counts of the received packets that were dropped as corrupt.
+    pub fn receive_errors(&self) -> EnttecReceiveErrors {
+        self.receive_errors
+    }
+
+    /// Record the status of a received packet. Return true if its data is usable.
+    fn check_receive_status(&mut self, status: u8) -> bool {
+        if status & RECEIVE_QUEUE_OVERFLOW != 0 {
+            self.receive_errors.queue_overflows += 1;
+        }
+        if status & RECEIVE_OVERRUN != 0 {
+            self.receive_errors.overruns += 1;
+        }
+        if status != 0 {
+            debug!(
+                "Dropping packet received by {} with status {:#04x}.",
+                self, status
+            );
+        }
+        status == 0
+    }
+
+    /// Unlock the Mk2 API and enable both of the widget's outputs for DMX.
+    fn unlock_mk2(&self, api: Mk2Api) -> Result<(), WriteError> {
+        let mut widget = self.widget().ok_or(WriteError::Disconnected)?;
+        let port = &mut widget.transport;
+        write_packet(
+            api.set_api_key_label,
+            &api.api_key.to_le_bytes(),
+            false,
+            &mut *port,
+        )?;
+        write_packet(api.port_assignment_label, &[1, 1], false, port)
+    }
+
     /// Write the current parameters out to the port.
     fn write_params(&mut self) -> Result<(), WriteError> {
-        self.params
-            .write_into(self.port.as_mut().ok_or(WriteError::Disconnected)?)?;
+        let mut widget = self.widget().ok_or(WriteError::Disconnected)?;
+        self.params.write_into(&[], &mut widget.transport)?;
+        drop(widget);
+        self.params_dirty = false;
+        Ok(())
+    }
+
+    /// Set the DMX output break time, in 10.67 microsecond units from 9 to
+    /// 127. It is sent to the widget with the next frame.
+    pub fn set_break_time(&mut self, break_time: u8) -> anyhow::Result<()> {
+        if !(9..=127).contains(&break_time) {
+            bail!("break time {break_time} is out of range; expected 9 to 127");
+        }
+        self.params.break_time = break_time;
+        self.params_dirty = true;
         Ok(())
     }
+
+    /// Set the DMX output mark after break time, in 10.67 microsecond units
+    /// from 1 to 127. It is sent to the widget with the next frame.
+    pub fn set_mark_after_break_time(&mut self, mark_after_break_time: u8) -> anyhow::Result<()> {
+        if !(1..=127).contains(&mark_after_break_time) {
+            bail!(
+                "mark after break time {mark_after_break_time} is out of range; expected 1 to 127"
+            );
+        }
+        self.params.mark_after_break_time = mark_after_break_time;
+        self.params_dirty = true;
+        Ok(())
+    }
+
+    /// Set the DMX output rate, from 1 to 40 packets per second, or 0 for as
+    /// fast as possible. It is sent to the widget with the next frame.
+    pub fn set_output_rate(&mut self, output_rate: u8) -> anyhow::Result<()> {
+        if output_rate > 40 {
+            bail!("output rate {output_rate} is out of range; expected 0 to 40");
+        }
+        self.params.output_rate = output_rate;
+        self.params_dirty = true;
+        Ok(())
+    }
+
+    /// The widget may have been replugged and given a different device path.
+    /// Look for it by its USB serial number, and move this port to its new
+    /// path.
+    fn relocate(&mut self) -> Result<(), OpenError> {
+        let available =
+            serialport::available_ports().map_err(|err| OpenError::Other(err.into()))?;
+        let Some(port_name) = relocated_port_name(&self.info, available) else {
+            return Err(OpenError::NotConnected);
+        };
+        info!(
+            "Enttec port {} reappeared as {}.",
+            self.info.port_name, port_name
+        );
+        self.info.port_name = port_name;
+        Ok(())
+    }
+
+    /// Request the widget's stored parameters, followed by up to
+    /// user_config_size bytes of its user configuration, and return the reply.
+    fn get_parameters(&mut self, user_config_size: usize) -> anyhow::Result<Vec<u8>> {
+        let mut widget = self.open_widget()?;
+        widget.clear_input()?;
+        write_packet(
+            GET_PARAMETERS,
+            &(user_config_size as u16).to_le_bytes(),
+            false,
+            &mut widget.transport,
+        )?;
+        let reply = widget
+            .read_packet(GET_PARAMETERS, Instant::now() + RESPONSE_TIMEOUT)?
+            .ok_or_else(|| anyhow!("timed out waiting for a reply from the widget"))?;
+        if reply.len() < PARAMETERS_REPLY_SIZE {
+            bail!("parameters reply too short: {} bytes", reply.len());
+        }
+        Ok(reply)
+    }
+
+    /// Read back the firmware version and output parameters stored on the
+    /// widget, which may differ from this port's own if another application
+    /// set them. The port must be open.
+    pub fn read_params(&mut self) -> anyhow::Result<EnttecStoredParams> {
+        let reply = self.get_parameters(0)?;
+        Ok(EnttecStoredParams::decode(&reply).expect("reply length was checked"))
+    }
+
+    /// Query the serial number the widget stores, which is printed on its label
+    /// and tells identical widgets apart even when the USB serial number
+    /// doesn't. Once queried, it also identifies the port in its Display form.
+    /// The port must be open.
+    pub fn query_widget_serial(&mut self) -> anyhow::Result<u32> {
+        let mut widget = self.open_widget()?;
+        widget.clear_input()?;
+        write_packet(GET_WIDGET_SERIAL, &[], false, &mut widget.transport)?;
+        let reply = widget
+            .read_packet(GET_WIDGET_SERIAL, Instant::now() + RESPONSE_TIMEOUT)?
+            .ok_or_else(|| anyhow!("timed out waiting for a reply from the widget"))?;
+        drop(widget);
+        let serial = decode_widget_serial(&reply)
+            .ok_or_else(|| anyhow!("malformed serial number reply: {reply:02x?}"))?;
+        self.widget_serial = Some(serial);
+        Ok(serial)
+    }
+
+    /// Return the serial number the widget reported, if it has been queried.
+    pub fn widget_serial(&self) -> Option<u32> {
+        self.widget_serial
+    }
+
+    /// Read up to len bytes of the user configuration stored on the widget.
+    /// The port must be open.
+    pub fn read_user_config(&mut self, len: usize) -> anyhow::Result<Vec<u8>> {
+        let len = min(len, MAX_USER_CONFIG_SIZE);
+        let mut reply = self.get_parameters(len)?;
+        Ok(reply.split_off(PARAMETERS_REPLY_SIZE))
+    }
+
+    /// Find the UIDs of every RDM responder attached to the widget's output.
+    /// The port must be open.
+    pub fn discover_rdm(&mut self) -> anyhow::Result<Vec<Uid>> {
+        rdm::discover(self)
+    }
+
+    /// Have the widget send received DMX to the host only when it changes,
+    /// rather than every packet, such as to stop an output-only widget
+    /// streaming its input. This port reads every packet as input, so
+    /// `DmxInputPort::open` turns this off again. The port must be open.
+    pub fn set_receive_changes_only(&mut self, changes_only: bool) -> anyhow::Result<()> {
+        let mode = if changes_only {
+            RECEIVE_CHANGES_ONLY
+        } else {
+            RECEIVE_ALWAYS
+        };
+        self.open_widget()?.set_receive_mode(mode)?;
+        Ok(())
+    }
+
+    /// Supply the key that unlocks the Mk2 API, which saved configs leave
+    /// out. Does nothing unless this port sends to the second universe of a
+    /// Mk2. Takes effect the next time the port is opened.
+    pub fn set_mk2_api_key(&mut self, api_key: u32) {
+        if let WidgetOutput::Mk2Port2(api) = &mut self.output {
+            api.api_key = api_key;
+        }
+    }
+
+    /// Send an RDM request, or a discovery request if discovery is set, and
+    /// return the status and data of the first packet the widget receives in
+    /// reply, if any. Replies are only awaited if await_reply is set.
+    fn rdm_exchange(
+        &mut self,
+        discovery: bool,
+        request: &Request,
+        await_reply: bool,
+    ) -> anyhow::Result<Option<(u8, Vec<u8>)>> {
+        let Some((request_label, discovery_label)) = self.output.rdm_labels() else {
+            bail!("{self} doesn't support RDM");
+        };
+        let label = if discovery {
+            discovery_label
+        } else {
+            request_label
+        };
+        self.rdm_transaction = self.rdm_transaction.wrapping_add(1);
+        let request = Request {
+            transaction_number: self.rdm_transaction,
+            ..request.clone()
+        };
+        let mut buf = [0; rdm::MAX_MESSAGE_SIZE];
+        let len = request
+            .encode(&mut buf)
+            .ok_or_else(|| anyhow!("RDM parameter data is too long"))?;
+        let mut widget = self.open_widget()?;
+        // Replies arrive as received packets, so the widget has to pass them
+        // all on until the exchange is over.
+        let receive_mode = widget.receive_mode;
+        let switch_mode = await_reply && receive_mode != RECEIVE_ALWAYS;
+        if switch_mode {
+            widget.set_receive_mode(RECEIVE_ALWAYS)?;
+        }
+        let exchange = |widget: &mut Widget| -> anyhow::Result<Option<Vec<u8>>> {
+            widget.clear_input()?;
+            write_packet(label, &buf[..len], false, &mut widget.transport)?;
+            if !await_reply {
+                return Ok(None);
+            }
+            widget.read_packet(RECEIVE_DMX_PACKET, Instant::now() + RDM_RESPONSE_TIMEOUT)
+        };
+        let reply = exchange(&mut widget);
+        if switch_mode {
+            widget.set_receive_mode(receive_mode)?;
+        }
+        Ok(reply?.and_then(|payload| {
+            let (&status, data) = payload.split_first()?;
+            Some((status, data.to_vec()))
+        }))
+    }
+
+    /// Store a user configuration blob of at most 508 bytes on
+    /// the widget, such as a rig name or calibration data. It persists across
+    /// power cycles and can be read back on any machine. The port must be open.
+    pub fn write_user_config(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        if data.len() > MAX_USER_CONFIG_SIZE {
+            bail!(
+                "user configuration is {} bytes; the widget stores at most {}",
+                data.len(),
+                MAX_USER_CONFIG_SIZE
+            );
+        }
+        let mut widget = self.open_widget()?;
+        self.params.write_into(data, &mut widget.transport)?;
+        Ok(())
+    }
+}
+
+impl RdmTransport for EnttecDmxPort {
+    /// The port must be open.
+    fn rdm_request(&mut self, request: &Request) -> anyhow::Result<Option<Response>> {
+        let reply = self.rdm_exchange(false, request, true)?;
+        Ok(reply
+            .filter(|(status, _)| *status == 0)
+            .and_then(|(_, data)| Response::decode(&data)))
+    }
+}
+
+impl RdmDiscovery for EnttecDmxPort {
+    fn un_mute_all(&mut self) -> anyhow::Result<()> {
+        let request = Request::new(
+            Uid::BROADCAST,
+            CommandClass::Discovery,
+            DISC_UN_MUTE,
+            vec![],
+        );
+        // Responders don't answer broadcasts.
+        self.rdm_exchange(false, &request, false)?;
+        Ok(())
+    }
+
+    fn unique_branch(&mut self, lower: Uid, upper: Uid) -> anyhow::Result<Branch> {
+        let request = Request::unique_branch(lower, upper);
+        Ok(match self.rdm_exchange(true, &request, true)? {
+            None => Branch::Empty,
+            Some((0, data)) => {
+                decode_discovery_response(&data).map_or(Branch::Collision, Branch::Found)
+            }
+            // The widget flags garbled responses, which mean a collision.
+            Some(_) => Branch::Collision,
+        })
+    }
+
+    fn mute(&mut self, uid: Uid) -> anyhow::Result<bool> {
+        let request = Request::new(uid, CommandClass::Discovery, DISC_MUTE, vec![]);
+        Ok(self
+            .rdm_request(&request)?
+            .is_some_and(|response| response.source == uid && response.pid == DISC_MUTE))
+    }
 }
 
 #[typetag::serde]
@@ -127,10 +929,25 @@ impl DmxPort for EnttecDmxPort {
     /// Return the available enttec ports connected to this system.
     /// TODO: provide a mechanism to specialize this implementation depending on platform.
     fn available_ports() -> anyhow::Result<PortListing> {
-        Ok(serialport::available_ports()?
+        let mut ports: Vec<_> = serialport::available_ports()?
             .into_iter()
             .filter(is_enttec)
-            .map(|info| Box::new(EnttecDmxPort::new(info)) as Box<dyn DmxPort>)
+            .collect();
+        // Order doesn't depend on how the OS happened to enumerate the devices.
+        ports.sort_by_cached_key(|info| {
+            (
+                serial_number(info).map(str::to_string),
+                info.port_name.clone(),
+            )
+        });
+        // List each output of a dual-output widget as its own port.
+        Ok(dedup_by_serial_number(ports)
+            .into_iter()
+            .flat_map(|info| {
+                widget_outputs(&info).into_iter().map(move |output| {
+                    Box::new(EnttecDmxPort::with_output(info.clone(), output)) as Box<dyn DmxPort>
+                })
+            })
             .collect())
     }
 
@@ -140,111 +957,394 @@ impl DmxPort for EnttecDmxPort {
             return Ok(());
         }
 
-        // baud rate is not used on FTDI
-        let port = match serialport::new(&self.info.port_name, 57600)
-            .timeout(Duration::from_millis(1))
-            .open()
-        {
-            Ok(port) => port,
-            Err(err) => {
-                if let serialport::ErrorKind::Io(std::io::ErrorKind::NotFound) = err.kind() {
-                    return Err(OpenError::NotConnected);
-                } else {
-                    return Err(OpenError::Other(err.into()));
-                }
+        if let WidgetOutput::Mk2Port2(api) = self.output {
+            if api.api_key == 0 {
+                return Err(OpenError::Other(anyhow!(
+                    "the Mk2 API key of {self} isn't set; saved configs leave it out"
+                )));
             }
-        };
+        }
+        let (widget, newly_opened) = self.connect()?;
+        self.port = Some(widget);
 
-        self.port = Some(port);
+        if let (Some(millis), None, true) = (self.latency_timer, &self.opener, newly_opened) {
+            if let Err(err) = set_latency_timer(&self.info.port_name, millis) {
+                warn!(
+                    "Failed to set the latency timer of {} to {millis}ms: {err}.",
+                    self.info.port_name
+                );
+            }
+        }
 
         // send the default parameters to the port
         if let Err(e) = self.write_params() {
-            self.port = None;
+            self.disconnect();
             return Err(OpenError::Other(e.into()));
         }
+        if let WidgetOutput::Mk2Port2(api) = self.output {
+            if let Err(e) = self.unlock_mk2(api) {
+                self.disconnect();
+                return Err(OpenError::Other(e.into()));
+            }
+        }
         Ok(())
     }
 
+    /// Close the port, first sending the close frame if one is set and
+    /// waiting for everything written to reach the widget.
     fn close(&mut self) {
+        if self.port.is_none() {
+            return;
+        }
+        if let Some(frame) = self.close_frame.clone() {
+            if let Err(err) = self.send(0, &frame) {
+                warn!("Failed to send the close frame to {}: {}.", self, err);
+            }
+        }
+        if let Some(mut widget) = self.widget() {
+            if let Err(err) = widget.transport.flush() {
+                debug!(
+                    "Failed to drain {} before closing: {}.",
+                    self.info.port_name, err
+                );
+            }
+        }
+        // Another output of the widget may still be using its transport.
         self.port = None;
     }
 
     fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
-        // If the port isn't open, try opening it.
-        // Quick profiling shows that a disconnected port only takes about
-        // 100us to poll and fail, so this is acceptable to do inside an
-        // application's render loop.
-        if self.port.is_none() {
-            if let Err(err) = self.open() {
-                debug!("Failed to reopen DMX port {}: {:#?}.", self, err);
-                return Err(WriteError::Disconnected);
-            }
+        self.send(0, frame)
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.send(start_code, data)
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        FrameSizeLimits {
+            min: MIN_FRAME_SIZE,
+            max: DMX_UNIVERSE_SIZE,
         }
-        let port = self.port.as_mut().ok_or(WriteError::Disconnected)?;
-        let size = frame.len();
-        let write_result = if size < MIN_UNIVERSE_SIZE {
-            let mut padded_frame = Vec::with_capacity(MIN_UNIVERSE_SIZE);
-            padded_frame.extend_from_slice(frame);
-            padded_frame.resize(MIN_UNIVERSE_SIZE, 0);
-            write_packet(SEND_DMX_PACKET, &padded_frame, true, port)
-        } else {
-            write_packet(
-                SEND_DMX_PACKET,
-                &frame[0..min(size, MAX_UNIVERSE_SIZE)],
-                true,
-                port,
-            )
-        };
-        if let Err(WriteError::Disconnected) = write_result {
-            self.port = None;
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        Some(self.params.max_fps())
+    }
+}
+
+#[typetag::serde]
+impl DmxInputPort for EnttecDmxPort {
+    /// Open the port and have the widget send every DMX packet it receives.
+    fn open(&mut self) -> Result<(), OpenError> {
+        DmxPort::open(self)?;
+        let mut widget = self.widget().ok_or(OpenError::NotConnected)?;
+        let result = widget.set_receive_mode(RECEIVE_ALWAYS);
+        drop(widget);
+        if let Err(err) = result {
+            self.disconnect();
+            return Err(OpenError::Other(err.into()));
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        DmxPort::close(self);
+    }
+
+    fn read(&mut self, timeout: Duration) -> Result<Option<InputFrame>, ReadError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut widget = self.widget().ok_or(ReadError::Disconnected)?;
+            let result = widget.read_packet(RECEIVE_DMX_PACKET, deadline);
+            drop(widget);
+            let payload = match result {
+                Ok(Some(payload)) => payload,
+                Ok(None) => return Ok(None),
+                Err(err) => {
+                    self.disconnect();
+                    return Err(ReadError::Other(err));
+                }
+            };
+            let Some((&status, data)) = payload.split_first() else {
+                continue;
+            };
+            if !self.check_receive_status(status) {
+                continue;
+            }
+            // Skip packets with alternate start codes, such as RDM.
+            let Some((0, levels)) = data.split_first() else {
+                continue;
+            };
+            return Ok(Some(InputFrame {
+                universe: 0,
+                source: None,
+                levels: levels.to_vec(),
+            }));
         }
-        write_result
     }
 }
 
 impl fmt::Display for EnttecDmxPort {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let SerialPortType::UsbPort(p) = &self.info.port_type {
-            if let Some(sn) = &p.serial_number {
-                return write!(f, "Enttec DMX USB PRO {}", sn);
-            }
+        match (serial_number(&self.info), self.widget_serial) {
+            (Some(sn), _) => write!(f, "Enttec DMX USB PRO {}", sn)?,
+            (None, Some(sn)) => write!(f, "Enttec DMX USB PRO {}", sn)?,
+            (None, None) => write!(f, "Enttec DMX USB PRO {}", self.info.port_name)?,
         }
-        write!(f, "Enttec DMX USB PRO {}", self.info.port_name)
+        match self.output {
+            WidgetOutput::Standard => (),
+            WidgetOutput::A => write!(f, " output A")?,
+            WidgetOutput::B => write!(f, " output B")?,
+            WidgetOutput::Mk2Port2(_) => write!(f, " output 2")?,
+        }
+        // A bare COM number is hard to map to hardware; Windows reports the
+        // device description, such as "USB Serial Port (COM7)", as the product.
+        #[cfg(windows)]
+        if let SerialPortType::UsbPort(UsbPortInfo {
+            product: Some(description),
+            ..
+        }) = &self.info.port_type
+        {
+            write!(f, " ({})", description)?;
+        }
+        Ok(())
     }
 }
 
-#[cfg(unix)]
+/// Set the FTDI latency timer of the USB serial device at path, which must be
+/// from 1 to 255ms. A saved config may hold any value, so it is checked here
+/// as well as by `with_latency_timer`.
+fn set_latency_timer(path: &str, millis: u8) -> io::Result<()> {
+    if millis == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the latency timer must be from 1 to 255ms",
+        ));
+    }
+    write_latency_timer(path, millis)
+}
+
+#[cfg(target_os = "linux")]
+fn write_latency_timer(path: &str, millis: u8) -> io::Result<()> {
+    std::fs::write(latency_timer_attribute(path)?, millis.to_string())
+}
+
+/// Return the sysfs attribute holding the latency timer of the USB serial
+/// device at path.
+#[cfg(target_os = "linux")]
+fn latency_timer_attribute(path: &str) -> io::Result<std::path::PathBuf> {
+    // Resolve symlinks such as /dev/serial/by-id to the ttyUSB device.
+    let device = std::fs::canonicalize(path)?;
+    let name = device
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a device path"))?;
+    Ok(std::path::Path::new("/sys/bus/usb-serial/devices")
+        .join(name)
+        .join("latency_timer"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn write_latency_timer(_path: &str, _millis: u8) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "setting the latency timer is only supported on Linux",
+    ))
+}
+
+/// Return the macOS callout device for a tty device path, such as
+/// /dev/cu.usbserial-EN123456 for /dev/tty.usbserial-EN123456.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn callout_path(path: &str) -> String {
+    match path.strip_prefix("/dev/tty.") {
+        Some(name) => format!("/dev/cu.{}", name),
+        None => path.to_string(),
+    }
+}
+
+/// A pattern matching the USB details of widgets that speak the Enttec Pro
+/// protocol, so they are listed by `available_ports`. Fields left as None
+/// match anything; manufacturer and product match substrings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsbMatch {
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+}
+
+impl UsbMatch {
+    fn matches(&self, details: &UsbPortInfo) -> bool {
+        let contains = |pattern: &Option<String>, value: &Option<String>| match pattern {
+            None => true,
+            Some(pattern) => value.as_ref().is_some_and(|value| value.contains(pattern)),
+        };
+        self.vid.is_none_or(|vid| vid == details.vid)
+            && self.pid.is_none_or(|pid| pid == details.pid)
+            && contains(&self.manufacturer, &details.manufacturer)
+            && contains(&self.product, &details.product)
+    }
+}
+
+/// The patterns set by `set_enttec_usb_matches`, or None for the defaults.
+static USB_MATCHES: Mutex<Option<Vec<UsbMatch>>> = Mutex::new(None);
+
+/// The patterns Enttec detection uses unless configured otherwise: genuine
+/// Enttec widgets and DMXKing widgets. Windows reports Enttec widgets only as
+/// FTDI devices, so there any FTDI device matches.
+fn default_usb_matches() -> Vec<UsbMatch> {
+    let enttec = if cfg!(windows) {
+        UsbMatch {
+            manufacturer: Some("FTDI".to_string()),
+            ..Default::default()
+        }
+    } else {
+        UsbMatch {
+            product: Some("DMX USB PRO".to_string()),
+            ..Default::default()
+        }
+    };
+    let dmxking = UsbMatch {
+        vid: Some(DMXKING_VID),
+        pid: Some(DMXKING_PID),
+        ..Default::default()
+    };
+    vec![enttec, dmxking]
+}
+
+/// Return the patterns used to detect Enttec Pro compatible widgets.
+pub fn enttec_usb_matches() -> Vec<UsbMatch> {
+    USB_MATCHES
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(default_usb_matches)
+}
+
+/// Replace the patterns used to detect Enttec Pro compatible widgets, such as
+/// to add a clone that reports its own USB IDs. Start from
+/// `enttec_usb_matches` to keep the defaults.
+pub fn set_enttec_usb_matches(patterns: Vec<UsbMatch>) {
+    *USB_MATCHES.lock().unwrap() = Some(patterns);
+}
+
 fn is_enttec(info: &SerialPortInfo) -> bool {
     let SerialPortType::UsbPort(details) = &info.port_type else {
         return false;
     };
-    let Some(product) = &details.product else {
+    // Each device is listed as both a tty and a callout device, which
+    // shouldn't be listed twice. macOS needs the callout device, since its tty
+    // device blocks on carrier detect.
+    if cfg!(target_os = "macos") && !info.port_name.starts_with("/dev/cu.") {
         return false;
-    };
-    product == "DMX USB PRO" && info.port_name.contains("tty")
+    }
+    if cfg!(all(unix, not(target_os = "macos"))) && !info.port_name.contains("tty") {
+        return false;
+    }
+    enttec_usb_matches()
+        .iter()
+        .any(|pattern| pattern.matches(details))
 }
 
-#[cfg(windows)]
-fn is_enttec(info: &SerialPortInfo) -> bool {
+/// Return true if this is a DMXKing widget, all of which speak the Enttec
+/// Pro protocol.
+fn is_dmxking(info: &SerialPortInfo) -> bool {
     let SerialPortType::UsbPort(details) = &info.port_type else {
         return false;
     };
-    let Some(manufacturer) = &details.manufacturer else {
+    details.vid == DMXKING_VID && details.pid == DMXKING_PID
+}
+
+/// Return true if this is a DMXKing widget with two independent outputs.
+fn is_dmxking_dual_output(info: &SerialPortInfo) -> bool {
+    let SerialPortType::UsbPort(details) = &info.port_type else {
         return false;
     };
-    manufacturer == "FTDI"
+    is_dmxking(info)
+        && details
+            .product
+            .as_ref()
+            .is_some_and(|product| product.contains("ultraDMX2"))
+}
+
+/// Return the outputs of a widget to list as ports: each output of a
+/// dual-output widget separately, or the standard output of any other.
+fn widget_outputs(info: &SerialPortInfo) -> Vec<WidgetOutput> {
+    if is_dmxking_dual_output(info) {
+        vec![WidgetOutput::A, WidgetOutput::B]
+    } else {
+        vec![WidgetOutput::Standard]
+    }
+}
+
+/// Decode a serial number reply: four bytes of binary-coded decimal, least
+/// significant first.
+fn decode_widget_serial(reply: &[u8]) -> Option<u32> {
+    let bytes: [u8; 4] = reply.get(..4)?.try_into().unwrap();
+    bytes.iter().rev().try_fold(0, |serial, &byte| {
+        let (high, low) = (byte >> 4, byte & 0xF);
+        (high < 10 && low < 10).then_some(serial * 100 + (high * 10 + low) as u32)
+    })
+}
+
+fn serial_number(info: &SerialPortInfo) -> Option<&str> {
+    match &info.port_type {
+        SerialPortType::UsbPort(details) => details.serial_number.as_deref(),
+        _ => None,
+    }
+}
+
+/// Return the device path a widget with the same USB serial number as info is
+/// now listed at, if it has moved.
+fn relocated_port_name(info: &SerialPortInfo, available: Vec<SerialPortInfo>) -> Option<String> {
+    let serial = serial_number(info)?;
+    available
+        .into_iter()
+        .filter(is_enttec)
+        .find(|candidate| serial_number(candidate) == Some(serial))
+        .map(|candidate| candidate.port_name)
+        .filter(|port_name| *port_name != info.port_name)
+}
+
+/// Some systems list the same widget more than once. Keep only the first
+/// listing of each USB serial number; ports without one are always kept.
+fn dedup_by_serial_number(ports: Vec<SerialPortInfo>) -> Vec<SerialPortInfo> {
+    let mut seen = HashSet::new();
+    ports
+        .into_iter()
+        .filter(|info| {
+            let Some(serial_number) = serial_number(info) else {
+                return true;
+            };
+            if seen.insert(serial_number.to_string()) {
+                return true;
+            }
+            debug!("Skipping duplicate listing {}.", info.port_name);
+            false
+        })
+        .collect()
 }
 
 #[derive(Error, Debug)]
 #[error(transparent)]
 pub struct EnttecWriteError(#[from] std::io::Error);
 
+#[cfg(unix)]
+const EIO: i32 = 5;
+
 impl From<EnttecWriteError> for WriteError {
     fn from(value: EnttecWriteError) -> Self {
-        if value.0.kind() == std::io::ErrorKind::BrokenPipe {
-            Self::Disconnected
-        } else {
-            Self::Other(value.0.into())
+        // Writing to a widget whose cable was pulled fails with EIO on Linux
+        // and macOS.
+        #[cfg(unix)]
+        if value.0.raw_os_error() == Some(EIO) {
+            return Self::Disconnected;
+        }
+        match value.0.kind() {
+            std::io::ErrorKind::BrokenPipe => Self::Disconnected,
+            // The FTDI driver only times out a write when its buffer is full.
+            std::io::ErrorKind::TimedOut => Self::Overrun,
+            _ => Self::Other(value.0.into()),
         }
     }
 }
@@ -281,9 +1381,13 @@ pub struct UsbPortInfoDef {
 
 #[cfg(test)]
 mod test {
+    use crate::enttec_codec::SET_PARAMETERS;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
     use std::{thread::sleep, time::Duration};
 
     use super::*;
+    use crate::ManualClock;
     use std::error::Error;
 
     #[test]
@@ -298,4 +1402,609 @@ mod test {
         port.write(&[0][..])?;
         Ok(())
     }
+
+    #[test]
+    fn test_dedup_by_serial_number() {
+        let usb = |name: &str, serial_number: Option<&str>| SerialPortInfo {
+            port_name: name.to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: 0x0403,
+                pid: 0x6001,
+                serial_number: serial_number.map(str::to_string),
+                manufacturer: None,
+                product: None,
+            }),
+        };
+        let ports = vec![
+            usb("a", Some("EN1")),
+            usb("b", Some("EN1")),
+            usb("c", Some("EN2")),
+            usb("d", None),
+            usb("e", None),
+        ];
+        let names: Vec<_> = dedup_by_serial_number(ports)
+            .into_iter()
+            .map(|info| info.port_name)
+            .collect();
+        assert_eq!(vec!["a", "c", "d", "e"], names);
+    }
+
+    #[test]
+    fn test_relocated_port_name() {
+        let usb = |name: &str, serial_number: &str| SerialPortInfo {
+            port_name: name.to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: DMXKING_VID,
+                pid: DMXKING_PID,
+                serial_number: Some(serial_number.to_string()),
+                manufacturer: None,
+                product: None,
+            }),
+        };
+        let info = usb("/dev/ttyUSB0", "A");
+        assert_eq!(
+            Some("/dev/ttyUSB1".to_string()),
+            relocated_port_name(
+                &info,
+                vec![usb("/dev/ttyUSB0", "B"), usb("/dev/ttyUSB1", "A")]
+            )
+        );
+        assert_eq!(
+            None,
+            relocated_port_name(&info, vec![usb("/dev/ttyUSB0", "A")])
+        );
+        assert_eq!(
+            None,
+            relocated_port_name(&info, vec![usb("/dev/ttyUSB1", "B")])
+        );
+    }
+
+    #[test]
+    fn test_latency_timer_range() {
+        let port = || {
+            EnttecDmxPort::new(SerialPortInfo {
+                port_name: "latency".to_string(),
+                port_type: SerialPortType::Unknown,
+            })
+        };
+        assert!(port().with_latency_timer(0).is_err());
+        assert_eq!(Some(1), port().with_latency_timer(1).unwrap().latency_timer);
+        assert_eq!(
+            io::ErrorKind::InvalidInput,
+            set_latency_timer("/dev/null", 0).unwrap_err().kind()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_latency_timer_attribute() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("rust_dmx_latency_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let device = dir.join("ttyUSB7");
+        std::fs::write(&device, "")?;
+        let link = dir.join("usb-ENTTEC_DMX_USB_PRO_EN123456-if00-port0");
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&device, &link)?;
+        let attribute = latency_timer_attribute(link.to_str().unwrap());
+        std::fs::remove_dir_all(&dir)?;
+        assert_eq!(
+            std::path::Path::new("/sys/bus/usb-serial/devices/ttyUSB7/latency_timer"),
+            attribute?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_port_is_not_connected() {
+        let missing = serialport::Error::new(
+            serialport::ErrorKind::Io(io::ErrorKind::NotFound),
+            "no such file",
+        );
+        assert!(matches!(open_error(missing), OpenError::NotConnected));
+        let no_device = serialport::Error::new(serialport::ErrorKind::NoDevice, "no device");
+        assert_eq!(
+            cfg!(windows),
+            matches!(open_error(no_device), OpenError::NotConnected)
+        );
+    }
+
+    #[test]
+    fn test_pulled_cable_is_disconnected() {
+        let write_error = |err| WriteError::from(EnttecWriteError(err));
+        #[cfg(unix)]
+        assert!(matches!(
+            write_error(io::Error::from_raw_os_error(EIO)),
+            WriteError::Disconnected
+        ));
+        assert!(matches!(
+            write_error(io::ErrorKind::BrokenPipe.into()),
+            WriteError::Disconnected
+        ));
+        assert!(matches!(
+            write_error(io::ErrorKind::TimedOut.into()),
+            WriteError::Overrun
+        ));
+        assert!(matches!(
+            write_error(io::ErrorKind::PermissionDenied.into()),
+            WriteError::Other(_)
+        ));
+    }
+
+    #[test]
+    fn test_callout_path() {
+        assert_eq!(
+            "/dev/cu.usbserial-EN1",
+            callout_path("/dev/tty.usbserial-EN1")
+        );
+        assert_eq!(
+            "/dev/cu.usbserial-EN1",
+            callout_path("/dev/cu.usbserial-EN1")
+        );
+        assert_eq!("/dev/ttyUSB0", callout_path("/dev/ttyUSB0"));
+    }
+
+    #[test]
+    fn test_usb_match() {
+        let details = UsbPortInfo {
+            vid: 0x0403,
+            pid: 0x6001,
+            serial_number: None,
+            manufacturer: Some("FTDI".to_string()),
+            product: Some("DMX USB PRO Mk2".to_string()),
+        };
+        let pattern = |vid, product: &str| UsbMatch {
+            vid,
+            product: Some(product.to_string()),
+            ..Default::default()
+        };
+        assert!(pattern(Some(0x0403), "DMX USB PRO").matches(&details));
+        assert!(!pattern(Some(0x16C0), "DMX USB PRO").matches(&details));
+        assert!(!pattern(None, "Open DMX").matches(&details));
+    }
+
+    #[test]
+    fn test_widget_outputs() {
+        let dmxking = |product: &str| SerialPortInfo {
+            port_name: "dmxking".to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: DMXKING_VID,
+                pid: DMXKING_PID,
+                serial_number: None,
+                manufacturer: Some("DMXking.com".to_string()),
+                product: Some(product.to_string()),
+            }),
+        };
+        assert!(is_dmxking(&dmxking("ultraDMX Micro")));
+        assert_eq!(
+            vec![WidgetOutput::Standard],
+            widget_outputs(&dmxking("ultraDMX Micro"))
+        );
+        assert_eq!(
+            vec![WidgetOutput::A, WidgetOutput::B],
+            widget_outputs(&dmxking("ultraDMX2 PRO"))
+        );
+    }
+
+    #[test]
+    fn test_read_packet_skips_other_messages() {
+        let mut input = vec![0x00];
+        input.extend_from_slice(&[START_VAL, SEND_DMX_PACKET, 1, 0, 9, END_VAL]);
+        input.extend_from_slice(&[START_VAL, GET_PARAMETERS, 2, 0, 7, 8, END_VAL]);
+        let payload = read_packet(
+            GET_PARAMETERS,
+            &input[..],
+            &mut Vec::new(),
+            Instant::now() + RESPONSE_TIMEOUT,
+        )
+        .unwrap();
+        assert_eq!(Some(vec![7, 8]), payload);
+    }
+
+    #[test]
+    fn test_read_packet_keeps_partial_message() {
+        let message = [START_VAL, GET_PARAMETERS, 2, 0, 7, 8, END_VAL];
+        let (first, rest) = message.split_at(3);
+        let mut pending = Vec::new();
+        let deadline = Instant::now();
+        assert_eq!(
+            None,
+            read_packet(GET_PARAMETERS, TimesOut(first), &mut pending, deadline).unwrap()
+        );
+        assert_eq!(first, pending);
+        assert_eq!(
+            Some(vec![7, 8]),
+            read_packet(GET_PARAMETERS, TimesOut(rest), &mut pending, deadline).unwrap()
+        );
+        assert!(pending.is_empty());
+    }
+
+    /// A reader that times out once its bytes have been read.
+    struct TimesOut<'a>(&'a [u8]);
+
+    impl Read for TimesOut<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf)? {
+                0 => Err(io::ErrorKind::TimedOut.into()),
+                n => Ok(n),
+            }
+        }
+    }
+
+    /// A transport that records everything written to it, and answers
+    /// messages with scripted replies.
+    #[derive(Clone, Default)]
+    struct MemoryTransport {
+        written: Arc<Mutex<Vec<u8>>>,
+        widget: Arc<Mutex<ScriptedWidget>>,
+    }
+
+    #[derive(Default)]
+    struct ScriptedWidget {
+        /// Replies to send, in order, each once a message with its label is
+        /// written.
+        replies: VecDeque<(u8, Vec<u8>)>,
+        /// Bytes sent by the widget that haven't been read yet.
+        input: VecDeque<u8>,
+        /// How long each write takes, on a clock.
+        delay: Option<(Arc<ManualClock>, Duration)>,
+    }
+
+    impl MemoryTransport {
+        fn written(&self) -> MutexGuard<'_, Vec<u8>> {
+            self.written.lock().unwrap()
+        }
+
+        /// Have the widget send a message of message_type with payload once
+        /// the next message with label is written, after any replies already
+        /// scripted.
+        fn reply_to(&self, label: u8, message_type: u8, payload: &[u8]) {
+            let mut buf = [0; MAX_PACKET_SIZE];
+            let len = encode_packet(message_type, payload, false, &mut buf).unwrap();
+            let reply = (label, buf[..len].to_vec());
+            self.widget.lock().unwrap().replies.push_back(reply);
+        }
+    }
+
+    impl Read for MemoryTransport {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let mut widget = self.widget.lock().unwrap();
+            if widget.input.is_empty() {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let len = buf.len().min(widget.input.len());
+            for (byte, input) in buf.iter_mut().zip(widget.input.drain(..len)) {
+                *byte = input;
+            }
+            Ok(len)
+        }
+    }
+
+    impl Write for MemoryTransport {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written().extend_from_slice(buf);
+            let mut widget = self.widget.lock().unwrap();
+            if let Some((clock, delay)) = &widget.delay {
+                clock.advance(*delay);
+            }
+            if let [START_VAL, label, ..] = *buf {
+                if widget.replies.front().is_some_and(|(l, _)| *l == label) {
+                    let (_, reply) = widget.replies.pop_front().unwrap();
+                    widget.input.extend(reply);
+                }
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SerialTransport for MemoryTransport {
+        fn clear_input(&mut self) -> io::Result<()> {
+            self.widget.lock().unwrap().input.clear();
+            Ok(())
+        }
+    }
+
+    /// Create a port for output of a widget at path that talks to transport.
+    fn memory_port(path: &str, output: WidgetOutput, transport: &MemoryTransport) -> EnttecDmxPort {
+        let info = SerialPortInfo {
+            port_name: path.to_string(),
+            port_type: SerialPortType::Unknown,
+        };
+        let transport = transport.clone();
+        let mut port = EnttecDmxPort::with_output(info, output);
+        port.opener = Some(Box::new(move |_| Ok(Box::new(transport.clone()))));
+        port
+    }
+
+    #[test]
+    fn test_writes_params_then_padded_frame() -> Result<(), Box<dyn Error>> {
+        let transport = MemoryTransport::default();
+        let info = SerialPortInfo {
+            port_name: "params".to_string(),
+            port_type: SerialPortType::Unknown,
+        };
+        let opener_transport = transport.clone();
+        let mut port =
+            EnttecDmxPort::with_transport(info, move |_| Ok(Box::new(opener_transport.clone())));
+        DmxPort::open(&mut port)?;
+        assert_eq!(
+            vec![START_VAL, SET_PARAMETERS, 5, 0, 0, 0, 9, 1, 40, END_VAL],
+            transport.written().drain(..).collect::<Vec<_>>()
+        );
+        port.write(&[1, 2, 3])?;
+        let mut expected = vec![START_VAL, SEND_DMX_PACKET, 25, 0, 0, 1, 2, 3];
+        expected.resize(4 + 25, 0);
+        expected.push(END_VAL);
+        assert_eq!(expected, *transport.written());
+
+        // Changed parameters go out ahead of the next frame.
+        assert!(port.set_output_rate(41).is_err());
+        port.set_output_rate(0)?;
+        transport.written().clear();
+        port.write(&[1, 2, 3])?;
+        assert_eq!(
+            vec![START_VAL, SET_PARAMETERS, 5, 0, 0, 0, 9, 1, 0, END_VAL],
+            transport.written()[..10]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_consistently_slow_writes_are_overruns() -> Result<(), Box<dyn Error>> {
+        let clock = Arc::new(ManualClock::new());
+        let transport = MemoryTransport::default();
+        let mut port =
+            memory_port("slow", WidgetOutput::Standard, &transport).with_clock(clock.clone());
+        DmxPort::open(&mut port)?;
+        transport.widget.lock().unwrap().delay = Some((clock, SLOW_WRITE));
+        for _ in 1..SLOW_WRITES_BEFORE_OVERRUN {
+            port.write(&[1; 2])?;
+        }
+        assert!(matches!(port.write(&[1; 2]), Err(WriteError::Overrun)));
+        // The count starts over after an overrun.
+        port.write(&[1; 2])?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_sends_close_frame() -> Result<(), Box<dyn Error>> {
+        let transport = MemoryTransport::default();
+        let info = SerialPortInfo {
+            port_name: "close_frame".to_string(),
+            port_type: SerialPortType::Unknown,
+        };
+        let opener_transport = transport.clone();
+        let mut port =
+            EnttecDmxPort::with_transport(info, move |_| Ok(Box::new(opener_transport.clone())))
+                .with_close_frame(vec![0; MIN_FRAME_SIZE]);
+        DmxPort::open(&mut port)?;
+        transport.written().clear();
+        DmxPort::close(&mut port);
+        let mut expected = vec![START_VAL, SEND_DMX_PACKET, 25, 0];
+        expected.resize(4 + 25, 0);
+        expected.push(END_VAL);
+        assert_eq!(expected, *transport.written());
+
+        // Closing a closed port sends nothing.
+        transport.written().clear();
+        DmxPort::close(&mut port);
+        assert!(transport.written().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlocks_mk2_second_output() -> Result<(), Box<dyn Error>> {
+        let transport = MemoryTransport::default();
+        let api = Mk2Api {
+            api_key: 0x04030201,
+            set_api_key_label: 200,
+            port_assignment_label: 201,
+            send_dmx_port2_label: 202,
+        };
+        let mut port = memory_port("mk2", WidgetOutput::Mk2Port2(api), &transport);
+        DmxPort::open(&mut port)?;
+        let written: Vec<_> = transport.written().drain(..).collect();
+        assert!(written.ends_with(&[
+            START_VAL, 200, 4, 0, 1, 2, 3, 4, END_VAL, START_VAL, 201, 2, 0, 1, 1, END_VAL
+        ]));
+        port.write(&[0; 24])?;
+        assert_eq!(202, transport.written()[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_saved_config_leaves_out_mk2_api_key() -> Result<(), Box<dyn Error>> {
+        let api = Mk2Api {
+            api_key: 0x04030201,
+            set_api_key_label: 200,
+            port_assignment_label: 201,
+            send_dmx_port2_label: 202,
+        };
+        let port = memory_port(
+            "mk2 config",
+            WidgetOutput::Mk2Port2(api),
+            &Default::default(),
+        );
+        let saved = serde_json::to_string(&port)?;
+        assert!(!saved.contains("\"api_key\""), "{saved}");
+
+        let mut loaded: EnttecDmxPort = serde_json::from_str(&saved)?;
+        let err = DmxPort::open(&mut loaded).unwrap_err();
+        assert!(err.to_string().contains("API key"), "{err}");
+        loaded.set_mk2_api_key(api.api_key);
+        assert_eq!(WidgetOutput::Mk2Port2(api), loaded.output);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rdm_restores_receive_mode() -> Result<(), Box<dyn Error>> {
+        let transport = MemoryTransport::default();
+        let mut port = memory_port("rdm", WidgetOutput::Standard, &transport);
+        DmxPort::open(&mut port)?;
+        port.set_receive_changes_only(true)?;
+        transport.written().clear();
+
+        let request = Request::new(Uid::new(1, 2), CommandClass::Get, rdm::DEVICE_INFO, vec![]);
+        assert!(port.rdm_request(&request)?.is_none());
+        let written = transport.written().clone();
+        let set_mode = |mode| [START_VAL, RECEIVE_DMX_ON_CHANGE, 1, 0, mode, END_VAL];
+        assert!(written.starts_with(&set_mode(RECEIVE_ALWAYS)));
+        assert_eq!(SEND_RDM_PACKET, written[7]);
+        assert!(written.ends_with(&set_mode(RECEIVE_CHANGES_ONLY)));
+
+        let mut output_a = memory_port("rdm a", WidgetOutput::A, &transport);
+        DmxPort::open(&mut output_a)?;
+        assert!(output_a.rdm_request(&request).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_outputs_share_one_transport() -> Result<(), Box<dyn Error>> {
+        let transport = MemoryTransport::default();
+        let opens = Arc::new(Mutex::new(0));
+        let port = |output| {
+            let info = SerialPortInfo {
+                port_name: "dual".to_string(),
+                port_type: SerialPortType::Unknown,
+            };
+            let (transport, opens) = (transport.clone(), opens.clone());
+            let mut port = EnttecDmxPort::with_output(info, output);
+            port.opener = Some(Box::new(move |_| {
+                *opens.lock().unwrap() += 1;
+                Ok(Box::new(transport.clone()))
+            }));
+            port
+        };
+        let (mut a, mut b) = (port(WidgetOutput::A), port(WidgetOutput::B));
+        DmxPort::open(&mut a)?;
+        DmxPort::open(&mut b)?;
+        assert_eq!(1, *opens.lock().unwrap());
+
+        transport.written().clear();
+        a.write(&[1; MIN_FRAME_SIZE])?;
+        b.write(&[2; MIN_FRAME_SIZE])?;
+        let written = transport.written().clone();
+        assert_eq!(SEND_DMX_PORT_A, written[1]);
+        assert_eq!(SEND_DMX_PORT_B, written[MIN_FRAME_SIZE + 7]);
+
+        // Closing one output leaves the transport open for the other.
+        DmxPort::close(&mut a);
+        b.write(&[2; MIN_FRAME_SIZE])?;
+        DmxPort::close(&mut b);
+        DmxPort::open(&mut a)?;
+        assert_eq!(2, *opens.lock().unwrap());
+        Ok(())
+    }
+
+    /// Encode a received packet carrying an RDM message as the widget sends it.
+    fn received_rdm(message: &[u8]) -> Vec<u8> {
+        let mut payload = vec![0];
+        payload.extend_from_slice(message);
+        payload
+    }
+
+    /// Encode an acknowledgement of a GET of pid from uid.
+    fn ack(uid: Uid, pid: u16, data: Vec<u8>) -> Vec<u8> {
+        let response = Request {
+            destination: Uid::DEFAULT_CONTROLLER,
+            source: uid,
+            transaction_number: 0,
+            // The response type, which is ACK.
+            port_id: 0,
+            sub_device: rdm::ROOT_DEVICE,
+            command_class: CommandClass::GetResponse,
+            pid,
+            data,
+        };
+        let mut buf = [0; rdm::MAX_MESSAGE_SIZE];
+        let len = response.encode(&mut buf).unwrap();
+        received_rdm(&buf[..len])
+    }
+
+    #[test]
+    fn test_read_params_through_transport() -> Result<(), Box<dyn Error>> {
+        let transport = MemoryTransport::default();
+        let mut port = memory_port("read params", WidgetOutput::Standard, &transport);
+        DmxPort::open(&mut port)?;
+        transport.reply_to(GET_PARAMETERS, GET_PARAMETERS, &[0x44, 0x01, 9, 1, 40]);
+        assert_eq!(
+            EnttecStoredParams {
+                firmware_version: 0x0144,
+                break_time: 9,
+                mark_after_break_time: 1,
+                output_rate: 40,
+            },
+            port.read_params()?
+        );
+        assert!(transport
+            .written()
+            .ends_with(&[START_VAL, GET_PARAMETERS, 2, 0, 0, 0, END_VAL]));
+
+        transport.reply_to(GET_PARAMETERS, GET_PARAMETERS, &[0x44, 0x01, 9]);
+        assert!(port.read_params().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rdm_request_through_transport() -> Result<(), Box<dyn Error>> {
+        let transport = MemoryTransport::default();
+        let mut port = memory_port("rdm get", WidgetOutput::Standard, &transport);
+        DmxPort::open(&mut port)?;
+        let uid = Uid::new(0x454E, 1);
+        // A stale reply from before the request is discarded.
+        transport.reply_to(SET_PARAMETERS, RECEIVE_DMX_PACKET, &[0, 0, 1]);
+        port.set_output_rate(0)?;
+        port.write(&[0; MIN_FRAME_SIZE])?;
+        transport.reply_to(
+            SEND_RDM_PACKET,
+            RECEIVE_DMX_PACKET,
+            &ack(uid, rdm::DMX_START_ADDRESS, vec![0, 17]),
+        );
+        assert_eq!(17, port.dmx_start_address(uid)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rdm_discovery_through_transport() -> Result<(), Box<dyn Error>> {
+        let transport = MemoryTransport::default();
+        let mut port = memory_port("rdm discovery", WidgetOutput::Standard, &transport);
+        DmxPort::open(&mut port)?;
+        let uid = Uid::new(0x454E, 2);
+        transport.reply_to(
+            SEND_RDM_DISCOVERY,
+            RECEIVE_DMX_PACKET,
+            &received_rdm(&rdm::encode_discovery_response(uid)),
+        );
+        transport.reply_to(
+            SEND_RDM_PACKET,
+            RECEIVE_DMX_PACKET,
+            &ack(uid, DISC_MUTE, vec![0, 0]),
+        );
+        assert_eq!(vec![uid], port.discover_rdm()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_widget_serial() {
+        assert_eq!(
+            Some(12345678),
+            decode_widget_serial(&[0x78, 0x56, 0x34, 0x12])
+        );
+        assert_eq!(None, decode_widget_serial(&[0x7A, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_max_fps() {
+        let mut params = EnttecParams::default();
+        assert_eq!(40.0, params.max_fps());
+        params.output_rate = 0;
+        assert!(
+            (params.max_fps() - 44.1).abs() < 0.1,
+            "{}",
+            params.max_fps()
+        );
+    }
 }
@@ -0,0 +1,135 @@
+//! Fluent combinators for assembling wrapper ports.
+use std::time::Duration;
+
+use crate::{
+    BlackoutOnClosePort, CurvePort, DedupPort, DmxPort, DualWritePort, FailoverPort, MasterPort,
+    RateLimitPort, RefreshPort, SafetyPort, SafetyRule, TeePort, TransformPort,
+};
+
+/// A port, boxed or not, that can be wrapped by the combinators in `DmxPortExt`.
+pub trait IntoDmxPort {
+    /// Box this port as a trait object.
+    fn into_port(self) -> Box<dyn DmxPort>;
+}
+
+impl<P: DmxPort + 'static> IntoDmxPort for P {
+    fn into_port(self) -> Box<dyn DmxPort> {
+        Box::new(self)
+    }
+}
+
+impl IntoDmxPort for Box<dyn DmxPort> {
+    fn into_port(self) -> Box<dyn DmxPort> {
+        self
+    }
+}
+
+/// Wrap ports in the crate's adapters by chaining calls, such as
+/// `port.with_safety(rules).with_rate_limit(40.0)?.with_curve(curve).tee(recorder)`.
+///
+/// Each combinator returns the wrapper port, so chains read from the innermost
+/// port outward: the last combinator applied is the first to see each frame.
+/// Apply `with_safety` first, directly to the output, so it checks the levels
+/// every other layer produces; applied later, a curve or transform after it
+/// could raise levels past its limits.
+pub trait DmxPortExt: IntoDmxPort + Sized {
+    /// Copy every frame into sink as well. See `TeePort`.
+    fn tee(self, sink: impl IntoDmxPort) -> TeePort {
+        TeePort::new(self.into_port(), sink.into_port())
+    }
+
+    /// Run transform over every frame before writing it. See `TransformPort`.
+    fn with_transform(self, transform: impl Fn(&mut [u8]) + Send + 'static) -> TransformPort {
+        TransformPort::new(self.into_port(), transform)
+    }
+
+    /// Scale every level by master, from 0.0 for blackout to 1.0 for
+    /// unchanged. See `MasterPort`.
+    fn with_master(self, master: f64) -> MasterPort {
+        MasterPort::new(self.into_port(), master)
+    }
+
+    /// Map every level through a lookup table, such as a dimmer curve. See
+    /// `CurvePort`.
+    fn with_curve(self, curve: [u8; 256]) -> CurvePort {
+        CurvePort::new(self.into_port(), curve)
+    }
+
+    /// Write at most fps frames per second, dropping frames that come sooner.
+    /// Fail unless fps is a positive rate. See `RateLimitPort`.
+    fn with_rate_limit(self, fps: f64) -> anyhow::Result<RateLimitPort> {
+        RateLimitPort::new(self.into_port(), fps)
+    }
+
+    /// Enforce safety rules on every frame. Apply this before any other
+    /// combinator, so the rules see the levels that reach the output.
+    /// See `SafetyPort`.
+    fn with_safety(self, rules: Vec<SafetyRule>) -> SafetyPort {
+        SafetyPort::new(self.into_port(), rules)
+    }
+
+    /// Fail over to backup when writes keep failing. See `FailoverPort`.
+    fn with_failover(self, backup: impl IntoDmxPort) -> FailoverPort {
+        FailoverPort::new(self.into_port(), backup.into_port())
+    }
+
+    /// Also write every frame to secondary and report divergence. See `DualWritePort`.
+    fn dual_write(self, secondary: impl IntoDmxPort) -> DualWritePort {
+        DualWritePort::new(self.into_port(), secondary.into_port())
+    }
+
+    /// Re-send the last frame so the line is refreshed at least fps times per
+    /// second. Fail unless fps is a positive rate. See `RefreshPort`.
+    fn with_refresh(self, fps: f64) -> anyhow::Result<RefreshPort> {
+        RefreshPort::new(self.into_port(), fps)
+    }
+
+    /// Skip unchanged frames, writing them anyway once max_idle has passed.
+    /// See `DedupPort`.
+    fn skip_unchanged(self, max_idle: Duration) -> DedupPort {
+        DedupPort::new(self.into_port(), max_idle)
+    }
+
+    /// Black out the universe when closed or dropped. See `BlackoutOnClosePort`.
+    fn blackout_on_close(self) -> BlackoutOnClosePort {
+        BlackoutOnClosePort::new(self.into_port())
+    }
+}
+
+impl<P: IntoDmxPort> DmxPortExt for P {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+
+    #[test]
+    fn test_chain() {
+        let output = TestPort::named("output");
+        let recorder = TestPort::named("recorder");
+        let mut inverted = [0; 256];
+        for (level, inverse) in inverted.iter_mut().enumerate() {
+            *inverse = 255 - level as u8;
+        }
+        let mut port = output
+            .clone()
+            .with_safety(vec![SafetyRule::Cap {
+                channel: 1,
+                max: 100,
+            }])
+            .with_rate_limit(40.0)
+            .unwrap()
+            .with_curve(inverted)
+            .with_master(0.5)
+            .tee(recorder.clone());
+        port.write(&[200, 0]).unwrap();
+        // The last combinator applied sees each frame first, and the safety
+        // rules see the levels the curve made.
+        assert_eq!(vec![vec![200, 0]], recorder.frames());
+        assert_eq!(vec![vec![155, 100]], output.frames());
+        assert_eq!(
+            "output (with 1 safety rules) (limited to 40 fps) (curved) (master at 50%)",
+            port.into_parts().0.to_string()
+        );
+    }
+}
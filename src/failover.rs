@@ -0,0 +1,281 @@
+//! A port that switches to a backup when its active output keeps failing.
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{system_clock, Clock, DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError};
+
+fn default_failures_before_failover() -> usize {
+    3
+}
+
+/// A switch of a `FailoverPort` from one of its ports to the other.
+#[derive(Debug, Clone)]
+pub struct FailoverEvent {
+    /// True if the port switched to its backup, false if back to its primary.
+    pub to_backup: bool,
+    /// The port that failed, in its Display form.
+    pub failed: String,
+    /// The error of the write that triggered the switch.
+    pub error: String,
+    /// When the switch happened.
+    pub at: Instant,
+}
+
+/// Called with each failover of a `FailoverPort`.
+type OnFailover = Box<dyn FnMut(&FailoverEvent) + Send>;
+
+/// Write each frame to whichever of a primary and a backup port is active,
+/// switching to the other one after a run of consecutive write failures.
+///
+/// Only the active port is written to. When a failover happens the frame that
+/// triggered it is retried on the newly active port, so a single failover never
+/// drops a frame. If the backup fails in turn the port switches back to the
+/// primary.
+#[derive(Serialize, Deserialize)]
+pub struct FailoverPort {
+    primary: Box<dyn DmxPort>,
+    backup: Box<dyn DmxPort>,
+    /// Fail over after this many consecutive write failures.
+    #[serde(default = "default_failures_before_failover")]
+    failures_before_failover: usize,
+    #[serde(skip)]
+    on_backup: bool,
+    #[serde(skip)]
+    consecutive_failures: usize,
+    #[serde(skip)]
+    failovers: usize,
+    #[serde(skip)]
+    on_failover: Option<OnFailover>,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+impl FailoverPort {
+    /// Write to primary, failing over to backup after three consecutive failures.
+    pub fn new(primary: Box<dyn DmxPort>, backup: Box<dyn DmxPort>) -> Self {
+        Self {
+            primary,
+            backup,
+            failures_before_failover: default_failures_before_failover(),
+            on_backup: false,
+            consecutive_failures: 0,
+            failovers: 0,
+            on_failover: None,
+            clock: system_clock(),
+        }
+    }
+
+    /// Fail over after this many consecutive write failures instead.
+    pub fn with_failures_before_failover(mut self, failures: usize) -> Self {
+        self.failures_before_failover = failures.max(1);
+        self
+    }
+
+    /// Call on_failover each time this port switches outputs, such as to
+    /// alert an operator that the show is running on its backup.
+    pub fn on_failover(mut self, on_failover: impl FnMut(&FailoverEvent) + Send + 'static) -> Self {
+        self.on_failover = Some(Box::new(on_failover));
+        self
+    }
+
+    /// Timestamp failovers using clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Return true if the backup is currently the active port.
+    pub fn on_backup(&self) -> bool {
+        self.on_backup
+    }
+
+    /// Return the number of times this port has switched outputs.
+    pub fn failovers(&self) -> usize {
+        self.failovers
+    }
+
+    /// Make the primary the active port again, such as after it was repaired.
+    pub fn restore_primary(&mut self) {
+        self.on_backup = false;
+        self.consecutive_failures = 0;
+    }
+
+    /// Unwrap this port into the primary and backup ports.
+    pub fn into_parts(self) -> (Box<dyn DmxPort>, Box<dyn DmxPort>) {
+        (self.primary, self.backup)
+    }
+
+    fn active(&mut self) -> &mut Box<dyn DmxPort> {
+        if self.on_backup {
+            &mut self.backup
+        } else {
+            &mut self.primary
+        }
+    }
+
+    fn fail_over(&mut self, err: &WriteError) {
+        let failed = self.active().to_string();
+        self.on_backup = !self.on_backup;
+        self.consecutive_failures = 0;
+        self.failovers += 1;
+        let event = FailoverEvent {
+            to_backup: self.on_backup,
+            failed,
+            error: err.to_string(),
+            at: self.clock.now(),
+        };
+        let active = self.active();
+        warn!(
+            "{} failed repeatedly ({}); failing over to {}.",
+            event.failed, err, active
+        );
+        if let Err(err) = active.open() {
+            warn!("Failed to open {}: {}.", active, err);
+        }
+        if let Some(on_failover) = &mut self.on_failover {
+            on_failover(&event);
+        }
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for FailoverPort {
+    /// Failover ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    /// Open both ports so the backup is ready to take over. Start on the backup
+    /// if only the primary fails to open.
+    fn open(&mut self) -> Result<(), OpenError> {
+        let backup = self.backup.open();
+        if let Err(err) = &backup {
+            warn!("Failed to open backup port {}: {}.", self.backup, err);
+        }
+        match self.primary.open() {
+            Ok(()) => Ok(()),
+            Err(err) if backup.is_ok() => {
+                warn!(
+                    "Failed to open primary port {} ({}); starting on {}.",
+                    self.primary, err, self.backup
+                );
+                self.on_backup = true;
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn close(&mut self) {
+        self.primary.close();
+        self.backup.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let Err(err) = self.active().write(frame) else {
+            self.consecutive_failures = 0;
+            return Ok(());
+        };
+        self.consecutive_failures += 1;
+        if self.consecutive_failures < self.failures_before_failover {
+            return Err(err);
+        }
+        self.fail_over(&err);
+        let result = self.active().write(frame);
+        if result.is_err() {
+            self.consecutive_failures += 1;
+        }
+        result
+    }
+
+    /// Black out the active port, without counting failures toward a failover.
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.active().write_blackout(frame)
+    }
+
+    /// Write to the active port, without counting failures toward a failover.
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.active().write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.active().sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        if self.on_backup {
+            self.backup.frame_size_limits()
+        } else {
+            self.primary.frame_size_limits()
+        }
+    }
+
+    /// Limited by the slower of the two ports, since either may be active.
+    fn max_fps(&self) -> Option<f64> {
+        match (self.primary.max_fps(), self.backup.max_fps()) {
+            (Some(primary), Some(backup)) => Some(primary.min(backup)),
+            (primary, backup) => primary.or(backup),
+        }
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        if self.on_backup {
+            self.backup.captured()
+        } else {
+            self.primary.captured()
+        }
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.primary.migrate(from_version);
+        self.backup.migrate(from_version);
+    }
+}
+
+impl fmt::Display for FailoverPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.on_backup {
+            write!(f, "{} (failed over from {})", self.backup, self.primary)
+        } else {
+            write!(f, "{} (backed up by {})", self.primary, self.backup)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+    use crate::ManualClock;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_fails_over_after_consecutive_failures() {
+        let primary = TestPort::named("primary");
+        primary.set_broken(true);
+        let backup = TestPort::named("backup");
+        let clock = Arc::new(ManualClock::new());
+        let (events, received) = mpsc::channel();
+        let mut port = FailoverPort::new(Box::new(primary), Box::new(backup.clone()))
+            .with_failures_before_failover(2)
+            .with_clock(clock.clone())
+            .on_failover(move |event| events.send(event.clone()).unwrap());
+        assert!(port.write(&[0]).is_err());
+        assert!(!port.on_backup());
+        assert!(received.try_recv().is_err());
+        assert!(port.write(&[0]).is_ok());
+        assert!(port.on_backup());
+        let event = received.try_recv().unwrap();
+        assert!(event.to_backup);
+        assert_eq!("primary", event.failed);
+        assert_eq!(WriteError::Disconnected.to_string(), event.error);
+        assert_eq!(clock.now(), event.at);
+        assert!(port.write(&[0]).is_ok());
+        assert_eq!(1, port.failovers());
+        assert_eq!(2, backup.writes());
+        assert_eq!("backup (failed over from primary)", port.to_string());
+    }
+}
@@ -0,0 +1,153 @@
+//! C ABI for listing, opening, and writing to ports.
+//!
+//! Build as a C library with `cargo rustc --release --features ffi --crate-type cdylib`.
+//! The matching declarations are in `include/rust_dmx.h`.
+use std::ffi::c_int;
+use std::ptr;
+use std::slice;
+
+use crate::{available_ports, DmxPort, WriteError};
+
+/// The write succeeded.
+pub const DMX_OK: c_int = 0;
+/// The port is not connected.
+pub const DMX_ERR_DISCONNECTED: c_int = -1;
+/// The port can't keep up with the output.
+pub const DMX_ERR_OVERRUN: c_int = -2;
+/// Any other write error.
+pub const DMX_ERR_OTHER: c_int = -3;
+/// A required pointer argument was null.
+pub const DMX_ERR_NULL: c_int = -4;
+
+/// A listing of available ports. Ports are removed from the listing as they are opened.
+pub struct DmxPortListing(Vec<Option<Box<dyn DmxPort>>>);
+
+/// An open port.
+pub struct DmxPortHandle(Box<dyn DmxPort>);
+
+/// List the available ports.
+/// Return null if the ports could not be listed.
+/// The listing must be freed with dmx_listing_free.
+#[no_mangle]
+pub extern "C" fn dmx_list_ports() -> *mut DmxPortListing {
+    match available_ports() {
+        Ok(ports) => Box::into_raw(Box::new(DmxPortListing(
+            ports.into_iter().map(Some).collect(),
+        ))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Return the number of entries in the listing.
+///
+/// # Safety
+/// listing must be null or a pointer returned by dmx_list_ports that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn dmx_listing_len(listing: *const DmxPortListing) -> usize {
+    listing.as_ref().map(|l| l.0.len()).unwrap_or_default()
+}
+
+/// Copy the name of the port at index into buf as a null-terminated string,
+/// truncating it if buf is too small.
+/// Return the length of the full name, not including the terminator, or 0 if
+/// there is no port at that index.
+///
+/// # Safety
+/// listing must be null or a valid listing, and buf must be null or point to
+/// at least buf_len writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dmx_listing_name(
+    listing: *const DmxPortListing,
+    index: usize,
+    buf: *mut u8,
+    buf_len: usize,
+) -> usize {
+    let Some(Some(port)) = listing.as_ref().and_then(|l| l.0.get(index)) else {
+        return 0;
+    };
+    let name = port.to_string();
+    if !buf.is_null() && buf_len > 0 {
+        let len = name.len().min(buf_len - 1);
+        ptr::copy_nonoverlapping(name.as_ptr(), buf, len);
+        *buf.add(len) = 0;
+    }
+    name.len()
+}
+
+/// Free a listing, including any ports that were not opened.
+///
+/// # Safety
+/// listing must be null or a valid listing, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dmx_listing_free(listing: *mut DmxPortListing) {
+    if !listing.is_null() {
+        drop(Box::from_raw(listing));
+    }
+}
+
+/// Open the port at index, removing it from the listing.
+/// Return null if there is no port at that index or it failed to open.
+/// The port must be released with dmx_close.
+///
+/// # Safety
+/// listing must be null or a valid listing.
+#[no_mangle]
+pub unsafe extern "C" fn dmx_open(
+    listing: *mut DmxPortListing,
+    index: usize,
+) -> *mut DmxPortHandle {
+    let Some(slot) = listing.as_mut().and_then(|l| l.0.get_mut(index)) else {
+        return ptr::null_mut();
+    };
+    let Some(mut port) = slot.take() else {
+        return ptr::null_mut();
+    };
+    if port.open().is_err() {
+        *slot = Some(port);
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(DmxPortHandle(port)))
+}
+
+/// Write a frame of len channel levels to the port.
+/// Return DMX_OK or one of the DMX_ERR codes.
+///
+/// # Safety
+/// port must be null or a handle returned by dmx_open that has not been closed,
+/// and frame must be null or point to at least len readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn dmx_write(
+    port: *mut DmxPortHandle,
+    frame: *const u8,
+    len: usize,
+) -> c_int {
+    let Some(port) = port.as_mut() else {
+        return DMX_ERR_NULL;
+    };
+    if frame.is_null() && len > 0 {
+        return DMX_ERR_NULL;
+    }
+    let frame = if len == 0 {
+        &[][..]
+    } else {
+        slice::from_raw_parts(frame, len)
+    };
+    match port.0.write(frame) {
+        Ok(()) => DMX_OK,
+        Err(WriteError::Disconnected) => DMX_ERR_DISCONNECTED,
+        Err(WriteError::Overrun) => DMX_ERR_OVERRUN,
+        Err(WriteError::Other(_)) => DMX_ERR_OTHER,
+    }
+}
+
+/// Close the port and free the handle.
+///
+/// # Safety
+/// port must be null or a valid handle, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dmx_close(port: *mut DmxPortHandle) {
+    if !port.is_null() {
+        let mut port = Box::from_raw(port);
+        port.0.close();
+    }
+}
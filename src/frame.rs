@@ -0,0 +1,98 @@
+//! A DMX frame and utilities for comparing frames.
+use std::fmt;
+use std::ops::Deref;
+
+/// The channel levels of one universe, starting with channel 1.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Frame(Vec<u8>);
+
+impl Frame {
+    /// Iterate over the channels whose levels differ between this frame and
+    /// other, as (channel, level in this frame, level in other).
+    /// Channels are numbered from 0, and channels beyond the end of the shorter
+    /// frame are treated as 0.
+    pub fn diff<'a>(&'a self, other: &'a Frame) -> impl Iterator<Item = (usize, u8, u8)> + 'a {
+        let len = self.len().max(other.len());
+        (0..len).filter_map(|i| {
+            let (old, new) = (level(self, i), level(other, i));
+            (old != new).then_some((i, old, new))
+        })
+    }
+
+    /// Return a human-readable description of how other differs from this
+    /// frame, such as "1: 0 -> 255, 12: 30 -> 0". Channels are numbered from 1.
+    pub fn display_diff<'a>(&'a self, other: &'a Frame) -> FrameDiff<'a> {
+        FrameDiff {
+            old: self,
+            new: other,
+        }
+    }
+
+    /// Unwrap this frame into its levels.
+    pub fn into_levels(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+fn level(frame: &[u8], channel: usize) -> u8 {
+    frame.get(channel).copied().unwrap_or_default()
+}
+
+impl Deref for Frame {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Frame {
+    fn from(levels: Vec<u8>) -> Self {
+        Self(levels)
+    }
+}
+
+impl From<&[u8]> for Frame {
+    fn from(levels: &[u8]) -> Self {
+        Self(levels.to_vec())
+    }
+}
+
+/// The changes between two frames, formatted for logging.
+pub struct FrameDiff<'a> {
+    old: &'a Frame,
+    new: &'a Frame,
+}
+
+impl fmt::Display for FrameDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut changes = self.old.diff(self.new).peekable();
+        if changes.peek().is_none() {
+            return write!(f, "no changes");
+        }
+        for (i, (channel, old, new)) in changes.enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {} -> {}", channel + 1, old, new)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = Frame::from(vec![0, 10, 20]);
+        let new = Frame::from(vec![0, 15, 20, 0, 7]);
+        assert_eq!(
+            vec![(1, 10, 15), (4, 0, 7)],
+            old.diff(&new).collect::<Vec<_>>()
+        );
+        assert_eq!("2: 10 -> 15, 5: 0 -> 7", old.display_diff(&new).to_string());
+        assert_eq!("no changes", old.display_diff(&old).to_string());
+    }
+}
@@ -0,0 +1,59 @@
+//! Entry points for the cargo-fuzz targets in fuzz/, which exercise the
+//! parsers that consume untrusted input. Only built with cfg(fuzzing).
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Decode widget messages from data until it runs out. The first byte selects
+/// the message type to look for.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn enttec_read_packet(data: &[u8]) {
+    let Some((&message_type, mut r)) = data.split_first() else {
+        return;
+    };
+    let mut pending = Vec::new();
+    while let Ok(Some(_)) =
+        crate::enttec::read_packet(message_type, &mut r, &mut pending, Instant::now())
+    {}
+}
+
+/// Parse data as an sACN data packet.
+pub fn sacn_parse_data_packet(data: &[u8]) {
+    let _ = crate::sacn::parse_data_packet(data);
+}
+
+/// Decode data as an ArtDmx packet.
+pub fn artnet_decode_dmx(data: &[u8]) {
+    let _ = crate::artnet::codec::decode_dmx(data);
+}
+
+/// Decode data as an ArtPollReply packet.
+pub fn artnet_decode_poll_reply(data: &[u8]) {
+    let _ = crate::artnet::codec::decode_poll_reply(data);
+}
+
+/// Decode data as an ArtTodData packet.
+pub fn artnet_decode_tod_data(data: &[u8]) {
+    let _ = crate::artnet::codec::decode_tod_data(data);
+}
+
+/// Decode data as an ArtRdm packet.
+pub fn artnet_decode_rdm(data: &[u8]) {
+    let _ = crate::artnet::codec::decode_rdm(data);
+}
+
+/// Decode data as an RDM response, and as a discovery response.
+pub fn rdm_decode_response(data: &[u8]) {
+    let _ = crate::rdm::Response::decode(data);
+    let _ = crate::rdm::decode_discovery_response(data);
+}
+
+/// Decode data as the parameter data of each response type with structured
+/// parameter data.
+pub fn rdm_decode_parameter_data(data: &[u8]) {
+    use crate::rdm::{DeviceInfo, SensorDefinition, SensorValue, StatusMessage};
+
+    let _ = DeviceInfo::decode(data);
+    let _ = SensorDefinition::decode(data);
+    let _ = SensorValue::decode(data);
+    let _ = StatusMessage::decode_all(data);
+}
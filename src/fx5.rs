@@ -0,0 +1,217 @@
+//! Support for the Digital Enlightenment FX5 family of USB DMX interfaces,
+//! such as the Nodle U1.
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use rust_dmx_core::fx5::{
+    encode_block, encode_mode, BLOCK_SIZE, FX5_USB_IDS, MODE_PC_OUT, REPORT_SIZE,
+};
+
+use crate::hid::{list_devices, HidConnection, HidDeviceId, HidTransport};
+use crate::{DmxPort, OpenError, PortListing, WriteError, DMX_UNIVERSE_SIZE};
+
+/// Send a universe from an interface of the Digital Enlightenment FX5
+/// family, such as the Nodle U1, DMXControl's common USB HID interface.
+///
+/// The interface holds all 512 channels and keeps outputting them, so only
+/// the blocks of 32 channels that changed since the last frame are sent.
+/// Channels beyond the end of a short frame are set to 0.
+#[derive(Serialize, Deserialize)]
+pub struct Fx5DmxPort {
+    device: HidDeviceId,
+    #[serde(skip)]
+    connection: HidConnection,
+    /// The levels the interface holds, if they are known.
+    #[serde(skip)]
+    sent: Option<Vec<u8>>,
+}
+
+impl Fx5DmxPort {
+    /// Create a port for an interface. The port is not opened yet.
+    pub fn new(device: HidDeviceId) -> Self {
+        Self {
+            device,
+            connection: HidConnection::default(),
+            sent: None,
+        }
+    }
+
+    /// Create a port that talks to the interface through transports from
+    /// opener instead of the HID device. The port is not opened yet.
+    pub fn with_transport(
+        device: HidDeviceId,
+        opener: impl FnMut(&HidDeviceId) -> Result<Box<dyn HidTransport>, OpenError> + Send + 'static,
+    ) -> Self {
+        Self {
+            connection: HidConnection::with_opener(Box::new(opener)),
+            ..Self::new(device)
+        }
+    }
+
+    /// Send the blocks of frame that the interface doesn't hold yet,
+    /// reopening the port if needed.
+    fn send(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        if !self.connection.is_open() {
+            if let Err(err) = DmxPort::open(self) {
+                debug!("Failed to reopen DMX port {}: {:#?}.", self, err);
+                return Err(WriteError::Disconnected);
+            }
+        }
+        let mut levels = frame[..frame.len().min(DMX_UNIVERSE_SIZE)].to_vec();
+        levels.resize(DMX_UNIVERSE_SIZE, 0);
+        let mut buf = [0; REPORT_SIZE];
+        for (block, chunk) in levels.chunks(BLOCK_SIZE).enumerate() {
+            let held = self
+                .sent
+                .as_ref()
+                .is_some_and(|sent| sent[block * BLOCK_SIZE..][..BLOCK_SIZE] == *chunk);
+            if held {
+                continue;
+            }
+            encode_block(block as u8, chunk, &mut buf).expect("buffer holds a report");
+            if let Err(err) = self.connection.write_report(&buf) {
+                // Some blocks may have been sent; send them all next time.
+                self.sent = None;
+                return Err(err);
+            }
+        }
+        self.sent = Some(levels);
+        Ok(())
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for Fx5DmxPort {
+    /// Return a port for each interface connected to this system.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(list_devices(&FX5_USB_IDS)?
+            .into_iter()
+            .map(|device| Box::new(Self::new(device)) as Box<dyn DmxPort>)
+            .collect())
+    }
+
+    /// Open the interface and have it output the levels it is sent.
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.connection.is_open() {
+            return Ok(());
+        }
+        self.connection.open(&self.device)?;
+        self.sent = None;
+        let mut buf = [0; REPORT_SIZE];
+        encode_mode(MODE_PC_OUT, &mut buf).expect("buffer holds a report");
+        if let Err(err) = self.connection.write_report(&buf) {
+            self.connection.close();
+            return Err(OpenError::Other(err.into()));
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.connection.close();
+        self.sent = None;
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.send(frame)
+    }
+
+    /// The levels the interface holds, all 512 channels of them.
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.sent.clone()
+    }
+}
+
+impl fmt::Display for Fx5DmxPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FX5 USB DMX {}", self.device)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the reports written to it, or fails once broken.
+    struct Recorder {
+        reports: Arc<Mutex<Vec<Vec<u8>>>>,
+        broken: Arc<Mutex<bool>>,
+    }
+
+    impl HidTransport for Recorder {
+        fn write_report(&mut self, report: &[u8]) -> io::Result<()> {
+            if *self.broken.lock().unwrap() {
+                return Err(io::Error::other("unplugged"));
+            }
+            self.reports.lock().unwrap().push(report.to_vec());
+            Ok(())
+        }
+    }
+
+    fn device() -> HidDeviceId {
+        HidDeviceId {
+            vendor_id: 0x16C0,
+            product_id: 0x088B,
+            serial_number: Some("1234".to_string()),
+            path: "/dev/hidraw0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sends_changed_blocks() -> Result<(), Box<dyn std::error::Error>> {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let broken = Arc::new(Mutex::new(false));
+        let mut port = Fx5DmxPort::with_transport(device(), {
+            let reports = reports.clone();
+            let broken = broken.clone();
+            move |_| {
+                Ok(Box::new(Recorder {
+                    reports: reports.clone(),
+                    broken: broken.clone(),
+                }) as Box<dyn HidTransport>)
+            }
+        });
+        let sent = || -> Vec<(u8, u8)> {
+            reports
+                .lock()
+                .unwrap()
+                .drain(..)
+                .map(|report| (report[1], report[2]))
+                .collect()
+        };
+        port.open()?;
+        assert_eq!(vec![(16, MODE_PC_OUT)], sent());
+
+        // The first frame sets every block.
+        port.write(&[1; 2])?;
+        let first = sent();
+        assert_eq!(16, first.len());
+        assert_eq!((0, 1), first[0]);
+
+        let mut frame = vec![1; 2];
+        frame.resize(70, 0);
+        frame[69] = 5;
+        port.write(&frame)?;
+        assert_eq!(vec![(2, 0)], sent());
+
+        // A failed write closes the port, and reopening sets everything again.
+        *broken.lock().unwrap() = true;
+        assert!(matches!(port.write(&[2]), Err(WriteError::Disconnected)));
+        *broken.lock().unwrap() = false;
+        port.write(&frame)?;
+        let reopened = sent();
+        assert_eq!((16, MODE_PC_OUT), reopened[0]);
+        assert_eq!(17, reopened.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trips_through_config() -> anyhow::Result<()> {
+        let port: Box<dyn DmxPort> = Box::new(Fx5DmxPort::new(device()));
+        let port: Box<dyn DmxPort> = serde_json::from_str(&serde_json::to_string(&port)?)?;
+        assert_eq!("FX5 USB DMX 1234", port.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+//! USB HID interfaces, which take DMX as fixed-size reports rather than as a
+//! byte stream.
+use anyhow::anyhow;
+use hidapi::{DeviceInfo, HidApi, HidDevice};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+
+use crate::{OpenError, WriteError};
+
+/// The reports to an interface.
+///
+/// This is a HID device in normal use; tests and simulators can provide their
+/// own to check exactly what the port sends.
+pub trait HidTransport: Send {
+    /// Send one output report, starting with its report ID.
+    fn write_report(&mut self, report: &[u8]) -> io::Result<()>;
+}
+
+impl HidTransport for HidDevice {
+    fn write_report(&mut self, report: &[u8]) -> io::Result<()> {
+        self.write(report).map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// Opens the transport to an interface. Called whenever the port is opened
+/// or reopened.
+pub type HidOpener =
+    Box<dyn FnMut(&HidDeviceId) -> Result<Box<dyn HidTransport>, OpenError> + Send>;
+
+/// A HID interface, as listed and saved.
+///
+/// A saved interface is found again by its serial number if it has one, so it
+/// can be plugged into another USB port; interfaces without one are found by
+/// the path they were listed at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HidDeviceId {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    #[serde(default)]
+    pub serial_number: Option<String>,
+    pub path: String,
+}
+
+impl HidDeviceId {
+    fn matches(&self, info: &DeviceInfo) -> bool {
+        if (info.vendor_id(), info.product_id()) != (self.vendor_id, self.product_id) {
+            return false;
+        }
+        match &self.serial_number {
+            Some(serial_number) => info.serial_number() == Some(serial_number.as_str()),
+            None => info.path().to_string_lossy() == self.path,
+        }
+    }
+}
+
+impl fmt::Display for HidDeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.serial_number {
+            Some(serial_number) => write!(f, "{serial_number}"),
+            None => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// The HID library's context, kept between calls so that its device list
+/// only needs refreshing.
+static HID_API: Mutex<Option<HidApi>> = Mutex::new(None);
+
+/// Run f with the HID library's context, its device list up to date.
+fn with_devices<T>(f: impl FnOnce(&HidApi) -> T) -> anyhow::Result<T> {
+    let mut context = HID_API.lock().unwrap();
+    let api = match context.take() {
+        Some(mut api) => {
+            api.refresh_devices()?;
+            api
+        }
+        None => HidApi::new()?,
+    };
+    Ok(f(context.insert(api)))
+}
+
+/// List the interfaces with any of the given USB vendor and product IDs, in
+/// an order that doesn't depend on how the OS enumerated them.
+pub(crate) fn list_devices(usb_ids: &[(u16, u16)]) -> anyhow::Result<Vec<HidDeviceId>> {
+    let mut devices = with_devices(|api| {
+        api.device_list()
+            .filter(|info| usb_ids.contains(&(info.vendor_id(), info.product_id())))
+            .map(|info| HidDeviceId {
+                vendor_id: info.vendor_id(),
+                product_id: info.product_id(),
+                serial_number: info
+                    .serial_number()
+                    .filter(|serial_number| !serial_number.is_empty())
+                    .map(str::to_string),
+                path: info.path().to_string_lossy().into_owned(),
+            })
+            .collect::<Vec<_>>()
+    })?;
+    devices.sort_by(|a, b| (&a.serial_number, &a.path).cmp(&(&b.serial_number, &b.path)));
+    // An interface with several HID interfaces is listed once for each.
+    devices.dedup_by(|a, b| a.serial_number.is_some() && a.serial_number == b.serial_number);
+    Ok(devices)
+}
+
+/// Open an interface.
+fn open_device(device: &HidDeviceId) -> Result<Box<dyn HidTransport>, OpenError> {
+    let opened = with_devices(|api| {
+        let info = api.device_list().find(|info| device.matches(info))?;
+        Some(info.open_device(api))
+    })?;
+    match opened {
+        Some(Ok(opened)) => Ok(Box::new(opened)),
+        Some(Err(err)) => Err(anyhow!("failed to open HID device {device}: {err}").into()),
+        None => Err(OpenError::NotConnected),
+    }
+}
+
+/// The transport to an interface, while its port is open.
+#[derive(Default)]
+pub(crate) struct HidConnection {
+    transport: Option<Box<dyn HidTransport>>,
+    /// Opens the transport in place of the HID device, if set.
+    opener: Option<HidOpener>,
+}
+
+impl HidConnection {
+    /// Open transports with opener instead of the HID device.
+    pub(crate) fn with_opener(opener: HidOpener) -> Self {
+        Self {
+            transport: None,
+            opener: Some(opener),
+        }
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    /// Open the transport to device, if it isn't open.
+    pub(crate) fn open(&mut self, device: &HidDeviceId) -> Result<(), OpenError> {
+        if self.transport.is_some() {
+            return Ok(());
+        }
+        self.transport = Some(match &mut self.opener {
+            Some(opener) => opener(device)?,
+            None => open_device(device)?,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.transport = None;
+    }
+
+    /// Send a report. An interface that can't be written to has most likely
+    /// been unplugged, so the transport is closed to be reopened later.
+    pub(crate) fn write_report(&mut self, report: &[u8]) -> Result<(), WriteError> {
+        let transport = self.transport.as_mut().ok_or(WriteError::Disconnected)?;
+        if let Err(err) = transport.write_report(report) {
+            debug!("Failed to write a HID report: {err}.");
+            self.transport = None;
+            return Err(WriteError::Disconnected);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! Just enough HTTP/1.1 for the crate's network-facing ports.
+// The ports and the daemon each use part of this, so with only some of their
+// features enabled the rest goes unused.
+#![cfg_attr(
+    not(all(feature = "websocket", feature = "sse", feature = "daemon")),
+    allow(dead_code)
+)]
+use anyhow::anyhow;
+use log::{debug, info};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Reject request heads larger than this.
+const MAX_HEAD_SIZE: usize = 8192;
+
+/// How often the accept thread checks whether it should stop.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Give up on a client that doesn't send its request in this time.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Drop a client that can't accept a message in this time, so one slow client
+/// can't stall the output.
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// The request line and headers of an HTTP request.
+pub(crate) struct RequestHead {
+    pub method: String,
+    pub path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl RequestHead {
+    /// Return the value of the named header, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Error, Debug)]
+pub(crate) enum HeadError {
+    #[error("request head too large")]
+    TooLarge,
+    #[error("malformed request line")]
+    Malformed,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Read the request line and headers of a request, leaving any body unread.
+pub(crate) fn read_head<R: BufRead>(reader: &mut R) -> Result<RequestHead, HeadError> {
+    let mut head_size = 0;
+    let mut read_line = |reader: &mut R| -> Result<String, HeadError> {
+        let mut line = String::new();
+        head_size += reader.read_line(&mut line)?;
+        if head_size > MAX_HEAD_SIZE {
+            return Err(HeadError::TooLarge);
+        }
+        Ok(line.trim_end().to_string())
+    };
+
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(HeadError::Malformed);
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok(RequestHead {
+        method,
+        path,
+        headers,
+    })
+}
+
+/// Complete a client's upgrade to a long-lived stream by writing the response head.
+pub(crate) type Upgrade = fn(&TcpStream, &RequestHead) -> anyhow::Result<()>;
+
+/// Accept HTTP clients on a background thread, upgrade their GET requests to
+/// long-lived streams, and broadcast messages to all of them.
+pub(crate) struct StreamServer {
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StreamServer {
+    /// Start listening on addr. The name is used for logging.
+    pub fn start(addr: &str, name: &'static str, upgrade: Upgrade) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        info!("{} port listening on {}.", name, listener.local_addr()?);
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (clients, stop) = (clients.clone(), stop.clone());
+            thread::spawn(move || accept_clients(listener, name, upgrade, &clients, &stop))
+        };
+        Ok(Self {
+            clients,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Return the number of currently connected clients.
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Write a message to every client, dropping any that fail.
+    pub fn broadcast(&self, message: &[u8]) {
+        self.clients.lock().unwrap().retain_mut(|client| {
+            if let Err(err) = client.write_all(message) {
+                debug!("Dropping stream client: {}.", err);
+                return false;
+            }
+            true
+        });
+    }
+}
+
+impl Drop for StreamServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn accept_clients(
+    listener: TcpListener,
+    name: &str,
+    upgrade: Upgrade,
+    clients: &Mutex<Vec<TcpStream>>,
+    stop: &AtomicBool,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, peer)) => match accept_client(&stream, upgrade) {
+                Ok(path) => {
+                    debug!("{} client connected to {} from {}.", name, path, peer);
+                    clients.lock().unwrap().push(stream);
+                }
+                Err(err) => debug!("{} handshake with {} failed: {}.", name, peer, err),
+            },
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+            Err(err) => debug!("Failed to accept {} client: {}.", name, err),
+        }
+    }
+}
+
+/// Read a client's request and upgrade it. Return the path it requested.
+fn accept_client(stream: &TcpStream, upgrade: Upgrade) -> anyhow::Result<String> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let head = read_head(&mut BufReader::new(stream))?;
+    if head.method != "GET" {
+        return Err(anyhow!("unexpected {} request", head.method));
+    }
+    upgrade(stream, &head)?;
+    stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+    Ok(head.path)
+}
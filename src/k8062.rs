@@ -0,0 +1,136 @@
+//! Support for the Velleman K8062 USB DMX interface.
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use rust_dmx_core::k8062::{encode_frame, K8062_USB_ID, MAX_FRAME_SIZE, MIN_CHANNELS, REPORT_SIZE};
+
+use crate::hid::{list_devices, HidConnection, HidDeviceId, HidTransport};
+use crate::{DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError, DMX_UNIVERSE_SIZE};
+
+/// Send a universe from a Velleman K8062, also sold as the HQ-Power VM116.
+///
+/// Each frame is sent as a run of small reports, skipping runs of zero
+/// channels, so mostly dark frames go out fastest. Frames shorter than 8
+/// channels are padded with zeros.
+#[derive(Serialize, Deserialize)]
+pub struct K8062DmxPort {
+    device: HidDeviceId,
+    #[serde(skip)]
+    connection: HidConnection,
+}
+
+impl K8062DmxPort {
+    /// Create a port for an interface. The port is not opened yet.
+    pub fn new(device: HidDeviceId) -> Self {
+        Self {
+            device,
+            connection: HidConnection::default(),
+        }
+    }
+
+    /// Create a port that talks to the interface through transports from
+    /// opener instead of the HID device. The port is not opened yet.
+    pub fn with_transport(
+        device: HidDeviceId,
+        opener: impl FnMut(&HidDeviceId) -> Result<Box<dyn HidTransport>, OpenError> + Send + 'static,
+    ) -> Self {
+        Self {
+            device,
+            connection: HidConnection::with_opener(Box::new(opener)),
+        }
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for K8062DmxPort {
+    /// Return a port for each interface connected to this system.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(list_devices(&[K8062_USB_ID])?
+            .into_iter()
+            .map(|device| Box::new(Self::new(device)) as Box<dyn DmxPort>)
+            .collect())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.connection.open(&self.device)
+    }
+
+    fn close(&mut self) {
+        self.connection.close();
+    }
+
+    /// Send the frame, reopening the port if needed.
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        if !self.connection.is_open() {
+            if let Err(err) = DmxPort::open(self) {
+                debug!("Failed to reopen DMX port {}: {:#?}.", self, err);
+                return Err(WriteError::Disconnected);
+            }
+        }
+        let mut levels = frame[..frame.len().min(DMX_UNIVERSE_SIZE)].to_vec();
+        levels.resize(levels.len().max(MIN_CHANNELS), 0);
+        let mut buf = [0; MAX_FRAME_SIZE];
+        let len = encode_frame(&levels, &mut buf).expect("buffer holds a frame");
+        for report in buf[..len].chunks(REPORT_SIZE) {
+            self.connection.write_report(report)?;
+        }
+        Ok(())
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        FrameSizeLimits {
+            min: MIN_CHANNELS,
+            max: DMX_UNIVERSE_SIZE,
+        }
+    }
+}
+
+impl fmt::Display for K8062DmxPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Velleman K8062 {}", self.device)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the reports written to it.
+    struct Recorder(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl HidTransport for Recorder {
+        fn write_report(&mut self, report: &[u8]) -> io::Result<()> {
+            self.0.lock().unwrap().push(report.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sends_padded_frames() -> Result<(), Box<dyn std::error::Error>> {
+        let device = HidDeviceId {
+            vendor_id: K8062_USB_ID.0,
+            product_id: K8062_USB_ID.1,
+            serial_number: None,
+            path: "/dev/hidraw1".to_string(),
+        };
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut port = K8062DmxPort::with_transport(device, {
+            let reports = reports.clone();
+            move |_| Ok(Box::new(Recorder(reports.clone())) as Box<dyn HidTransport>)
+        });
+        assert_eq!("Velleman K8062 /dev/hidraw1", port.to_string());
+        // Writing opens the port.
+        port.write(&[0, 7])?;
+        assert_eq!(
+            vec![
+                vec![0, 4, 2, 7, 0, 0, 0, 0, 0],
+                vec![0, 3, 0, 0, 0, 0, 0, 0, 0],
+            ],
+            *reports.lock().unwrap()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+//! Resend a port's last frame from a background thread while it is idle.
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{Clock, Wakeup};
+
+/// What the keep-alive thread knows about the frames a port has sent.
+#[derive(Debug)]
+struct State {
+    /// The last frame the port sent, if any.
+    frame: Option<Vec<u8>>,
+    last_sent: Instant,
+    stop: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    wakeup: Wakeup,
+    clock: Arc<dyn Clock>,
+}
+
+/// Resend the last frame a port sent whenever interval passes without it
+/// sending another. The thread is stopped when this is dropped.
+///
+/// The thread decides to resend and resends while holding the state that
+/// `sending` updates, so a frame the port records afterwards always goes on
+/// the wire after the resent one, never before it.
+#[derive(Debug)]
+pub(crate) struct KeepAlive {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl KeepAlive {
+    /// Start resending through resend every interval that passes idle on clock.
+    pub(crate) fn start(
+        interval: Duration,
+        clock: Arc<dyn Clock>,
+        resend: impl FnMut(&[u8]) + Send + 'static,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                frame: None,
+                last_sent: clock.now(),
+                stop: false,
+            }),
+            wakeup: Wakeup::new(),
+            clock,
+        });
+        let thread = {
+            let shared = shared.clone();
+            thread::spawn(move || run(interval, &shared, resend))
+        };
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Record that the port is about to send frame. Call this before the
+    /// frame goes on the wire.
+    pub(crate) fn sending(&self, frame: &[u8]) {
+        let mut state = self.shared.state.lock().unwrap();
+        let first = state.frame.is_none();
+        state.frame = Some(frame.to_vec());
+        state.last_sent = self.shared.clock.now();
+        drop(state);
+        if first {
+            self.shared.wakeup.wake();
+        }
+    }
+}
+
+impl Drop for KeepAlive {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stop = true;
+        self.shared.wakeup.wake();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(interval: Duration, shared: &Shared, mut resend: impl FnMut(&[u8])) {
+    let mut state = shared.state.lock().unwrap();
+    while !state.stop {
+        let now = shared.clock.now();
+        let due = state.last_sent + interval;
+        match &state.frame {
+            Some(frame) if now >= due => {
+                resend(frame);
+                state.last_sent = now;
+            }
+            frame => {
+                // With nothing sent yet, wait until the first frame is.
+                let deadline = frame.is_some().then_some(due);
+                drop(state);
+                shared.clock.wait_until(deadline, &shared.wakeup);
+                state = shared.state.lock().unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ManualClock;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_resends_only_when_idle() {
+        let clock = Arc::new(ManualClock::new());
+        let (resent, receiver) = mpsc::channel();
+        let keep_alive = KeepAlive::start(Duration::from_millis(20), clock.clone(), move |frame| {
+            resent.send(frame.to_vec()).unwrap();
+        });
+        keep_alive.sending(&[1]);
+        assert_eq!(
+            clock.now() + Duration::from_millis(20),
+            clock.wait_for_deadline()
+        );
+
+        // A frame sent before the interval passes pushes the resend back.
+        clock.advance(Duration::from_millis(10));
+        keep_alive.sending(&[2]);
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(
+            clock.now() + Duration::from_millis(10),
+            clock.wait_for_deadline()
+        );
+        assert!(receiver.try_recv().is_err());
+
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(vec![2], receiver.recv().unwrap());
+        assert_eq!(
+            clock.now() + Duration::from_millis(20),
+            clock.wait_for_deadline()
+        );
+    }
+}
@@ -0,0 +1,229 @@
+//! Ports that scale or remap every level before writing it.
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+use crate::{DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError};
+
+/// Scale every level by a grand master before writing it to the inner port.
+///
+/// Unlike a `TransformPort` doing the same, the master is saved with the port.
+#[derive(Serialize, Deserialize)]
+pub struct MasterPort {
+    inner: Box<dyn DmxPort>,
+    /// From 0.0 for blackout to 1.0 for unchanged.
+    master: f64,
+    #[serde(skip)]
+    buffer: Vec<u8>,
+}
+
+impl MasterPort {
+    /// Wrap inner, scaling every level by master, from 0.0 to 1.0.
+    pub fn new(inner: Box<dyn DmxPort>, master: f64) -> Self {
+        Self {
+            inner,
+            master: master.clamp(0.0, 1.0),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Return the master, from 0.0 to 1.0.
+    pub fn master(&self) -> f64 {
+        self.master
+    }
+
+    /// Change the master, from 0.0 to 1.0. It applies from the next write.
+    pub fn set_master(&mut self, master: f64) {
+        self.master = master.clamp(0.0, 1.0);
+    }
+
+    /// Unwrap this port into the inner port.
+    pub fn into_inner(self) -> Box<dyn DmxPort> {
+        self.inner
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for MasterPort {
+    /// Master ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.inner.open()
+    }
+
+    fn close(&mut self) {
+        self.inner.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        // Reuse the buffer to avoid allocating on every frame.
+        self.buffer.clear();
+        self.buffer.extend(
+            frame
+                .iter()
+                .map(|&level| (level as f64 * self.master).round() as u8),
+        );
+        self.inner.write(&self.buffer)
+    }
+
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_blackout(frame)
+    }
+
+    /// Alternate start code packets aren't levels, so they aren't scaled.
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.inner.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.inner.max_fps()
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.migrate(from_version);
+    }
+}
+
+impl fmt::Display for MasterPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (master at {:.0}%)", self.inner, self.master * 100.0)
+    }
+}
+
+/// Map every level through a lookup table, such as a dimmer or gamma curve,
+/// before writing it to the inner port. The curve is saved with the port.
+#[derive(Serialize, Deserialize)]
+pub struct CurvePort {
+    inner: Box<dyn DmxPort>,
+    /// The level to send for each level written.
+    #[serde(deserialize_with = "deserialize_curve")]
+    curve: Vec<u8>,
+    #[serde(skip)]
+    buffer: Vec<u8>,
+}
+
+impl CurvePort {
+    /// Wrap inner, sending curve[level] for every level written.
+    pub fn new(inner: Box<dyn DmxPort>, curve: [u8; 256]) -> Self {
+        Self {
+            inner,
+            curve: curve.to_vec(),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Unwrap this port into the inner port.
+    pub fn into_inner(self) -> Box<dyn DmxPort> {
+        self.inner
+    }
+}
+
+/// Load a curve, which must have an entry for every level.
+fn deserialize_curve<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let curve = Vec::<u8>::deserialize(deserializer)?;
+    if curve.len() != 256 {
+        return Err(de::Error::invalid_length(curve.len(), &"256 levels"));
+    }
+    Ok(curve)
+}
+
+#[typetag::serde]
+impl DmxPort for CurvePort {
+    /// Curve ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.inner.open()
+    }
+
+    fn close(&mut self) {
+        self.inner.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        // Reuse the buffer to avoid allocating on every frame.
+        self.buffer.clear();
+        self.buffer
+            .extend(frame.iter().map(|&level| self.curve[level as usize]));
+        self.inner.write(&self.buffer)
+    }
+
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_blackout(frame)
+    }
+
+    /// Alternate start code packets aren't levels, so they aren't mapped.
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.inner.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.inner.max_fps()
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.migrate(from_version);
+    }
+}
+
+impl fmt::Display for CurvePort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (curved)", self.inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+
+    #[test]
+    fn test_levels_are_saved_with_the_port() -> Result<(), Box<dyn std::error::Error>> {
+        let inner = TestPort::default();
+        let mut inverted = [0; 256];
+        for (level, inverse) in inverted.iter_mut().enumerate() {
+            *inverse = 255 - level as u8;
+        }
+        let curve = CurvePort::new(Box::new(inner.clone()), inverted);
+        let mut port: Box<dyn DmxPort> = Box::new(MasterPort::new(Box::new(curve), 0.5));
+        port.write(&[0, 200])?;
+        assert_eq!(vec![vec![255, 155]], inner.frames());
+
+        let saved = serde_json::to_string(&port)?;
+        let loaded: Box<dyn DmxPort> = serde_json::from_str(&saved)?;
+        assert_eq!("test (curved) (master at 50%)", loaded.to_string());
+        assert_eq!(saved, serde_json::to_string(&loaded)?);
+
+        let short_curve = saved.replace("[255,254,", "[");
+        assert!(serde_json::from_str::<Box<dyn DmxPort>>(&short_curve).is_err());
+        Ok(())
+    }
+}
@@ -1,19 +1,133 @@
 use io::Write;
+use log::warn;
 use std::fmt;
 use std::io;
+use std::path::Path;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{panic, thread};
 use thiserror::Error;
 
+pub mod artnet;
+mod clock;
+mod config;
+mod conflict;
+#[cfg(feature = "daemon")]
+pub mod daemon;
+mod dedup;
+mod dual_write;
+#[cfg(not(target_arch = "wasm32"))]
 mod enttec;
+pub use rust_dmx_core::enttec as enttec_codec;
+mod ext;
+mod failover;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod frame;
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzz;
+#[cfg(all(feature = "hidapi", not(target_arch = "wasm32")))]
+mod fx5;
+#[cfg(all(feature = "hidapi", not(target_arch = "wasm32")))]
+mod hid;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(all(feature = "hidapi", not(target_arch = "wasm32")))]
+mod k8062;
+mod keep_alive;
+mod levels;
+mod monitor;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod net;
+mod observer;
 mod offline;
+#[cfg(feature = "osc")]
+mod osc;
+mod rate_limit;
+pub mod rdm;
+mod refresh;
+mod registry;
+mod reload;
+mod sacn;
+mod safety;
+mod sender;
+mod shutdown;
+#[cfg(feature = "sse")]
+mod sse;
+mod tee;
+#[cfg(test)]
+mod test_port;
+mod text;
+mod transform;
+mod universe;
+#[cfg(feature = "websocket")]
+mod websocket;
 
-pub use enttec::EnttecDmxPort;
+pub use artnet::{
+    ArtnetDiscovery, ArtnetDmxPort, ArtnetEvent, ArtnetInputPort, ArtnetWatcher, PortAddress,
+    UdpTransport, UdpTransportOpener,
+};
+pub use clock::{system_clock, Clock, ManualClock, SystemClock, Wakeup};
+pub use config::{VersionedPort, CONFIG_VERSION};
+pub use conflict::{Conflict, ConflictEvent, ConflictListener, CONFLICT_TIMEOUT};
+pub use dedup::DedupPort;
+pub use dual_write::DualWritePort;
+#[cfg(not(target_arch = "wasm32"))]
+pub use enttec::{
+    enttec_usb_matches, set_enttec_usb_matches, EnttecDmxPort, EnttecReceiveErrors,
+    EnttecStoredParams, Mk2Api, SerialTransport, TransportOpener, UsbMatch, WidgetOutput,
+};
+pub use ext::{DmxPortExt, IntoDmxPort};
+pub use failover::{FailoverEvent, FailoverPort};
+pub use frame::{Frame, FrameDiff};
+#[cfg(all(feature = "hidapi", not(target_arch = "wasm32")))]
+pub use fx5::Fx5DmxPort;
+#[cfg(all(feature = "hidapi", not(target_arch = "wasm32")))]
+pub use hid::{HidDeviceId, HidOpener, HidTransport};
+#[cfg(all(feature = "hidapi", not(target_arch = "wasm32")))]
+pub use k8062::K8062DmxPort;
+pub use levels::{CurvePort, MasterPort};
+pub use monitor::InputMonitor;
+#[cfg(feature = "mqtt")]
+pub use mqtt::{MqttDmxPort, MqttPayload};
+pub use observer::{Protocol, SourceTraffic, TrafficObserver, UniverseTraffic};
 pub use offline::OfflineDmxPort;
+#[cfg(feature = "osc")]
+pub use osc::OscDmxPort;
+pub use rate_limit::RateLimitPort;
+pub use refresh::RefreshPort;
+pub use registry::{
+    emergency_blackout_senders, release_senders_blackout, PortConfig, PortRegistry, ReloadReport,
+};
+pub use reload::ConfigWatcher;
+pub use sacn::{SacnDmxPort, SacnInputPort, SacnMergeMode, SacnSource};
+pub use safety::{SafetyPort, SafetyRule};
+pub use sender::{BackgroundSender, FrameWatch, QueuePolicy, SenderConfig, SenderMetrics};
+pub use shutdown::BlackoutOnClosePort;
+#[cfg(feature = "sse")]
+pub use sse::SseDmxPort;
+pub use tee::TeePort;
+pub use text::{text_packet, TEXT_START_CODE};
+pub use transform::{FrameTransform, TransformPort};
+pub use universe::{Patch, UniverseManager};
+#[cfg(feature = "websocket")]
+pub use websocket::{WebSocketFormat, WebSocketPort};
+
+pub use rust_dmx_core::DMX_UNIVERSE_SIZE;
+
+/// The smallest frame that some hardware will transmit; shorter frames are padded
+/// with zeros up to this size.
+pub const MIN_FRAME_SIZE: usize = 24;
 
 /// Trait for the general notion of a DMX port.
 /// This enables creation of an "offline" port to slot into place if an API requires an output.
 #[typetag::serde(tag = "type")]
-pub trait DmxPort: fmt::Display {
+pub trait DmxPort: fmt::Display + Send {
     /// Return the available ports.  The ports will need to be opened before use.
+    /// Ports should be listed in a stable order that doesn't depend on the
+    /// order the OS enumerates devices in.
     fn available_ports() -> anyhow::Result<PortListing>
     where
         Self: Sized;
@@ -30,35 +144,212 @@ pub trait DmxPort: fmt::Display {
     /// it will be padded with zeros.  If the frame is larger than the maximum universe size, the
     /// values beyond the max size will be ignored.
     fn write(&mut self, frame: &[u8]) -> Result<(), WriteError>;
+
+    /// Write an all-zero frame for an emergency blackout. Ports that wrap
+    /// others pass it straight on to them, bypassing anything that would
+    /// change the levels, so the zeros reach the hardware as zeros.
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.write(frame)
+    }
+
+    /// Write a packet with an alternate start code, such as a text packet.
+    /// Ports that can only carry levels return an error.
+    fn write_alternate(&mut self, start_code: u8, _data: &[u8]) -> Result<(), WriteError> {
+        Err(anyhow::anyhow!("{self} can't send alternate start code {start_code:#04x}").into())
+    }
+
+    /// Release the frames written since the last sync all at once, on ports
+    /// whose receivers can hold their output until told to, such as Art-Net
+    /// nodes with ArtSync. Once a receiver has been synced, it may wait for
+    /// the next sync before outputting new frames. Ports without
+    /// synchronization output every frame as it is written, and do nothing.
+    fn sync(&mut self) -> Result<(), WriteError> {
+        Ok(())
+    }
+
+    /// Return the range of frame sizes this port transmits without padding or
+    /// truncation.
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        FrameSizeLimits::default()
+    }
+
+    /// Return the fastest rate, in frames per second, at which the port can
+    /// actually transmit full frames, or None if it has no practical limit.
+    fn max_fps(&self) -> Option<f64> {
+        None
+    }
+
+    /// Return the levels of the last frame the port transmitted, as they went
+    /// out, on ports that can capture their output. Return None on ports that
+    /// can't, and before the first frame.
+    fn captured(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Update a port that was deserialized from a config written with an older
+    /// schema version. Fields added since then will already hold their serde
+    /// defaults; this is the place to fix up anything that needs more than that.
+    fn migrate(&mut self, _from_version: u32) {}
+}
+
+/// Trait for the general notion of a port that receives DMX.
+#[typetag::serde(tag = "type")]
+pub trait DmxInputPort: fmt::Display + Send {
+    /// Open the port for reading.  Implementations should no-op if this is
+    /// called twice rather than returning an error.
+    fn open(&mut self) -> Result<(), OpenError>;
+
+    /// Close the port.
+    fn close(&mut self);
+
+    /// Wait up to timeout for the next frame to arrive.  Return None if no
+    /// frame arrived in that time.
+    fn read(&mut self, timeout: Duration) -> Result<Option<InputFrame>, ReadError>;
+}
+
+/// A frame received by an input port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputFrame {
+    /// The universe the frame arrived on, for ports that receive more than one.
+    pub universe: u16,
+    /// An identifier for the source that sent the frame, or None if the frame
+    /// was merged from several sources.
+    pub source: Option<String>,
+    /// The channel levels, starting with channel 1.
+    pub levels: Vec<u8>,
+}
+
+/// The effective minimum and maximum frame sizes of a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSizeLimits {
+    /// Frames shorter than this will be padded with zeros.
+    pub min: usize,
+    /// Values beyond this many channels will be ignored.
+    pub max: usize,
+}
+
+impl Default for FrameSizeLimits {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: DMX_UNIVERSE_SIZE,
+        }
+    }
+}
+
+impl FrameSizeLimits {
+    /// Return the number of channels that will actually be sent for a frame of the given size.
+    pub fn transmitted_len(&self, frame_len: usize) -> usize {
+        frame_len.clamp(self.min, self.max)
+    }
 }
 
 /// A listing of available ports.
 type PortListing = Vec<Box<dyn DmxPort>>;
 
+/// A function that lists the ports of one backend.
+type Provider = fn() -> anyhow::Result<PortListing>;
+
+/// All of the providers, in the order their ports are listed.
+const PROVIDERS: &[Provider] = &[
+    OfflineDmxPort::available_ports,
+    #[cfg(not(target_arch = "wasm32"))]
+    EnttecDmxPort::available_ports,
+    #[cfg(all(feature = "hidapi", not(target_arch = "wasm32")))]
+    Fx5DmxPort::available_ports,
+    #[cfg(all(feature = "hidapi", not(target_arch = "wasm32")))]
+    K8062DmxPort::available_ports,
+    ArtnetDmxPort::available_ports,
+];
+
 /// Gather up all of the providers and use them to get listings of all ports they have available.
 /// Return them as a vector of names plus opener functions.
 /// This function does not check whether or not any of the ports are in use already.
+///
+/// Providers are polled concurrently, so a slow backend doesn't hold up the
+/// others; the listing is still returned in provider order, starting with the
+/// offline port, so that the index of a port is stable between calls.
 pub fn available_ports() -> anyhow::Result<PortListing> {
+    // wasm32 has no threads.
+    #[cfg(target_arch = "wasm32")]
+    let listings: Vec<_> = PROVIDERS.iter().map(|provider| provider()).collect();
+    #[cfg(not(target_arch = "wasm32"))]
+    let listings: Vec<_> = thread::scope(|s| {
+        let handles: Vec<_> = PROVIDERS.iter().map(|provider| s.spawn(provider)).collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect()
+    });
     let mut ports = Vec::new();
-    ports.extend(OfflineDmxPort::available_ports()?);
-    ports.extend(EnttecDmxPort::available_ports()?);
+    for listing in listings {
+        ports.extend(listing?);
+    }
     Ok(ports)
 }
 
+/// Open every port in the listing, such as a saved rig that was just deserialized.
+/// Every port is attempted even if some fail; return the index and error of
+/// each port that failed to open.
+pub fn open_all(ports: &mut [Box<dyn DmxPort>]) -> Vec<(usize, OpenError)> {
+    ports
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(i, port)| {
+            let err = port.open().err()?;
+            warn!("Failed to open DMX port {}: {}.", port, err);
+            Some((i, err))
+        })
+        .collect()
+}
+
+/// Close every port in the listing, such as when tearing down a rig.
+/// Closing can't fail, so there is nothing to report.
+pub fn close_all(ports: &mut [Box<dyn DmxPort>]) {
+    for port in ports {
+        port.close();
+    }
+}
+
 /// Prompt the user to select a port via the command prompt.
 pub fn select_port() -> anyhow::Result<Box<dyn DmxPort>> {
+    prompt_for_port(None)
+}
+
+/// Prompt the user to select a port, offering the port they selected last
+/// time as the default so that pressing Enter picks it again. The selection is
+/// remembered in a file at path. Ports are matched by their displayed name.
+pub fn select_port_remembering(path: impl AsRef<Path>) -> anyhow::Result<Box<dyn DmxPort>> {
+    prompt_for_port(Some(path.as_ref()))
+}
+
+fn prompt_for_port(last_used_path: Option<&Path>) -> anyhow::Result<Box<dyn DmxPort>> {
     let mut ports = available_ports()?;
+    let last_used = last_used_path.and_then(|path| std::fs::read_to_string(path).ok());
+    let default = last_used.and_then(|last_used| {
+        ports
+            .iter()
+            .position(|port| port.to_string() == last_used.trim())
+    });
     println!("Available DMX ports:");
     for (i, port) in ports.iter().enumerate() {
         println!("{}: {}", i, port);
     }
     let mut port = loop {
-        print!("Select a port: ");
+        match default {
+            Some(default) => print!("Select a port [{}]: ", default),
+            None => print!("Select a port: "),
+        }
         io::stdout().flush()?;
         let input = read_string()?;
-        let index = match input.trim().parse::<usize>() {
-            Ok(num) => num,
-            Err(e) => {
+        let index = match (input.trim().parse::<usize>(), default) {
+            (Ok(num), _) => num,
+            (Err(_), Some(default)) if input.is_empty() => default,
+            (Err(e), _) => {
                 println!("{}; please enter an integer.", e);
                 continue;
             }
@@ -70,6 +361,15 @@ pub fn select_port() -> anyhow::Result<Box<dyn DmxPort>> {
         break ports.swap_remove(index);
     };
     port.open()?;
+    if let Some(path) = last_used_path {
+        if let Err(err) = std::fs::write(path, port.to_string()) {
+            warn!(
+                "Failed to remember the selected port in {}: {}.",
+                path.display(),
+                err
+            );
+        }
+    }
     Ok(port)
 }
 
@@ -90,6 +390,16 @@ pub enum OpenError {
 
 #[derive(Error, Debug)]
 pub enum WriteError {
+    #[error("the DMX port is not connected")]
+    Disconnected,
+    #[error("the DMX port can't keep up with the output; reduce the frame rate or universe size")]
+    Overrun,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum ReadError {
     #[error("the DMX port is not connected")]
     Disconnected,
     #[error(transparent)]
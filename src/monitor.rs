@@ -0,0 +1,178 @@
+//! Live view of the levels arriving on an input port.
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::{DmxInputPort, Frame, ReadError};
+
+/// Weight given to the newest frame interval in the frame rate estimate.
+const FPS_SMOOTHING: f64 = 0.1;
+
+/// Channels shown on each row when the monitor is displayed.
+const CHANNELS_PER_ROW: usize = 16;
+
+/// Read frames from an input port and keep track of the current levels, which
+/// channels changed in the most recent frame, and the incoming frame rate.
+///
+/// The Display form is a plain-text grid of levels with an asterisk after each
+/// channel that changed. Applications that want colour can draw their own grid
+/// from `levels` and `changed`, as the monitor example does.
+pub struct InputMonitor {
+    port: Box<dyn DmxInputPort>,
+    universe: Option<u16>,
+    levels: Frame,
+    changed: Vec<bool>,
+    last_frame: Option<Instant>,
+    /// Smoothed interval between frames, in seconds.
+    interval: Option<f64>,
+}
+
+impl InputMonitor {
+    /// Monitor every frame that arrives on an open port.
+    pub fn new(port: Box<dyn DmxInputPort>) -> Self {
+        Self {
+            port,
+            universe: None,
+            levels: Frame::default(),
+            changed: Vec::new(),
+            last_frame: None,
+            interval: None,
+        }
+    }
+
+    /// Only monitor frames received on the given universe.
+    pub fn with_universe(mut self, universe: u16) -> Self {
+        self.universe = Some(universe);
+        self
+    }
+
+    /// Wait up to timeout for the next frame and update the monitor with it.
+    /// Return true if a frame arrived.
+    pub fn poll(&mut self, timeout: Duration) -> Result<bool, ReadError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(frame) = self.port.read(remaining)? else {
+                return Ok(false);
+            };
+            if self.universe.is_some_and(|u| u != frame.universe) {
+                continue;
+            }
+            self.update(frame.levels.into(), Instant::now());
+            return Ok(true);
+        }
+    }
+
+    fn update(&mut self, levels: Frame, now: Instant) {
+        // Channels the last frame didn't have are new, so they count as
+        // changed even at level 0.
+        self.changed.clear();
+        self.changed
+            .resize(levels.len().min(self.levels.len()), false);
+        self.changed.resize(levels.len(), true);
+        for (channel, _, _) in self.levels.diff(&levels) {
+            if let Some(changed) = self.changed.get_mut(channel) {
+                *changed = true;
+            }
+        }
+        self.levels = levels;
+        if let Some(last_frame) = self.last_frame {
+            let interval = (now - last_frame).as_secs_f64();
+            self.interval = Some(match self.interval {
+                Some(smoothed) => smoothed + FPS_SMOOTHING * (interval - smoothed),
+                None => interval,
+            });
+        }
+        self.last_frame = Some(now);
+    }
+
+    /// Return the levels of the most recent frame.
+    pub fn levels(&self) -> &[u8] {
+        &self.levels
+    }
+
+    /// Return true if the channel, counting from 0, changed in the most recent frame.
+    pub fn changed(&self, channel: usize) -> bool {
+        self.changed.get(channel).copied().unwrap_or_default()
+    }
+
+    /// Return the smoothed rate frames are arriving at, or 0 before two frames
+    /// have arrived.
+    pub fn fps(&self) -> f64 {
+        match self.interval {
+            Some(interval) if interval > 0.0 => 1.0 / interval,
+            _ => 0.0,
+        }
+    }
+
+    /// Return the port this monitor reads from.
+    pub fn port(&self) -> &dyn DmxInputPort {
+        &*self.port
+    }
+
+    /// Unwrap this monitor into the port it reads from.
+    pub fn into_inner(self) -> Box<dyn DmxInputPort> {
+        self.port
+    }
+}
+
+impl fmt::Display for InputMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} - {:.1} fps", self.port, self.fps())?;
+        for (row, levels) in self.levels.chunks(CHANNELS_PER_ROW).enumerate() {
+            let first = row * CHANNELS_PER_ROW;
+            write!(f, "{:>3}:", first + 1)?;
+            for (i, level) in levels.iter().enumerate() {
+                let mark = if self.changed(first + i) { '*' } else { ' ' };
+                write!(f, " {:>3}{}", level, mark)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::SacnInputPort;
+
+    #[test]
+    fn test_tracks_changes_and_rate() {
+        let mut monitor =
+            InputMonitor::new(Box::new(SacnInputPort::new(vec![1], Default::default())));
+        let start = Instant::now();
+        monitor.update(vec![0, 0].into(), start);
+        monitor.update(vec![0, 5, 1].into(), start + Duration::from_millis(25));
+        assert_eq!(&[0, 5, 1], monitor.levels());
+        assert_eq!(
+            vec![false, true, true],
+            (0..3).map(|i| monitor.changed(i)).collect::<Vec<_>>()
+        );
+        assert!((monitor.fps() - 40.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_new_channels_count_as_changed() {
+        let mut monitor =
+            InputMonitor::new(Box::new(SacnInputPort::new(vec![1], Default::default())));
+        let start = Instant::now();
+        monitor.update(vec![0, 1].into(), start);
+        monitor.update(vec![0, 1, 0].into(), start);
+        assert_eq!(
+            vec![false, false, true],
+            (0..3).map(|i| monitor.changed(i)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_display_marks_changes_without_escapes() {
+        let mut monitor =
+            InputMonitor::new(Box::new(SacnInputPort::new(vec![1], Default::default())));
+        let start = Instant::now();
+        monitor.update(vec![0, 1].into(), start);
+        monitor.update(vec![0, 5].into(), start);
+        let shown = monitor.to_string();
+        assert!(!shown.contains('\x1b'));
+        assert!(shown.ends_with("  1:   0    5*\n"), "{shown:?}");
+    }
+}
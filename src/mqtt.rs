@@ -0,0 +1,390 @@
+//! A port that publishes frames to an MQTT broker.
+//!
+//! This speaks just enough MQTT 3.1.1 to connect, publish at QoS 0 and ping.
+use anyhow::{anyhow, bail};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{system_clock, Clock, DmxPort, OpenError, PortListing, Wakeup, WriteError};
+
+/// Keep-alive interval requested from the broker, in seconds.
+const KEEP_ALIVE_SECS: u16 = 60;
+
+/// Ping the broker after sending nothing for this long, well inside the
+/// keep-alive interval so the broker never drops an idle connection.
+const PING_INTERVAL: Duration = Duration::from_secs(KEEP_ALIVE_SECS as u64 / 2);
+
+/// Give up on a broker that doesn't respond in this time.
+const BROKER_TIMEOUT: Duration = Duration::from_secs(2);
+
+// MQTT control packet types, shifted into the high nibble of the fixed header.
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PINGREQ: u8 = 0xc0;
+const PINGRESP: u8 = 0xd0;
+
+/// What gets published for each frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MqttPayload {
+    /// Publish the whole frame as raw bytes to the topic.
+    #[default]
+    FullUniverse,
+    /// Publish each channel that changed since the previous frame to
+    /// `{topic}/{channel}` as a decimal string. Channels are numbered from 1.
+    ChangedChannels,
+}
+
+/// Publish frames to topics on an MQTT broker.
+///
+/// While the port is open but idle, a background thread pings the broker.
+/// If the broker doesn't answer, the next write fails as disconnected. If the
+/// connection to the broker drops, the next write will try to reconnect.
+#[derive(Serialize, Deserialize)]
+pub struct MqttDmxPort {
+    /// Broker host and port, such as "localhost:1883".
+    broker: String,
+    client_id: String,
+    topic: String,
+    payload: MqttPayload,
+    /// If true, the broker retains published values for late subscribers.
+    retain: bool,
+    #[serde(skip)]
+    connection: Option<Connection>,
+    #[serde(skip)]
+    last_frame: Vec<u8>,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+impl MqttDmxPort {
+    /// Create a port that publishes to topic on broker.
+    /// The port is not connected until opened.
+    pub fn new(
+        broker: impl Into<String>,
+        client_id: impl Into<String>,
+        topic: impl Into<String>,
+        payload: MqttPayload,
+        retain: bool,
+    ) -> Self {
+        Self {
+            broker: broker.into(),
+            client_id: client_id.into(),
+            topic: topic.into(),
+            payload,
+            retain,
+            connection: None,
+            last_frame: Vec::new(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Time pings using clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn connect(&self) -> anyhow::Result<TcpStream> {
+        let mut stream = TcpStream::connect(&self.broker)?;
+        stream.set_read_timeout(Some(BROKER_TIMEOUT))?;
+        stream.set_write_timeout(Some(BROKER_TIMEOUT))?;
+        stream.set_nodelay(true)?;
+        stream.write_all(&encode_connect(&self.client_id))?;
+        let mut connack = [0; 4];
+        stream.read_exact(&mut connack)?;
+        if connack[0] != CONNACK {
+            bail!("unexpected response from MQTT broker: {:#04x}", connack[0]);
+        }
+        if connack[3] != 0 {
+            bail!("MQTT broker refused connection with code {}", connack[3]);
+        }
+        Ok(stream)
+    }
+
+    /// Encode all of the messages to publish for this frame.
+    fn encode_frame(&self, frame: &[u8]) -> Vec<u8> {
+        match self.payload {
+            MqttPayload::FullUniverse => encode_publish(&self.topic, frame, self.retain),
+            MqttPayload::ChangedChannels => frame
+                .iter()
+                .enumerate()
+                .filter(|(i, level)| self.last_frame.get(*i) != Some(level))
+                .flat_map(|(i, level)| {
+                    let topic = format!("{}/{}", self.topic, i + 1);
+                    encode_publish(&topic, level.to_string().as_bytes(), self.retain)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for MqttDmxPort {
+    /// MQTT ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.connection.is_some() {
+            return Ok(());
+        }
+        let stream = self.connect()?;
+        self.connection = Some(Connection::start(stream, self.clock.clone()));
+        // Publish everything again on a fresh connection.
+        self.last_frame.clear();
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.connection = None;
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        if self.connection.is_none() {
+            if let Err(err) = self.open() {
+                debug!("Failed to reconnect {}: {}.", self, err);
+                return Err(WriteError::Disconnected);
+            }
+        }
+        let messages = self.encode_frame(frame);
+        let connection = self.connection.as_ref().ok_or(WriteError::Disconnected)?;
+        if let Err(err) = connection.send(&messages) {
+            self.connection = None;
+            return Err(err);
+        }
+        self.last_frame.clear();
+        self.last_frame.extend_from_slice(frame);
+        Ok(())
+    }
+}
+
+impl fmt::Display for MqttDmxPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MQTT {} {}", self.broker, self.topic)
+    }
+}
+
+/// What the ping thread shares with writes.
+struct State {
+    stream: TcpStream,
+    last_sent: Instant,
+    /// Set once the broker fails to answer a ping.
+    lost: bool,
+    stop: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    wakeup: Wakeup,
+    clock: Arc<dyn Clock>,
+}
+
+/// A connection to the broker, pinged from a background thread whenever
+/// nothing has been sent for the ping interval. The thread is stopped when
+/// this is dropped.
+struct Connection {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Connection {
+    fn start(stream: TcpStream, clock: Arc<dyn Clock>) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                stream,
+                last_sent: clock.now(),
+                lost: false,
+                stop: false,
+            }),
+            wakeup: Wakeup::new(),
+            clock,
+        });
+        let thread = {
+            let shared = shared.clone();
+            thread::spawn(move || ping_while_idle(&shared))
+        };
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    fn send(&self, messages: &[u8]) -> Result<(), WriteError> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.lost {
+            return Err(WriteError::Disconnected);
+        }
+        state
+            .stream
+            .write_all(messages)
+            .map_err(|err| WriteError::Other(anyhow!(err)))?;
+        state.last_sent = self.shared.clock.now();
+        Ok(())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().stop = true;
+        self.shared.wakeup.wake();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn ping_while_idle(shared: &Shared) {
+    let mut state = shared.state.lock().unwrap();
+    while !state.stop && !state.lost {
+        let now = shared.clock.now();
+        let due = state.last_sent + PING_INTERVAL;
+        if now >= due {
+            // Hold the state while waiting for the answer, so a write can't
+            // go out on a connection that is about to be found dead.
+            match ping(&mut state.stream) {
+                Ok(()) => state.last_sent = now,
+                Err(err) => {
+                    debug!("MQTT broker didn't answer ping: {}.", err);
+                    state.lost = true;
+                }
+            }
+        } else {
+            drop(state);
+            shared.clock.wait_until(Some(due), &shared.wakeup);
+            state = shared.state.lock().unwrap();
+        }
+    }
+}
+
+fn ping(stream: &mut TcpStream) -> anyhow::Result<()> {
+    stream.write_all(&encode_packet(PINGREQ, &[]))?;
+    let mut pingresp = [0; 2];
+    stream.read_exact(&mut pingresp)?;
+    if pingresp != [PINGRESP, 0] {
+        bail!("unexpected response to ping: {:#04x}", pingresp[0]);
+    }
+    Ok(())
+}
+
+fn encode_connect(client_id: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_string(&mut body, "MQTT");
+    body.push(4); // protocol level 3.1.1
+    body.push(0x02); // clean session
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+    push_string(&mut body, client_id);
+    encode_packet(CONNECT, &body)
+}
+
+fn encode_publish(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+    push_string(&mut body, topic);
+    body.extend_from_slice(payload);
+    encode_packet(PUBLISH | retain as u8, &body)
+}
+
+/// Prefix a packet body with its fixed header.
+fn encode_packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    packet.push(header);
+    // The remaining length is a base-128 varint.
+    let mut len = body.len();
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        if len == 0 {
+            packet.push(byte);
+            break;
+        }
+        packet.push(byte | 0x80);
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn push_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ManualClock;
+    use std::net::TcpListener;
+
+    /// Read one packet with a short body, returning its header and body.
+    fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut header = [0; 2];
+        stream.read_exact(&mut header).unwrap();
+        let mut body = vec![0; header[1] as usize];
+        stream.read_exact(&mut body).unwrap();
+        (header[0], body)
+    }
+
+    #[test]
+    fn test_pings_while_idle() -> Result<(), Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let clock = Arc::new(ManualClock::new());
+        let mut port = MqttDmxPort::new(
+            listener.local_addr()?.to_string(),
+            "test",
+            "dmx",
+            MqttPayload::FullUniverse,
+            false,
+        )
+        .with_clock(clock.clone());
+        let broker = thread::spawn(move || {
+            let (mut broker, _) = listener.accept().unwrap();
+            assert_eq!(CONNECT, read_packet(&mut broker).0);
+            broker.write_all(&[CONNACK, 2, 0, 0]).unwrap();
+            broker
+        });
+        port.open()?;
+        let mut broker = broker.join().unwrap();
+
+        assert_eq!(clock.now() + PING_INTERVAL, clock.wait_for_deadline());
+        clock.advance(PING_INTERVAL);
+        assert_eq!((PINGREQ, vec![]), read_packet(&mut broker));
+        broker.write_all(&[PINGRESP, 0])?;
+        port.write(&[1])?;
+        assert_eq!(
+            (PUBLISH, vec![0, 3, b'd', b'm', b'x', 1]),
+            read_packet(&mut broker)
+        );
+
+        // The broker goes away instead of answering the next ping.
+        assert_eq!(clock.now() + PING_INTERVAL, clock.wait_for_deadline());
+        clock.advance(PING_INTERVAL);
+        assert_eq!((PINGREQ, vec![]), read_packet(&mut broker));
+        drop(broker);
+        assert!(matches!(port.write(&[2]), Err(WriteError::Disconnected)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_publish() {
+        assert_eq!(
+            vec![0x31, 6, 0, 1, b'a', 1, 2, 3],
+            encode_publish("a", &[1, 2, 3], true)
+        );
+        let full = encode_publish("dmx", &[0; 512], false);
+        assert_eq!(&[0x30, 0x85, 0x04, 0, 3], &full[..5]);
+    }
+
+    #[test]
+    fn test_changed_channels() {
+        let mut port = MqttDmxPort::new("", "", "dmx", MqttPayload::ChangedChannels, false);
+        port.last_frame = vec![0, 5, 0];
+        let expected: Vec<u8> = [encode_publish("dmx/2", b"6", false)].concat();
+        assert_eq!(expected, port.encode_frame(&[0, 6, 0]));
+    }
+}
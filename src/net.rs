@@ -0,0 +1,67 @@
+//! Sockets shared with other lighting software on the same host, and the
+//! interfaces they can bind to.
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+
+/// Bind a UDP socket to addr with SO_REUSEADDR and, where the platform has
+/// it, SO_REUSEPORT set, so well-known ports like Art-Net's and sACN's can be
+/// bound while another port or program on this host has them open too.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn bind_reusable(addr: SocketAddrV4) -> io::Result<UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(
+        unix,
+        not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+    ))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
+
+/// The browser has no socket options to set.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn bind_reusable(addr: SocketAddrV4) -> io::Result<UdpSocket> {
+    UdpSocket::bind(addr)
+}
+
+/// Return the IPv4 addresses of the host's interfaces, sorted and without
+/// loopback.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn interface_addresses() -> io::Result<Vec<Ipv4Addr>> {
+    let mut addrs: Vec<_> = if_addrs::get_if_addrs()?
+        .into_iter()
+        .filter(|interface| !interface.is_loopback())
+        .filter_map(|interface| match interface.ip() {
+            std::net::IpAddr::V4(addr) => Some(addr),
+            std::net::IpAddr::V6(_) => None,
+        })
+        .collect();
+    addrs.sort();
+    addrs.dedup();
+    Ok(addrs)
+}
+
+/// The browser doesn't expose the host's interfaces.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn interface_addresses() -> io::Result<Vec<Ipv4Addr>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binds_a_port_twice() -> io::Result<()> {
+        let first = bind_reusable(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0))?;
+        let addr = match first.local_addr()? {
+            std::net::SocketAddr::V4(addr) => addr,
+            addr => panic!("bound IPv6 address {addr}"),
+        };
+        bind_reusable(addr)?;
+        Ok(())
+    }
+}
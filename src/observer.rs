@@ -0,0 +1,440 @@
+//! Passive view of the Art-Net and sACN traffic on the network.
+use anyhow::anyhow;
+use log::warn;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+
+use rust_dmx_core::artnet::{decode_dmx, ARTNET_PORT};
+use rust_dmx_core::sacn::SACN_PORT;
+
+use crate::net::bind_reusable;
+use crate::sacn::{
+    format_cid, multicast_group, parse_data_packet, SEQUENCE_WINDOW, SOURCE_TIMEOUT,
+};
+use crate::{OpenError, ReadError};
+
+/// Weight given to the newest packet interval in the packet rate estimate.
+const RATE_SMOOTHING: f64 = 0.1;
+
+/// The longest each socket is waited on before the other gets a turn.
+const POLL_SLICE: Duration = Duration::from_millis(10);
+
+/// Large enough for any Art-Net or sACN data packet.
+const RECEIVE_BUFFER_SIZE: usize = 1024;
+
+/// The protocol a universe was seen on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Protocol {
+    Artnet,
+    Sacn,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Artnet => "Art-Net",
+            Self::Sacn => "sACN",
+        })
+    }
+}
+
+/// The packets one source has sent to a universe.
+#[derive(Debug, Clone)]
+pub struct SourceTraffic {
+    /// The sender's address for Art-Net, or its CID for sACN.
+    pub source: String,
+    /// The sACN source name, or empty for Art-Net.
+    pub name: String,
+    pub packets: u64,
+    /// Packets that never arrived, judging by gaps in the sequence numbers.
+    pub missed: u64,
+    /// Packets that arrived after a later one.
+    pub out_of_order: u64,
+    sequence: u8,
+    last_seen: Instant,
+}
+
+/// The packets seen on one universe.
+#[derive(Debug, Clone, Default)]
+pub struct UniverseTraffic {
+    pub packets: u64,
+    sources: Vec<SourceTraffic>,
+    last_packet: Option<Instant>,
+    /// Smoothed interval between packets, in seconds.
+    interval: Option<f64>,
+}
+
+impl UniverseTraffic {
+    /// Return the sources that have sent to the universe recently.
+    pub fn sources(&self) -> &[SourceTraffic] {
+        &self.sources
+    }
+
+    /// Return the smoothed rate packets are arriving at from every source
+    /// together, or 0 before two packets have arrived.
+    pub fn fps(&self) -> f64 {
+        match self.interval {
+            Some(interval) if interval > 0.0 => 1.0 / interval,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Return how far ahead of last a sequence number is, or None if the source
+/// doesn't number its packets. Art-Net numbers from 1 to 255, using 0 to mean
+/// that sequencing is off; sACN uses every value.
+fn sequence_delta(protocol: Protocol, last: u8, sequence: u8) -> Option<i8> {
+    match protocol {
+        Protocol::Sacn => Some(sequence.wrapping_sub(last) as i8),
+        Protocol::Artnet if last == 0 || sequence == 0 => None,
+        Protocol::Artnet => {
+            let delta = (sequence as i16 - last as i16).rem_euclid(255);
+            Some(if delta > 127 { delta - 255 } else { delta } as i8)
+        }
+    }
+}
+
+/// Listen for ArtDmx packets and sACN data packets without taking part, and
+/// keep track of each universe's packet rate and sources, and of the gaps in
+/// each source's sequence numbers.
+///
+/// ArtDmx packets are only seen if they are broadcast or sent to this host.
+/// sACN is multicast, so the observer joins the groups of the universes it is
+/// given; unicast sACN is only seen if it is sent to this host.
+pub struct TrafficObserver {
+    sacn_universes: Vec<u16>,
+    artnet_port: u16,
+    sacn_port: u16,
+    sockets: Vec<(Protocol, UdpSocket)>,
+    traffic: BTreeMap<(Protocol, u16), UniverseTraffic>,
+}
+
+impl TrafficObserver {
+    /// Create an observer that watches every Art-Net universe and the given
+    /// sACN universes. It doesn't listen until opened.
+    pub fn new(sacn_universes: Vec<u16>) -> Self {
+        Self {
+            sacn_universes,
+            artnet_port: ARTNET_PORT,
+            sacn_port: SACN_PORT,
+            sockets: Vec::new(),
+            traffic: BTreeMap::new(),
+        }
+    }
+
+    /// Listen for Art-Net on udp_port instead of the standard port.
+    pub fn with_artnet_port(mut self, udp_port: u16) -> Self {
+        self.artnet_port = udp_port;
+        self
+    }
+
+    /// Listen for sACN on udp_port instead of the standard port.
+    pub fn with_sacn_port(mut self, udp_port: u16) -> Self {
+        self.sacn_port = udp_port;
+        self
+    }
+
+    /// Bind both ports, shared with any other lighting software on this
+    /// host, and join the sACN groups. A group that can't be joined is
+    /// reported and left out.
+    pub fn open(&mut self) -> Result<(), OpenError> {
+        if !self.sockets.is_empty() {
+            return Ok(());
+        }
+        let bind = |protocol: Protocol, udp_port: u16| {
+            bind_reusable(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, udp_port))
+                .map_err(|err| anyhow!("failed to bind {protocol} port {udp_port}: {err}"))
+        };
+        let artnet = bind(Protocol::Artnet, self.artnet_port)?;
+        let sacn = bind(Protocol::Sacn, self.sacn_port)?;
+        for &universe in &self.sacn_universes {
+            if let Err(err) =
+                sacn.join_multicast_v4(&multicast_group(universe), &Ipv4Addr::UNSPECIFIED)
+            {
+                warn!("Failed to join sACN universe {universe}: {err}.");
+            }
+        }
+        self.sockets = vec![(Protocol::Artnet, artnet), (Protocol::Sacn, sacn)];
+        Ok(())
+    }
+
+    /// Stop listening. The traffic seen so far is kept.
+    pub fn close(&mut self) {
+        self.sockets.clear();
+    }
+
+    /// Record the packets that arrive within timeout, and return how many did.
+    pub fn poll(&mut self, timeout: Duration) -> Result<usize, ReadError> {
+        if self.sockets.is_empty() {
+            return Err(ReadError::Disconnected);
+        }
+        let deadline = Instant::now() + timeout;
+        let mut buf = [0; RECEIVE_BUFFER_SIZE];
+        let mut received = 0;
+        loop {
+            for i in 0..self.sockets.len() {
+                let now = Instant::now();
+                if now >= deadline {
+                    self.expire(now);
+                    return Ok(received);
+                }
+                let (protocol, socket) = &self.sockets[i];
+                let protocol = *protocol;
+                socket
+                    .set_read_timeout(Some((deadline - now).min(POLL_SLICE)))
+                    .map_err(anyhow::Error::from)?;
+                let (len, sender) = match socket.recv_from(&mut buf) {
+                    Ok(received) => received,
+                    Err(err)
+                        if matches!(
+                            err.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) =>
+                    {
+                        continue;
+                    }
+                    Err(err) => return Err(anyhow::Error::from(err).into()),
+                };
+                if self.receive(protocol, &buf[..len], sender, Instant::now()) {
+                    received += 1;
+                }
+            }
+        }
+    }
+
+    /// Record a packet if it is a data packet, and return true if it was.
+    fn receive(
+        &mut self,
+        protocol: Protocol,
+        packet: &[u8],
+        sender: SocketAddr,
+        now: Instant,
+    ) -> bool {
+        match protocol {
+            Protocol::Artnet => {
+                let Some(packet) = decode_dmx(packet) else {
+                    return false;
+                };
+                let source = sender.ip().to_string();
+                self.observe(
+                    protocol,
+                    packet.port_address,
+                    source,
+                    "",
+                    packet.sequence,
+                    now,
+                );
+            }
+            Protocol::Sacn => {
+                let Some(packet) = parse_data_packet(packet) else {
+                    return false;
+                };
+                let source = format_cid(&packet.cid);
+                self.observe(
+                    protocol,
+                    packet.universe,
+                    source,
+                    &packet.source_name,
+                    packet.sequence,
+                    now,
+                );
+            }
+        }
+        true
+    }
+
+    /// Count a packet from source against a universe.
+    fn observe(
+        &mut self,
+        protocol: Protocol,
+        universe: u16,
+        source: String,
+        name: &str,
+        sequence: u8,
+        now: Instant,
+    ) {
+        let traffic = self.traffic.entry((protocol, universe)).or_default();
+        traffic.packets += 1;
+        if let Some(last_packet) = traffic.last_packet {
+            let interval = (now - last_packet).as_secs_f64();
+            traffic.interval = Some(match traffic.interval {
+                Some(smoothed) => smoothed + RATE_SMOOTHING * (interval - smoothed),
+                None => interval,
+            });
+        }
+        traffic.last_packet = Some(now);
+        traffic
+            .sources
+            .retain(|s| now - s.last_seen < SOURCE_TIMEOUT);
+        let Some(known) = traffic.sources.iter_mut().find(|s| s.source == source) else {
+            traffic.sources.push(SourceTraffic {
+                source,
+                name: name.to_string(),
+                packets: 1,
+                missed: 0,
+                out_of_order: 0,
+                sequence,
+                last_seen: now,
+            });
+            return;
+        };
+        known.packets += 1;
+        known.last_seen = now;
+        known.name = name.to_string();
+        match sequence_delta(protocol, known.sequence, sequence) {
+            Some(delta) if delta <= 0 && delta > -SEQUENCE_WINDOW => {
+                known.out_of_order += 1;
+                return;
+            }
+            Some(delta) if delta > 1 => known.missed += delta as u64 - 1,
+            _ => (),
+        }
+        known.sequence = sequence;
+    }
+
+    /// Forget the sources that have gone quiet.
+    fn expire(&mut self, now: Instant) {
+        for traffic in self.traffic.values_mut() {
+            traffic
+                .sources
+                .retain(|s| now - s.last_seen < SOURCE_TIMEOUT);
+        }
+    }
+
+    /// Return the traffic seen on each universe so far, in order.
+    pub fn traffic(&self) -> impl Iterator<Item = (Protocol, u16, &UniverseTraffic)> {
+        self.traffic
+            .iter()
+            .map(|(&(protocol, universe), traffic)| (protocol, universe, traffic))
+    }
+
+    /// Return the traffic seen on one universe so far, if any.
+    pub fn universe(&self, protocol: Protocol, universe: u16) -> Option<&UniverseTraffic> {
+        self.traffic.get(&(protocol, universe))
+    }
+}
+
+impl fmt::Display for TrafficObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (protocol, universe, traffic) in self.traffic() {
+            writeln!(
+                f,
+                "{protocol} universe {universe} - {:.1} fps",
+                traffic.fps()
+            )?;
+            for source in traffic.sources() {
+                write!(f, "  {}", source.source)?;
+                if !source.name.is_empty() {
+                    write!(f, " ({})", source.name)?;
+                }
+                writeln!(
+                    f,
+                    ": {} packets, {} missed, {} out of order",
+                    source.packets, source.missed, source.out_of_order
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ArtnetDmxPort, DmxPort, PortAddress, SacnDmxPort};
+
+    #[test]
+    fn test_counts_sequence_gaps() {
+        let mut observer = TrafficObserver::new(Vec::new());
+        let start = Instant::now();
+        let mut observe = |protocol, source: &str, sequence| {
+            observer.observe(protocol, 1, source.to_string(), "", sequence, start)
+        };
+        for sequence in [254, 255, 2, 1, 3] {
+            observe(Protocol::Sacn, "a", sequence);
+        }
+        // Art-Net skips 0 when it wraps, and 0 turns sequencing off.
+        for sequence in [254, 255, 1, 3, 0, 7] {
+            observe(Protocol::Artnet, "b", sequence);
+        }
+        observe(Protocol::Artnet, "c", 1);
+        let counts = |protocol| {
+            observer
+                .universe(protocol, 1)
+                .unwrap()
+                .sources()
+                .iter()
+                .map(|s| (s.source.as_str(), s.packets, s.missed, s.out_of_order))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![("a", 5, 2, 1)], counts(Protocol::Sacn));
+        assert_eq!(
+            vec![("b", 6, 1, 0), ("c", 1, 0, 0)],
+            counts(Protocol::Artnet)
+        );
+    }
+
+    #[test]
+    fn test_tracks_rate_and_expires_sources() {
+        let mut observer = TrafficObserver::new(Vec::new());
+        let start = Instant::now();
+        for i in 0..3 {
+            let now = start + Duration::from_millis(25) * i;
+            observer.observe(Protocol::Sacn, 1, "a".to_string(), "", i as u8, now);
+        }
+        let traffic = observer.universe(Protocol::Sacn, 1).unwrap();
+        assert!((traffic.fps() - 40.0).abs() < 0.01);
+        observer.expire(start + SOURCE_TIMEOUT * 2);
+        let traffic = observer.universe(Protocol::Sacn, 1).unwrap();
+        assert_eq!(3, traffic.packets);
+        assert!(traffic.sources().is_empty());
+    }
+
+    #[test]
+    fn test_observes_both_protocols() -> Result<(), Box<dyn std::error::Error>> {
+        // Use non-standard ports to stay out of the way of other lighting software.
+        let artnet_port = ARTNET_PORT + 3;
+        let sacn_port = SACN_PORT + 4;
+        let mut observer = TrafficObserver::new(vec![5])
+            .with_artnet_port(artnet_port)
+            .with_sacn_port(sacn_port);
+        observer.open()?;
+        let mut artnet = ArtnetDmxPort::new(Ipv4Addr::LOCALHOST, PortAddress::new(0, 1, 2)?)
+            .with_interface(Ipv4Addr::LOCALHOST)
+            .with_own_socket()
+            .with_udp_port(artnet_port);
+        let mut sacn = SacnDmxPort::new(5)
+            .with_unicast(vec![Ipv4Addr::LOCALHOST])
+            .with_udp_port(sacn_port);
+        DmxPort::open(&mut artnet)?;
+        DmxPort::open(&mut sacn)?;
+        for _ in 0..2 {
+            artnet.write(&[1; 2])?;
+            sacn.write(&[1; 2])?;
+        }
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut received = 0;
+        while received < 4 && Instant::now() < deadline {
+            received += observer.poll(Duration::from_millis(50))?;
+        }
+        let seen: Vec<_> = observer
+            .traffic()
+            .map(|(protocol, universe, traffic)| {
+                let source = &traffic.sources()[0];
+                (protocol, universe, traffic.packets, source.missed)
+            })
+            .collect();
+        assert_eq!(
+            vec![(Protocol::Artnet, 0x12, 2, 0), (Protocol::Sacn, 5, 2, 0)],
+            seen
+        );
+        assert_eq!(
+            sacn.source_id(),
+            observer.universe(Protocol::Sacn, 5).unwrap().sources()[0].source
+        );
+        Ok(())
+    }
+}
@@ -21,6 +21,10 @@ impl DmxPort for OfflineDmxPort {
     fn write(&mut self, _: &[u8]) -> Result<(), WriteError> {
         Ok(())
     }
+
+    fn write_alternate(&mut self, _: u8, _: &[u8]) -> Result<(), WriteError> {
+        Ok(())
+    }
 }
 
 impl fmt::Display for OfflineDmxPort {
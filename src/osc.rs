@@ -0,0 +1,108 @@
+//! A port that sends frames as OSC blobs over UDP.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::UdpSocket;
+
+use crate::{DmxPort, OpenError, PortListing, WriteError};
+
+/// Send each frame as a single OSC message whose only argument is a blob of
+/// channel levels, such as `/dmx/1 ,b <512 bytes>`.
+///
+/// Many media servers and visualizers accept DMX in this form.
+#[derive(Serialize, Deserialize)]
+pub struct OscDmxPort {
+    /// Destination host and port, such as "192.168.1.10:7000".
+    dest: String,
+    /// The OSC address each message is sent to.
+    address: String,
+    #[serde(skip)]
+    socket: Option<UdpSocket>,
+}
+
+impl OscDmxPort {
+    /// Create a port that sends to `/dmx/{universe}` at dest.
+    pub fn new(dest: impl Into<String>, universe: u16) -> Self {
+        Self::with_address(dest, format!("/dmx/{universe}"))
+    }
+
+    /// Create a port that sends to an arbitrary OSC address at dest.
+    pub fn with_address(dest: impl Into<String>, address: impl Into<String>) -> Self {
+        Self {
+            dest: dest.into(),
+            address: address.into(),
+            socket: None,
+        }
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for OscDmxPort {
+    /// OSC ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.socket.is_some() {
+            return Ok(());
+        }
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(anyhow::Error::from)?;
+        socket
+            .connect(&self.dest)
+            .map_err(|err| OpenError::Other(err.into()))?;
+        self.socket = Some(socket);
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.socket = None;
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let socket = self.socket.as_ref().ok_or(WriteError::Disconnected)?;
+        socket
+            .send(&encode_blob_message(&self.address, frame))
+            .map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for OscDmxPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OSC {} {}", self.dest, self.address)
+    }
+}
+
+/// Encode an OSC message with a single blob argument.
+fn encode_blob_message(address: &str, blob: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(address.len() + blob.len() + 16);
+    push_padded(&mut message, address.as_bytes(), true);
+    push_padded(&mut message, b",b", true);
+    message.extend_from_slice(&(blob.len() as i32).to_be_bytes());
+    push_padded(&mut message, blob, false);
+    message
+}
+
+/// Append bytes, optionally null-terminated, padded with zeros to a multiple of 4.
+fn push_padded(message: &mut Vec<u8>, bytes: &[u8], terminate: bool) {
+    message.extend_from_slice(bytes);
+    let len = bytes.len() + terminate as usize;
+    message.resize(message.len() + len.next_multiple_of(4) - bytes.len(), 0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode_blob_message() {
+        assert_eq!(
+            b"/dmx/1\0\0,b\0\0\0\0\0\x03\x01\x02\x03\0".to_vec(),
+            encode_blob_message("/dmx/1", &[1, 2, 3])
+        );
+        assert_eq!(
+            b"/dmx\0\0\0\0,b\0\0\0\0\0\x04\x01\x02\x03\x04".to_vec(),
+            encode_blob_message("/dmx", &[1, 2, 3, 4])
+        );
+    }
+}
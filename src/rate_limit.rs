@@ -0,0 +1,170 @@
+//! A port that drops frames written faster than a maximum rate.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::clock::{deserialize_fps, frame_interval};
+use crate::{system_clock, Clock, DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError};
+
+/// Write at most fps frames per second to the inner port, dropping frames
+/// that arrive sooner than one interval after the last frame written.
+///
+/// Use this in front of receivers that can't keep up with an application
+/// writing as fast as it renders. Since every frame carries the whole
+/// universe, the levels of a dropped frame go out with the next write after
+/// the interval.
+#[derive(Serialize, Deserialize)]
+pub struct RateLimitPort {
+    inner: Box<dyn DmxPort>,
+    /// The most frames per second to write.
+    #[serde(deserialize_with = "deserialize_fps")]
+    fps: f64,
+    /// When the last frame was written.
+    #[serde(skip)]
+    last_write: Option<Instant>,
+    #[serde(skip)]
+    dropped: u64,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimitPort {
+    /// Wrap inner, writing at most fps frames per second.
+    /// Fail unless fps is a positive rate.
+    pub fn new(inner: Box<dyn DmxPort>, fps: f64) -> anyhow::Result<Self> {
+        frame_interval(fps)?;
+        Ok(Self {
+            inner,
+            fps,
+            last_write: None,
+            dropped: 0,
+            clock: system_clock(),
+        })
+    }
+
+    /// Time the interval between frames using clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Return the number of frames dropped because they came too soon.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Unwrap this port into the inner port.
+    pub fn into_inner(self) -> Box<dyn DmxPort> {
+        self.inner
+    }
+
+    fn interval(&self) -> Duration {
+        frame_interval(self.fps).expect("fps is checked when the port is created or loaded")
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for RateLimitPort {
+    /// Rate limit ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.inner.open()
+    }
+
+    fn close(&mut self) {
+        self.last_write = None;
+        self.inner.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let now = self.clock.now();
+        if self
+            .last_write
+            .is_some_and(|last| now - last < self.interval())
+        {
+            self.dropped += 1;
+            return Ok(());
+        }
+        self.inner.write(frame)?;
+        self.last_write = Some(now);
+        Ok(())
+    }
+
+    /// Never dropped, since a blackout must not wait.
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_blackout(frame)
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.inner.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        Some(
+            self.inner
+                .max_fps()
+                .map_or(self.fps, |max| max.min(self.fps)),
+        )
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.migrate(from_version);
+    }
+}
+
+impl fmt::Display for RateLimitPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (limited to {} fps)", self.inner, self.fps)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+    use crate::ManualClock;
+
+    #[test]
+    fn test_drops_frames_written_too_soon() {
+        let inner = TestPort::default();
+        let clock = Arc::new(ManualClock::new());
+        let mut port = RateLimitPort::new(Box::new(inner.clone()), 40.0)
+            .unwrap()
+            .with_clock(clock.clone());
+        assert_eq!(Some(40.0), port.max_fps());
+        port.write(&[1]).unwrap();
+        clock.advance(Duration::from_millis(10));
+        port.write(&[2]).unwrap();
+        clock.advance(Duration::from_millis(15));
+        port.write(&[3]).unwrap();
+        assert_eq!(vec![vec![1], vec![3]], inner.frames());
+        assert_eq!(1, port.dropped());
+    }
+
+    #[test]
+    fn test_rejects_invalid_rates() {
+        for fps in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::MIN_POSITIVE] {
+            assert!(RateLimitPort::new(Box::new(TestPort::default()), fps).is_err());
+        }
+        let port = RateLimitPort::new(Box::new(TestPort::default()), 40.0).unwrap();
+        let saved = serde_json::to_string(&(Box::new(port) as Box<dyn DmxPort>)).unwrap();
+        let negative = saved.replace("40.0", "-40.0");
+        assert!(serde_json::from_str::<Box<dyn DmxPort>>(&negative).is_err());
+    }
+}
@@ -0,0 +1,925 @@
+//! Remote Device Management (ANSI E1.20) messages.
+//!
+//! These types and codecs are shared by every transport that carries RDM, such
+//! as the Enttec Pro and Art-Net gateways. Like the other codecs, encoders
+//! write into a caller-provided buffer and return the number of bytes used, or
+//! None if the buffer is too small, and decoders return None for anything
+//! that isn't a well-formed message.
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The start code of an RDM packet.
+pub const START_CODE: u8 = 0xCC;
+/// The sub start code that follows the start code of every RDM message.
+pub const SUB_START_CODE: u8 = 0x01;
+
+/// Size of a message before its parameter data.
+pub const HEADER_SIZE: usize = 24;
+/// The most parameter data a message can carry.
+pub const MAX_PARAMETER_DATA_SIZE: usize = 231;
+/// Size of the checksum that ends every message.
+pub const CHECKSUM_SIZE: usize = 2;
+/// A buffer of this size holds any message.
+pub const MAX_MESSAGE_SIZE: usize = HEADER_SIZE + MAX_PARAMETER_DATA_SIZE + CHECKSUM_SIZE;
+
+/// Discover responders whose UIDs fall within a range.
+pub const DISC_UNIQUE_BRANCH: u16 = 0x0001;
+/// Stop a responder answering discovery.
+pub const DISC_MUTE: u16 = 0x0002;
+/// Let a responder answer discovery again.
+pub const DISC_UN_MUTE: u16 = 0x0003;
+/// The next queued response, such as the answer to a request acknowledged
+/// with a timer.
+pub const QUEUED_MESSAGE: u16 = 0x0020;
+/// Queued status messages, such as faults.
+pub const STATUS_MESSAGES: u16 = 0x0030;
+/// A responder's model, footprint, personality, and start address.
+pub const DEVICE_INFO: u16 = 0x0060;
+/// The personality, or channel layout, a responder uses.
+pub const DMX_PERSONALITY: u16 = 0x00E0;
+/// The first DMX channel a responder listens to, counting from 1.
+pub const DMX_START_ADDRESS: u16 = 0x00F0;
+/// What one of a responder's sensors measures.
+pub const SENSOR_DEFINITION: u16 = 0x0200;
+/// The current reading of one of a responder's sensors.
+pub const SENSOR_VALUE: u16 = 0x0201;
+/// Whether a responder is identifying itself, such as by flashing.
+pub const IDENTIFY_DEVICE: u16 = 0x1000;
+
+/// Ask for queued status messages of every severity.
+pub const STATUS_ADVISORY: u8 = 0x02;
+/// Ask for queued warnings and errors.
+pub const STATUS_WARNING: u8 = 0x03;
+/// Ask for queued errors only.
+pub const STATUS_ERROR: u8 = 0x04;
+
+/// The sub device that addresses a responder itself.
+pub const ROOT_DEVICE: u16 = 0;
+
+/// Give up on a responder that still asks for more time after this many
+/// timers in answer to one request.
+const MAX_ACK_TIMERS: usize = 10;
+
+/// Precedes a discovery response, up to seven times.
+const DISCOVERY_PREAMBLE: u8 = 0xFE;
+/// Separates a discovery response's preamble from its encoded UID.
+const DISCOVERY_DELIMITER: u8 = 0xAA;
+/// Size of the encoded UID and checksum of a discovery response.
+const DISCOVERY_RESPONSE_SIZE: usize = 16;
+
+/// The unique ID of an RDM responder or controller: a 16-bit ESTA manufacturer
+/// ID and a 32-bit device ID.
+///
+/// It is displayed and parsed in the standard "mmmm:dddddddd" hex form.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Uid {
+    pub manufacturer: u16,
+    pub device: u32,
+}
+
+impl Uid {
+    /// Every responder.
+    pub const BROADCAST: Self = Self::new(0xFFFF, 0xFFFF_FFFF);
+
+    /// The largest UID a responder can have.
+    pub const MAX: Self = Self::new(0xFFFF, 0xFFFF_FFFE);
+
+    /// A UID in the range ESTA reserves for prototypes, which controllers use
+    /// as their source unless they are given their own.
+    pub const DEFAULT_CONTROLLER: Self = Self::new(0x7FF0, 0x0000_0001);
+
+    pub const fn new(manufacturer: u16, device: u32) -> Self {
+        Self {
+            manufacturer,
+            device,
+        }
+    }
+
+    /// Every responder of one manufacturer.
+    pub const fn manufacturer_broadcast(manufacturer: u16) -> Self {
+        Self::new(manufacturer, 0xFFFF_FFFF)
+    }
+
+    /// Unpack a UID from the 48-bit form messages carry.
+    pub fn from_bytes(bytes: [u8; 6]) -> Self {
+        let [m0, m1, d0, d1, d2, d3] = bytes;
+        Self::new(
+            u16::from_be_bytes([m0, m1]),
+            u32::from_be_bytes([d0, d1, d2, d3]),
+        )
+    }
+
+    /// Pack a UID into the 48-bit form messages carry.
+    pub fn to_bytes(self) -> [u8; 6] {
+        let [m0, m1] = self.manufacturer.to_be_bytes();
+        let [d0, d1, d2, d3] = self.device.to_be_bytes();
+        [m0, m1, d0, d1, d2, d3]
+    }
+
+    /// Return the UID as a 48-bit number, for comparing and bisecting ranges.
+    pub fn to_u64(self) -> u64 {
+        (self.manufacturer as u64) << 32 | self.device as u64
+    }
+
+    /// Truncate a 48-bit number to a UID.
+    pub fn from_u64(value: u64) -> Self {
+        Self::new((value >> 32) as u16, value as u32)
+    }
+}
+
+impl FromStr for Uid {
+    type Err = anyhow::Error;
+
+    /// Parse "mmmm:dddddddd", in hex.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let Some((manufacturer, device)) = s.split_once(':') else {
+            bail!("invalid RDM UID {s:?}; expected mmmm:dddddddd");
+        };
+        let parse_err = |err| anyhow!("invalid RDM UID {s:?}: {err}");
+        Ok(Self::new(
+            u16::from_str_radix(manufacturer, 16).map_err(parse_err)?,
+            u32::from_str_radix(device, 16).map_err(parse_err)?,
+        ))
+    }
+}
+
+impl fmt::Display for Uid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}:{:08X}", self.manufacturer, self.device)
+    }
+}
+
+/// What a message asks for, or answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandClass {
+    Discovery,
+    DiscoveryResponse,
+    Get,
+    GetResponse,
+    Set,
+    SetResponse,
+}
+
+impl CommandClass {
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::Discovery => 0x10,
+            Self::DiscoveryResponse => 0x11,
+            Self::Get => 0x20,
+            Self::GetResponse => 0x21,
+            Self::Set => 0x30,
+            Self::SetResponse => 0x31,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Some(match byte {
+            0x10 => Self::Discovery,
+            0x11 => Self::DiscoveryResponse,
+            0x20 => Self::Get,
+            0x21 => Self::GetResponse,
+            0x30 => Self::Set,
+            0x31 => Self::SetResponse,
+            _ => return None,
+        })
+    }
+
+    /// Return the class of a response to a request of this class.
+    pub fn response(self) -> Self {
+        match self {
+            Self::Discovery | Self::DiscoveryResponse => Self::DiscoveryResponse,
+            Self::Get | Self::GetResponse => Self::GetResponse,
+            Self::Set | Self::SetResponse => Self::SetResponse,
+        }
+    }
+}
+
+/// How a responder answered a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseType {
+    /// The request succeeded.
+    Ack,
+    /// The responder needs time; ask again after this many tenths of a second.
+    AckTimer(u16),
+    /// The request failed for this reason code.
+    NackReason(u16),
+    /// The request succeeded, and more data follows in further responses.
+    AckOverflow,
+}
+
+/// The sum of bytes that ends every message.
+pub fn checksum(message: &[u8]) -> u16 {
+    message
+        .iter()
+        .fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16))
+}
+
+/// An RDM request from a controller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub destination: Uid,
+    pub source: Uid,
+    /// Echoed in the response, to match it to this request.
+    pub transaction_number: u8,
+    /// The controller's port the request is sent from, counting from 1.
+    pub port_id: u8,
+    pub sub_device: u16,
+    pub command_class: CommandClass,
+    pub pid: u16,
+    pub data: Vec<u8>,
+}
+
+impl Request {
+    /// Create a request to the root device of destination from the default
+    /// controller UID. The transport sets the transaction number.
+    pub fn new(destination: Uid, command_class: CommandClass, pid: u16, data: Vec<u8>) -> Self {
+        Self {
+            destination,
+            source: Uid::DEFAULT_CONTROLLER,
+            transaction_number: 0,
+            port_id: 1,
+            sub_device: ROOT_DEVICE,
+            command_class,
+            pid,
+            data,
+        }
+    }
+
+    /// Create a request for every unmuted responder with a UID from lower to
+    /// upper to answer.
+    pub fn unique_branch(lower: Uid, upper: Uid) -> Self {
+        let mut data = lower.to_bytes().to_vec();
+        data.extend_from_slice(&upper.to_bytes());
+        Self::new(
+            Uid::BROADCAST,
+            CommandClass::Discovery,
+            DISC_UNIQUE_BRANCH,
+            data,
+        )
+    }
+
+    /// Encode this request into buf, from its start code through its checksum.
+    /// Transports that carry the start code separately, such as Art-Net, send
+    /// everything after it.
+    pub fn encode(&self, buf: &mut [u8]) -> Option<usize> {
+        if self.data.len() > MAX_PARAMETER_DATA_SIZE {
+            return None;
+        }
+        let len = HEADER_SIZE + self.data.len();
+        let message = buf.get_mut(..len + CHECKSUM_SIZE)?;
+        message[..3].copy_from_slice(&[START_CODE, SUB_START_CODE, len as u8]);
+        message[3..9].copy_from_slice(&self.destination.to_bytes());
+        message[9..15].copy_from_slice(&self.source.to_bytes());
+        message[15..18].copy_from_slice(&[self.transaction_number, self.port_id, 0]);
+        message[18..20].copy_from_slice(&self.sub_device.to_be_bytes());
+        message[20] = self.command_class.to_byte();
+        message[21..23].copy_from_slice(&self.pid.to_be_bytes());
+        message[23] = self.data.len() as u8;
+        message[HEADER_SIZE..len].copy_from_slice(&self.data);
+        let sum = checksum(&message[..len]);
+        message[len..].copy_from_slice(&sum.to_be_bytes());
+        Some(message.len())
+    }
+}
+
+/// A responder's answer to a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub destination: Uid,
+    pub source: Uid,
+    pub transaction_number: u8,
+    pub response_type: ResponseType,
+    /// How many more queued messages the responder holds.
+    pub message_count: u8,
+    pub sub_device: u16,
+    pub command_class: CommandClass,
+    pub pid: u16,
+    /// The parameter data. For a timer or NACK, this is consumed by the
+    /// response type.
+    pub data: Vec<u8>,
+}
+
+impl Response {
+    /// Decode a response, starting at its start code, and check its checksum.
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        let header = buf.get(..HEADER_SIZE)?;
+        if header[..2] != [START_CODE, SUB_START_CODE] {
+            return None;
+        }
+        let len = header[2] as usize;
+        if len != HEADER_SIZE + header[23] as usize {
+            return None;
+        }
+        let sum = buf.get(len..len + CHECKSUM_SIZE)?;
+        if checksum(&buf[..len]).to_be_bytes() != sum {
+            return None;
+        }
+        let data = &buf[HEADER_SIZE..len];
+        let data_u16 = || Some(u16::from_be_bytes(data.try_into().ok()?));
+        let response_type = match header[16] {
+            0 => ResponseType::Ack,
+            1 => ResponseType::AckTimer(data_u16()?),
+            2 => ResponseType::NackReason(data_u16()?),
+            3 => ResponseType::AckOverflow,
+            _ => return None,
+        };
+        Some(Self {
+            destination: Uid::from_bytes(header[3..9].try_into().unwrap()),
+            source: Uid::from_bytes(header[9..15].try_into().unwrap()),
+            transaction_number: header[15],
+            response_type,
+            message_count: header[17],
+            sub_device: u16::from_be_bytes([header[18], header[19]]),
+            command_class: CommandClass::from_byte(header[20])?,
+            pid: u16::from_be_bytes([header[21], header[22]]),
+            data: data.to_vec(),
+        })
+    }
+}
+
+/// Describe a NACK reason code.
+pub fn nack_reason(code: u16) -> &'static str {
+    match code {
+        0x0000 => "unknown parameter",
+        0x0001 => "format error",
+        0x0002 => "hardware fault",
+        0x0003 => "proxy reject",
+        0x0004 => "write protected",
+        0x0005 => "unsupported command class",
+        0x0006 => "data out of range",
+        0x0007 => "buffer full",
+        0x0008 => "packet size unsupported",
+        0x0009 => "sub device out of range",
+        0x000A => "proxy buffer full",
+        _ => "unknown reason",
+    }
+}
+
+/// A responder's answer to a DEVICE_INFO request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub protocol_version: u16,
+    pub model_id: u16,
+    pub product_category: u16,
+    pub software_version_id: u32,
+    /// How many DMX channels the current personality uses.
+    pub dmx_footprint: u16,
+    /// The current personality, counting from 1.
+    pub personality: u8,
+    pub personality_count: u8,
+    /// The first DMX channel the responder listens to, counting from 1, or
+    /// 0xFFFF if it has no footprint.
+    pub dmx_start_address: u16,
+    pub sub_device_count: u16,
+    pub sensor_count: u8,
+}
+
+impl DeviceInfo {
+    /// Decode the parameter data of a DEVICE_INFO response.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let data: &[u8; 19] = data.get(..19)?.try_into().unwrap();
+        let u16_at = |i: usize| u16::from_be_bytes([data[i], data[i + 1]]);
+        Some(Self {
+            protocol_version: u16_at(0),
+            model_id: u16_at(2),
+            product_category: u16_at(4),
+            software_version_id: u32::from_be_bytes([data[6], data[7], data[8], data[9]]),
+            dmx_footprint: u16_at(10),
+            personality: data[12],
+            personality_count: data[13],
+            dmx_start_address: u16_at(14),
+            sub_device_count: u16_at(16),
+            sensor_count: data[18],
+        })
+    }
+}
+
+/// What one of a responder's sensors measures, from SENSOR_DEFINITION.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SensorDefinition {
+    pub sensor: u8,
+    /// The E1.20 sensor type, such as 0x00 for temperature.
+    pub sensor_type: u8,
+    /// The E1.20 unit, such as 0x01 for degrees Celsius.
+    pub unit: u8,
+    /// The E1.20 power of ten prefix of the unit.
+    pub prefix: u8,
+    pub range: (i16, i16),
+    pub normal_range: (i16, i16),
+    /// Whether the sensor records values, and tracks its lowest and highest.
+    pub recorded_value_support: u8,
+    pub description: String,
+}
+
+impl SensorDefinition {
+    /// Decode the parameter data of a SENSOR_DEFINITION response.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let header = data.get(..13)?;
+        let i16_at = |i: usize| i16::from_be_bytes([header[i], header[i + 1]]);
+        Some(Self {
+            sensor: header[0],
+            sensor_type: header[1],
+            unit: header[2],
+            prefix: header[3],
+            range: (i16_at(4), i16_at(6)),
+            normal_range: (i16_at(8), i16_at(10)),
+            recorded_value_support: header[12],
+            description: String::from_utf8_lossy(&data[13..])
+                .trim_end_matches('\0')
+                .to_string(),
+        })
+    }
+}
+
+/// The readings of one of a responder's sensors, from SENSOR_VALUE. Fields a
+/// sensor doesn't support are 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensorValue {
+    pub sensor: u8,
+    pub present: i16,
+    pub lowest: i16,
+    pub highest: i16,
+    pub recorded: i16,
+}
+
+impl SensorValue {
+    /// Decode the parameter data of a SENSOR_VALUE response.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let data = data.get(..9)?;
+        let i16_at = |i: usize| i16::from_be_bytes([data[i], data[i + 1]]);
+        Some(Self {
+            sensor: data[0],
+            present: i16_at(1),
+            lowest: i16_at(3),
+            highest: i16_at(5),
+            recorded: i16_at(7),
+        })
+    }
+}
+
+/// A queued report from a responder, such as a fault, from STATUS_MESSAGES.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusMessage {
+    pub sub_device: u16,
+    /// The severity, such as STATUS_ERROR, or with 0x10 added once cleared.
+    pub status_type: u8,
+    /// The E1.20 message ID, which says how to read the data values.
+    pub message_id: u16,
+    pub data: (i16, i16),
+}
+
+impl StatusMessage {
+    /// Size of one message in a STATUS_MESSAGES response.
+    const SIZE: usize = 9;
+
+    /// Decode the parameter data of a STATUS_MESSAGES response, which holds
+    /// any number of messages.
+    pub fn decode_all(data: &[u8]) -> Vec<Self> {
+        data.chunks_exact(Self::SIZE)
+            .map(|m| Self {
+                sub_device: u16::from_be_bytes([m[0], m[1]]),
+                status_type: m[2],
+                message_id: u16::from_be_bytes([m[3], m[4]]),
+                data: (
+                    i16::from_be_bytes([m[5], m[6]]),
+                    i16::from_be_bytes([m[7], m[8]]),
+                ),
+            })
+            .collect()
+    }
+}
+
+/// A transport that carries RDM requests to responders and their responses
+/// back, with helpers for common parameters built on top.
+pub trait RdmTransport {
+    /// Send request, and return the response, or None if none arrived. The
+    /// transport sets the transaction number.
+    fn rdm_request(&mut self, request: &Request) -> anyhow::Result<Option<Response>>;
+
+    /// Wait before asking a responder that answered with a timer again.
+    fn rdm_wait(&mut self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    /// Send a request to uid and return the parameter data of its
+    /// acknowledgement. Responses that overflow into several messages are
+    /// collected and joined.
+    ///
+    /// A responder that needs time answers with a timer. As E1.20 describes,
+    /// the controller then waits that long and collects the answer with a
+    /// GET of QUEUED_MESSAGE, which comes back carrying the original pid.
+    fn rdm_command(
+        &mut self,
+        uid: Uid,
+        command_class: CommandClass,
+        pid: u16,
+        data: Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut request = Request::new(uid, command_class, pid, data);
+        let mut data = Vec::new();
+        let mut ack_timers = 0;
+        loop {
+            let response = self
+                .rdm_request(&request)?
+                .ok_or_else(|| anyhow!("no RDM response from {uid}"))?;
+            // A responder still busy with the queued answer times the
+            // QUEUED_MESSAGE request itself.
+            let still_busy = request.pid == QUEUED_MESSAGE
+                && response.pid == QUEUED_MESSAGE
+                && matches!(response.response_type, ResponseType::AckTimer(_));
+            if response.source != uid
+                || !still_busy
+                    && (response.pid != pid || response.command_class != command_class.response())
+            {
+                bail!("mismatched RDM response from {}", response.source);
+            }
+            match response.response_type {
+                ResponseType::Ack => {
+                    data.extend_from_slice(&response.data);
+                    return Ok(data);
+                }
+                ResponseType::AckOverflow => data.extend_from_slice(&response.data),
+                ResponseType::AckTimer(tenths) => {
+                    ack_timers += 1;
+                    if ack_timers > MAX_ACK_TIMERS {
+                        bail!("{uid} is still busy after {MAX_ACK_TIMERS} retries");
+                    }
+                    self.rdm_wait(Duration::from_millis(tenths as u64 * 100));
+                    request =
+                        Request::new(uid, CommandClass::Get, QUEUED_MESSAGE, vec![STATUS_ERROR]);
+                }
+                ResponseType::NackReason(code) => bail!(
+                    "{uid} rejected request for parameter {pid:#06x}: {}",
+                    nack_reason(code)
+                ),
+            }
+        }
+    }
+
+    /// GET a parameter from uid.
+    fn rdm_get(&mut self, uid: Uid, pid: u16, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        self.rdm_command(uid, CommandClass::Get, pid, data)
+    }
+
+    /// SET a parameter on uid.
+    fn rdm_set(&mut self, uid: Uid, pid: u16, data: Vec<u8>) -> anyhow::Result<()> {
+        self.rdm_command(uid, CommandClass::Set, pid, data)?;
+        Ok(())
+    }
+
+    /// Return uid's model, footprint, personality, and start address.
+    fn device_info(&mut self, uid: Uid) -> anyhow::Result<DeviceInfo> {
+        let data = self.rdm_get(uid, DEVICE_INFO, Vec::new())?;
+        DeviceInfo::decode(&data).ok_or_else(|| anyhow!("malformed device info from {uid}"))
+    }
+
+    /// Return the first DMX channel uid listens to, counting from 1.
+    fn dmx_start_address(&mut self, uid: Uid) -> anyhow::Result<u16> {
+        let data = self.rdm_get(uid, DMX_START_ADDRESS, Vec::new())?;
+        let address = data
+            .get(..2)
+            .ok_or_else(|| anyhow!("malformed start address from {uid}"))?;
+        Ok(u16::from_be_bytes([address[0], address[1]]))
+    }
+
+    /// Set the first DMX channel uid listens to, from 1 to 512.
+    fn set_dmx_start_address(&mut self, uid: Uid, address: u16) -> anyhow::Result<()> {
+        if !(1..=512).contains(&address) {
+            bail!("DMX start address {address} is out of range");
+        }
+        self.rdm_set(uid, DMX_START_ADDRESS, address.to_be_bytes().to_vec())
+    }
+
+    /// Return uid's current personality and how many it has, counting from 1.
+    fn dmx_personality(&mut self, uid: Uid) -> anyhow::Result<(u8, u8)> {
+        match self.rdm_get(uid, DMX_PERSONALITY, Vec::new())?[..] {
+            [current, count, ..] => Ok((current, count)),
+            _ => bail!("malformed personality from {uid}"),
+        }
+    }
+
+    /// Switch uid to a personality, counting from 1.
+    fn set_dmx_personality(&mut self, uid: Uid, personality: u8) -> anyhow::Result<()> {
+        self.rdm_set(uid, DMX_PERSONALITY, vec![personality])
+    }
+
+    /// Return whether uid is identifying itself.
+    fn is_identifying(&mut self, uid: Uid) -> anyhow::Result<bool> {
+        match self.rdm_get(uid, IDENTIFY_DEVICE, Vec::new())?[..] {
+            [identifying, ..] => Ok(identifying != 0),
+            _ => bail!("malformed identify state from {uid}"),
+        }
+    }
+
+    /// Start or stop uid identifying itself, such as by flashing, so it can be
+    /// found in the rig.
+    fn identify(&mut self, uid: Uid, on: bool) -> anyhow::Result<()> {
+        self.rdm_set(uid, IDENTIFY_DEVICE, vec![on as u8])
+    }
+
+    /// Return what one of uid's sensors measures. Sensors count from 0, up to
+    /// the sensor count in its device info.
+    fn sensor_definition(&mut self, uid: Uid, sensor: u8) -> anyhow::Result<SensorDefinition> {
+        let data = self.rdm_get(uid, SENSOR_DEFINITION, vec![sensor])?;
+        SensorDefinition::decode(&data)
+            .ok_or_else(|| anyhow!("malformed sensor definition from {uid}"))
+    }
+
+    /// Return the readings of one of uid's sensors.
+    fn sensor_value(&mut self, uid: Uid, sensor: u8) -> anyhow::Result<SensorValue> {
+        let data = self.rdm_get(uid, SENSOR_VALUE, vec![sensor])?;
+        SensorValue::decode(&data).ok_or_else(|| anyhow!("malformed sensor value from {uid}"))
+    }
+
+    /// Collect uid's queued status messages of at least the given severity,
+    /// such as STATUS_WARNING. Each message is only reported once.
+    fn status_messages(&mut self, uid: Uid, severity: u8) -> anyhow::Result<Vec<StatusMessage>> {
+        let data = self.rdm_get(uid, STATUS_MESSAGES, vec![severity])?;
+        Ok(StatusMessage::decode_all(&data))
+    }
+
+    /// Flip whether uid is identifying itself, and return the new state.
+    fn toggle_identify(&mut self, uid: Uid) -> anyhow::Result<bool> {
+        let on = !self.is_identifying(uid)?;
+        self.identify(uid, on)?;
+        Ok(on)
+    }
+}
+
+/// Decode the answer to a DISC_UNIQUE_BRANCH request, which unlike other
+/// responses has no start code, and is encoded so that a lone responder can
+/// be told apart from several answering at once. Return None if it doesn't
+/// decode, which usually means several responders collided.
+pub fn decode_discovery_response(buf: &[u8]) -> Option<Uid> {
+    let start = buf.iter().position(|&b| b == DISCOVERY_DELIMITER)?;
+    if start > 7 || buf[..start].iter().any(|&b| b != DISCOVERY_PREAMBLE) {
+        return None;
+    }
+    let encoded = buf.get(start + 1..start + 1 + DISCOVERY_RESPONSE_SIZE)?;
+    // Each byte is sent twice, once with the bits of 0xAA set and once with
+    // those of 0x55, so AND recovers it.
+    let decoded: Vec<u8> = encoded
+        .chunks_exact(2)
+        .map(|pair| pair[0] & pair[1])
+        .collect();
+    if checksum(&encoded[..12]).to_be_bytes() != decoded[6..] {
+        return None;
+    }
+    Some(Uid::from_bytes(decoded[..6].try_into().unwrap()))
+}
+
+/// Encode the answer of a lone responder with uid to a DISC_UNIQUE_BRANCH
+/// request, such as for a responder simulator.
+pub fn encode_discovery_response(uid: Uid) -> Vec<u8> {
+    let mut encoded = vec![DISCOVERY_PREAMBLE, DISCOVERY_DELIMITER];
+    for byte in uid.to_bytes() {
+        encoded.extend_from_slice(&[byte | 0xAA, byte | 0x55]);
+    }
+    let sum = checksum(&encoded[2..]);
+    for byte in sum.to_be_bytes() {
+        encoded.extend_from_slice(&[byte | 0xAA, byte | 0x55]);
+    }
+    encoded
+}
+
+/// What came back from a DISC_UNIQUE_BRANCH request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Branch {
+    /// No responder in the range answered.
+    Empty,
+    /// Exactly one responder answered.
+    Found(Uid),
+    /// Several responders answered at once, garbling the response.
+    Collision,
+}
+
+/// The discovery requests of a transport that can reach RDM responders.
+pub trait RdmDiscovery {
+    /// Let every responder answer discovery again.
+    fn un_mute_all(&mut self) -> anyhow::Result<()>;
+
+    /// Ask unmuted responders with UIDs from lower to upper to identify themselves.
+    fn unique_branch(&mut self, lower: Uid, upper: Uid) -> anyhow::Result<Branch>;
+
+    /// Stop uid answering discovery. Return true if it acknowledged.
+    fn mute(&mut self, uid: Uid) -> anyhow::Result<bool>;
+}
+
+/// Find every responder the transport reaches, by binary search of the UID
+/// space. Responders are muted once found, so each range is searched until
+/// it falls silent, and ranges where answers collide are split in half.
+pub fn discover(transport: &mut impl RdmDiscovery) -> anyhow::Result<Vec<Uid>> {
+    transport.un_mute_all()?;
+    let mut found = Vec::new();
+    let mut ranges = vec![(0, Uid::MAX.to_u64())];
+    while let Some((lower, upper)) = ranges.pop() {
+        match transport.unique_branch(Uid::from_u64(lower), Uid::from_u64(upper))? {
+            Branch::Empty => {}
+            // A responder that won't mute would answer forever, so its range
+            // is split as if it collided until it is narrowed out.
+            Branch::Found(uid) if transport.mute(uid)? => {
+                found.push(uid);
+                ranges.push((lower, upper));
+            }
+            Branch::Found(_) | Branch::Collision => {
+                if lower < upper {
+                    let mid = lower + (upper - lower) / 2;
+                    ranges.push((mid + 1, upper));
+                    ranges.push((lower, mid));
+                }
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() -> anyhow::Result<()> {
+        let uid: Uid = "454E:0000002A".parse()?;
+        assert_eq!(Uid::new(0x454E, 42), uid);
+        assert_eq!("454E:0000002A", uid.to_string());
+
+        let request = Request {
+            destination: uid,
+            source: Uid::new(0x7FF0, 1),
+            transaction_number: 3,
+            port_id: 1,
+            sub_device: ROOT_DEVICE,
+            command_class: CommandClass::Get,
+            pid: DMX_START_ADDRESS,
+            data: Vec::new(),
+        };
+        let mut buf = [0; MAX_MESSAGE_SIZE];
+        let len = request.encode(&mut buf).unwrap();
+        assert_eq!(26, len);
+
+        // Turn the request into an ACK carrying a start address of 1.
+        buf[2] = 26;
+        buf[16] = 0;
+        buf[20] = CommandClass::GetResponse.to_byte();
+        buf[23] = 2;
+        buf[24..26].copy_from_slice(&[0, 1]);
+        let sum = checksum(&buf[..26]);
+        buf[26..28].copy_from_slice(&sum.to_be_bytes());
+        let response = Response::decode(&buf[..28]).unwrap();
+        assert_eq!(
+            (ResponseType::Ack, DMX_START_ADDRESS, vec![0, 1]),
+            (response.response_type, response.pid, response.data)
+        );
+        buf[27] ^= 1;
+        assert_eq!(None, Response::decode(&buf[..28]));
+        Ok(())
+    }
+
+    /// A responder that answers requests from a script of responses.
+    #[derive(Default)]
+    struct Scripted {
+        responses: std::collections::VecDeque<(ResponseType, u16, Vec<u8>)>,
+        requests: Vec<(CommandClass, u16, Vec<u8>)>,
+        waited: Vec<Duration>,
+    }
+
+    impl RdmTransport for Scripted {
+        fn rdm_request(&mut self, request: &Request) -> anyhow::Result<Option<Response>> {
+            self.requests
+                .push((request.command_class, request.pid, request.data.clone()));
+            Ok(self
+                .responses
+                .pop_front()
+                .map(|(response_type, pid, data)| Response {
+                    destination: request.source,
+                    source: request.destination,
+                    transaction_number: request.transaction_number,
+                    response_type,
+                    message_count: 0,
+                    sub_device: ROOT_DEVICE,
+                    command_class: CommandClass::SetResponse,
+                    pid,
+                    data,
+                }))
+        }
+
+        fn rdm_wait(&mut self, duration: Duration) {
+            self.waited.push(duration);
+        }
+    }
+
+    #[test]
+    fn test_ack_timer_collects_queued_message() -> anyhow::Result<()> {
+        let uid = Uid::new(0x454E, 1);
+        let mut responder = Scripted::default();
+        responder.responses.extend([
+            (ResponseType::AckTimer(5), DMX_START_ADDRESS, Vec::new()),
+            (ResponseType::AckTimer(2), QUEUED_MESSAGE, Vec::new()),
+            (ResponseType::Ack, DMX_START_ADDRESS, Vec::new()),
+        ]);
+        responder.set_dmx_start_address(uid, 17)?;
+        let queued = (CommandClass::Get, QUEUED_MESSAGE, vec![STATUS_ERROR]);
+        assert_eq!(
+            vec![
+                (CommandClass::Set, DMX_START_ADDRESS, vec![0, 17]),
+                queued.clone(),
+                queued,
+            ],
+            responder.requests
+        );
+        assert_eq!(
+            vec![Duration::from_millis(500), Duration::from_millis(200)],
+            responder.waited
+        );
+
+        // A responder that never finishes is given up on.
+        let busy = (ResponseType::AckTimer(1), DMX_START_ADDRESS, Vec::new());
+        responder.responses.push_back(busy);
+        let still_busy = (ResponseType::AckTimer(1), QUEUED_MESSAGE, Vec::new());
+        responder
+            .responses
+            .extend(std::iter::repeat_n(still_busy, MAX_ACK_TIMERS));
+        assert!(responder.set_dmx_start_address(uid, 17).is_err());
+        assert!(responder.responses.is_empty());
+        Ok(())
+    }
+
+    /// Responders that answer discovery the way real ones would.
+    struct Responders {
+        unmuted: Vec<Uid>,
+        all: Vec<Uid>,
+    }
+
+    impl RdmDiscovery for Responders {
+        fn un_mute_all(&mut self) -> anyhow::Result<()> {
+            self.unmuted = self.all.clone();
+            Ok(())
+        }
+
+        fn unique_branch(&mut self, lower: Uid, upper: Uid) -> anyhow::Result<Branch> {
+            let answering: Vec<_> = self
+                .unmuted
+                .iter()
+                .filter(|uid| (lower..=upper).contains(uid))
+                .collect();
+            Ok(match answering[..] {
+                [] => Branch::Empty,
+                [&uid] => decode_discovery_response(&encode_discovery_response(uid))
+                    .map_or(Branch::Collision, Branch::Found),
+                _ => Branch::Collision,
+            })
+        }
+
+        fn mute(&mut self, uid: Uid) -> anyhow::Result<bool> {
+            self.unmuted.retain(|&u| u != uid);
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_discover() -> anyhow::Result<()> {
+        let all = vec![
+            Uid::new(0x454E, 1),
+            Uid::new(0x454E, 2),
+            Uid::new(0x4D41, 0x1234_5678),
+        ];
+        let mut responders = Responders {
+            unmuted: Vec::new(),
+            all: all.clone(),
+        };
+        assert_eq!(all, discover(&mut responders)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_sensors() {
+        let value = SensorValue::decode(&[1, 0, 45, 0, 20, 0, 60, 0, 0]).unwrap();
+        assert_eq!(
+            (1, 45, 20, 60),
+            (value.sensor, value.present, value.lowest, value.highest)
+        );
+
+        let mut definition = vec![1, 0, 1, 0, 0xFF, 0xF6, 0, 100, 0, 0, 0, 70, 3];
+        definition.extend_from_slice(b"LED\0");
+        let definition = SensorDefinition::decode(&definition).unwrap();
+        assert_eq!((-10, 100), definition.range);
+        assert_eq!("LED", definition.description);
+
+        let messages = StatusMessage::decode_all(&[0, 0, STATUS_ERROR, 0, 1, 0, 2, 0, 0, 0]);
+        assert_eq!(1, messages.len());
+        assert_eq!(
+            (STATUS_ERROR, 1, (2, 0)),
+            (
+                messages[0].status_type,
+                messages[0].message_id,
+                messages[0].data
+            )
+        );
+    }
+}
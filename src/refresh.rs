@@ -0,0 +1,207 @@
+//! A port that keeps re-sending its last frame so fixtures see a steady refresh.
+use log::debug;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::clock::{deserialize_fps, frame_interval};
+use crate::keep_alive::KeepAlive;
+use crate::{system_clock, Clock, DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError};
+
+/// Re-send the last frame written to the inner port whenever no new frame has
+/// been written for one refresh interval.
+///
+/// Some dimmers flicker or shut down when the DMX line goes quiet, which
+/// happens when an application only writes when something changes. Frames
+/// written through this port go out immediately; the refresh thread only
+/// fills the gaps between them, so the line carries at least fps frames per
+/// second while the port is open.
+#[derive(Serialize, Deserialize)]
+pub struct RefreshPort {
+    #[serde(with = "shared_port")]
+    inner: Arc<Mutex<Box<dyn DmxPort>>>,
+    /// The minimum output rate in frames per second.
+    #[serde(deserialize_with = "deserialize_fps")]
+    fps: f64,
+    #[serde(skip)]
+    refresher: Option<KeepAlive>,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+impl RefreshPort {
+    /// Wrap inner, refreshing it at least fps times per second.
+    /// Fail unless fps is a positive rate.
+    pub fn new(inner: Box<dyn DmxPort>, fps: f64) -> anyhow::Result<Self> {
+        frame_interval(fps)?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(inner)),
+            fps,
+            refresher: None,
+            clock: system_clock(),
+        })
+    }
+
+    /// Time the refresh interval using clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Unwrap this port into the inner port, stopping the refresh.
+    pub fn into_inner(mut self) -> Box<dyn DmxPort> {
+        self.refresher = None;
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => inner.into_inner().unwrap(),
+            Err(_) => unreachable!("the refresh thread has been joined"),
+        }
+    }
+
+    fn interval(&self) -> Duration {
+        frame_interval(self.fps).expect("fps is checked when the port is created or loaded")
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for RefreshPort {
+    /// Refresh ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.inner.lock().unwrap().open()?;
+        if self.refresher.is_none() {
+            let inner = self.inner.clone();
+            let refresher = KeepAlive::start(self.interval(), self.clock.clone(), move |frame| {
+                let mut port = inner.lock().unwrap();
+                if let Err(err) = port.write(frame) {
+                    debug!("Failed to refresh {}: {}.", port, err);
+                }
+            });
+            self.refresher = Some(refresher);
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        // Stop refreshing first, so the thread doesn't reopen the port.
+        self.refresher = None;
+        self.inner.lock().unwrap().close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        if let Some(refresher) = &self.refresher {
+            refresher.sending(frame);
+        }
+        self.inner.lock().unwrap().write(frame)
+    }
+
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        if let Some(refresher) = &self.refresher {
+            refresher.sending(frame);
+        }
+        self.inner.lock().unwrap().write_blackout(frame)
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.inner.lock().unwrap().write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.inner.lock().unwrap().sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.lock().unwrap().frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.inner.lock().unwrap().max_fps()
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.lock().unwrap().captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.lock().unwrap().migrate(from_version);
+    }
+}
+
+impl fmt::Display for RefreshPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (refreshed at {} fps)",
+            self.inner.lock().unwrap(),
+            self.fps
+        )
+    }
+}
+
+/// Serialize the shared inner port as if it were a plain boxed port.
+mod shared_port {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        port: &Arc<Mutex<Box<dyn DmxPort>>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        port.lock().unwrap().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Arc<Mutex<Box<dyn DmxPort>>>, D::Error> {
+        Ok(Arc::new(Mutex::new(Box::deserialize(deserializer)?)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+    use crate::ManualClock;
+
+    #[test]
+    fn test_refreshes_last_frame() {
+        let inner = TestPort::default();
+        let clock = Arc::new(ManualClock::new());
+        let mut port = RefreshPort::new(Box::new(inner.clone()), 100.0)
+            .unwrap()
+            .with_clock(clock.clone());
+        port.open().unwrap();
+
+        // Nothing is refreshed until a frame has been written.
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(0, inner.writes());
+
+        port.write(&[1, 2, 3]).unwrap();
+        for writes in 2..4 {
+            assert_eq!(
+                clock.now() + Duration::from_millis(10),
+                clock.wait_for_deadline()
+            );
+            clock.advance(Duration::from_millis(10));
+            assert!(inner.wait_for_writes(writes, Duration::from_secs(1)));
+        }
+        assert_eq!(vec![vec![1, 2, 3]; 3], inner.frames());
+
+        port.close();
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(3, inner.writes());
+    }
+
+    #[test]
+    fn test_rejects_invalid_rates() {
+        for fps in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::MIN_POSITIVE] {
+            assert!(RefreshPort::new(Box::new(TestPort::default()), fps).is_err());
+        }
+        let port = RefreshPort::new(Box::new(TestPort::default()), 40.0).unwrap();
+        let saved = serde_json::to_string(&(Box::new(port) as Box<dyn DmxPort>)).unwrap();
+        let zero = saved.replace("40.0", "0.0");
+        assert!(serde_json::from_str::<Box<dyn DmxPort>>(&zero).is_err());
+    }
+}
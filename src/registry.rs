@@ -0,0 +1,236 @@
+//! A named collection of running outputs.
+use log::warn;
+use std::collections::BTreeMap;
+
+use crate::{sender, BackgroundSender, DmxPort, FrameWatch, OpenError, SenderConfig};
+
+/// A saved port setup: output names mapped to the port each should drive.
+pub type PortConfig = BTreeMap<String, Box<dyn DmxPort>>;
+
+/// Immediately start writing all-zero frames to every output in the process
+/// that is driven by a `BackgroundSender`, including the outputs of every
+/// registry and the daemon, and hold them dark until
+/// `release_senders_blackout` is called.
+///
+/// Queued and newly sent frames are not written while the blackout is latched;
+/// outputs resume with the newest frame sent to them once it is released.
+/// The zeros skip the sender's queue and are written with
+/// `DmxPort::write_blackout`, so wrapper ports such as transforms pass them
+/// to the hardware unchanged.
+///
+/// Only sender-driven outputs are reached. Ports the application opens and
+/// writes to directly keep whatever they were last sent; wrap them in a
+/// `BlackoutOnClosePort` or write zeros to them as well.
+pub fn emergency_blackout_senders() {
+    warn!("Emergency blackout.");
+    sender::set_blackout(true);
+}
+
+/// Release a blackout latched by `emergency_blackout_senders`, resuming
+/// normal output.
+pub fn release_senders_blackout() {
+    warn!("Emergency blackout released.");
+    sender::set_blackout(false);
+}
+
+/// Stop an output's sender and return its port, logging a panic that lost it.
+fn stop_output(name: &str, output: Output) -> Option<Box<dyn DmxPort>> {
+    match output.sender.stop() {
+        Ok(port) => Some(port),
+        Err(err) => {
+            warn!(
+                "The output thread for {name} panicked: {}.",
+                sender::panic_message(&*err)
+            );
+            None
+        }
+    }
+}
+
+/// A set of named outputs, each driven by its own background sender.
+///
+/// Because every output runs on its own thread, adding, removing, or
+/// retargeting one output never interrupts the others.
+pub struct PortRegistry {
+    outputs: BTreeMap<String, Output>,
+    config: SenderConfig,
+}
+
+struct Output {
+    /// The serialized config of the port, used to tell whether a reloaded
+    /// config points this output at a different port. None if the port
+    /// couldn't be serialized, in which case a reload always retargets it.
+    target: Option<serde_json::Value>,
+    sender: BackgroundSender,
+}
+
+/// The changes made when applying a config to a registry.
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub retargeted: Vec<String>,
+    /// Outputs whose new port failed to open. They are still registered, and
+    /// ports that support it will keep trying to reopen on write.
+    pub open_errors: Vec<(String, OpenError)>,
+}
+
+impl PortRegistry {
+    /// Create an empty registry; outputs will be driven using config, each at
+    /// config's rate or its port's `max_fps`, whichever is lower.
+    pub fn new(config: SenderConfig) -> Self {
+        Self {
+            outputs: BTreeMap::new(),
+            config,
+        }
+    }
+
+    /// Start driving a port under the provided name, which should already be open.
+    /// Return the port previously registered under that name, if any and it
+    /// wasn't lost to a panic.
+    pub fn insert(&mut self, name: String, port: Box<dyn DmxPort>) -> Option<Box<dyn DmxPort>> {
+        let config = self.config.clone().limited_to_port(&*port);
+        let output = Output {
+            target: serialized(&*port),
+            sender: BackgroundSender::spawn(port, config),
+        };
+        let old = self.outputs.insert(name.clone(), output)?;
+        stop_output(&name, old)
+    }
+
+    /// Stop driving the named output and return its port.
+    /// Return None if there is no output with that name, or its port was lost
+    /// because writing to it panicked.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn DmxPort>> {
+        let output = self.outputs.remove(name)?;
+        stop_output(name, output)
+    }
+
+    /// Queue a frame for the named output.
+    /// Return false if there is no output with that name, or its output
+    /// thread has died.
+    pub fn send(&self, name: &str, frame: &[u8]) -> bool {
+        let Some(output) = self.outputs.get(name) else {
+            return false;
+        };
+        if let Err(err) = output.sender.send(frame) {
+            warn!("Failed to send to output {}: {}.", name, err);
+            return false;
+        }
+        true
+    }
+
+    /// Observe the frames written to the named output.
+    /// Return None if there is no output with that name.
+    pub fn subscribe(&self, name: &str) -> Option<FrameWatch> {
+        Some(self.outputs.get(name)?.sender.subscribe())
+    }
+
+    /// Return the names of the registered outputs, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.outputs.keys().map(String::as_str)
+    }
+
+    /// Bring the registry in line with config.
+    ///
+    /// Outputs missing from the config are stopped and closed, new outputs are
+    /// opened and started, and outputs whose port changed are retargeted.
+    /// Outputs that are unchanged keep running undisturbed.
+    pub fn apply(&mut self, config: PortConfig) -> ReloadReport {
+        let mut report = ReloadReport::default();
+
+        let removed: Vec<String> = self
+            .outputs
+            .keys()
+            .filter(|name| !config.contains_key(*name))
+            .cloned()
+            .collect();
+        for name in removed {
+            if let Some(mut port) = self.remove(&name) {
+                port.close();
+            }
+            report.removed.push(name);
+        }
+
+        for (name, mut port) in config {
+            let retarget = match self.outputs.get(&name) {
+                Some(output) if output.target.is_some() && output.target == serialized(&*port) => {
+                    continue
+                }
+                Some(_) => true,
+                None => false,
+            };
+            if let Err(err) = port.open() {
+                warn!("Failed to open {} for output {}: {}.", port, name, err);
+                report.open_errors.push((name.clone(), err));
+            }
+            if let Some(mut old) = self.insert(name.clone(), port) {
+                old.close();
+            }
+            if retarget {
+                report.retargeted.push(name);
+            } else {
+                report.added.push(name);
+            }
+        }
+        report
+    }
+}
+
+/// Return the config of port as it would be saved.
+fn serialized(port: &dyn DmxPort) -> Option<serde_json::Value> {
+    serde_json::to_value(port).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{OfflineDmxPort, SafetyPort, SafetyRule, TeePort};
+
+    fn config(entries: Vec<(&str, Box<dyn DmxPort>)>) -> PortConfig {
+        entries
+            .into_iter()
+            .map(|(name, port)| (name.to_string(), port))
+            .collect()
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut registry = PortRegistry::new(SenderConfig::default());
+        let report = registry.apply(config(vec![
+            ("a", Box::new(OfflineDmxPort)),
+            ("b", Box::new(OfflineDmxPort)),
+        ]));
+        assert_eq!(vec!["a", "b"], report.added);
+
+        let tee = TeePort::new(Box::new(OfflineDmxPort), Box::new(OfflineDmxPort));
+        let report = registry.apply(config(vec![
+            ("b", Box::new(tee)),
+            ("c", Box::new(OfflineDmxPort)),
+        ]));
+        assert_eq!(vec!["c"], report.added);
+        assert_eq!(vec!["a"], report.removed);
+        assert_eq!(vec!["b"], report.retargeted);
+        assert_eq!(vec!["b", "c"], registry.names().collect::<Vec<_>>());
+
+        let report = registry.apply(config(vec![("c", Box::new(OfflineDmxPort))]));
+        assert_eq!(vec!["b"], report.removed);
+        assert!(report.added.is_empty() && report.retargeted.is_empty());
+    }
+
+    #[test]
+    fn test_apply_retargets_changed_config_with_same_name() {
+        let capped = |max| -> Box<dyn DmxPort> {
+            let rules = vec![SafetyRule::Cap { channel: 0, max }];
+            Box::new(SafetyPort::new(Box::new(OfflineDmxPort), rules))
+        };
+        assert_eq!(capped(100).to_string(), capped(200).to_string());
+        let mut registry = PortRegistry::new(SenderConfig::default());
+        registry.apply(config(vec![("a", capped(100))]));
+
+        let report = registry.apply(config(vec![("a", capped(100))]));
+        assert!(report.retargeted.is_empty());
+        let report = registry.apply(config(vec![("a", capped(200))]));
+        assert_eq!(vec!["a"], report.retargeted);
+    }
+}
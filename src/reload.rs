@@ -0,0 +1,46 @@
+//! Hot-reload a saved port setup into a running registry.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{PortConfig, PortRegistry, ReloadReport};
+
+/// Watch a saved port config file and apply changes to a registry.
+///
+/// The watcher doesn't know the file format; the loader is responsible for
+/// reading and deserializing the config with whichever serde format the
+/// application uses.
+pub struct ConfigWatcher<F> {
+    path: PathBuf,
+    loader: F,
+    modified: Option<SystemTime>,
+}
+
+impl<F> ConfigWatcher<F>
+where
+    F: FnMut(&Path) -> anyhow::Result<PortConfig>,
+{
+    pub fn new(path: impl Into<PathBuf>, loader: F) -> Self {
+        Self {
+            path: path.into(),
+            loader,
+            modified: None,
+        }
+    }
+
+    /// Check whether the config file has changed since the last poll, and if
+    /// so load it and apply it to the registry.
+    ///
+    /// The first poll always loads the file. Return None if nothing changed.
+    /// If the file can't be loaded, the registry is left untouched and the
+    /// load will be retried on the next poll.
+    pub fn poll(&mut self, registry: &mut PortRegistry) -> anyhow::Result<Option<ReloadReport>> {
+        let modified = fs::metadata(&self.path)?.modified()?;
+        if self.modified == Some(modified) {
+            return Ok(None);
+        }
+        let config = (self.loader)(&self.path)?;
+        self.modified = Some(modified);
+        Ok(Some(registry.apply(config)))
+    }
+}
@@ -0,0 +1,798 @@
+//! Support for sACN (ANSI E1.31).
+use anyhow::anyhow;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::net::bind_reusable;
+use crate::{
+    DmxInputPort, DmxPort, InputFrame, OpenError, PortListing, ReadError, WriteError,
+    DMX_UNIVERSE_SIZE,
+};
+pub(crate) use rust_dmx_core::sacn::parse_data_packet;
+use rust_dmx_core::sacn::{
+    encode_data_packet, DataPacket, MAX_DATA_PACKET_SIZE, PREVIEW_DATA, SACN_PORT,
+    STREAM_TERMINATED,
+};
+
+/// The priority of packets sent by an output port unless configured otherwise.
+const DEFAULT_PRIORITY: u8 = 100;
+
+/// The highest valid priority.
+const MAX_PRIORITY: u8 = 200;
+
+/// Sources send this many stream terminated packets when they stop.
+const TERMINATION_PACKETS: usize = 3;
+
+/// The valid range of universe numbers.
+const UNIVERSES: std::ops::RangeInclusive<u16> = 1..=63999;
+
+/// A source that sends nothing for this long is considered gone.
+pub(crate) const SOURCE_TIMEOUT: Duration = Duration::from_millis(2500);
+
+/// A packet whose sequence number is up to this far behind the last one is
+/// out of order and discarded; anything further behind means the source restarted.
+pub(crate) const SEQUENCE_WINDOW: i8 = 20;
+
+/// How many sources an input port tracks on each universe by default.
+const DEFAULT_MAX_SOURCES: usize = 16;
+
+/// Return the multicast group a universe is sent to.
+pub(crate) fn multicast_group(universe: u16) -> Ipv4Addr {
+    let [hi, lo] = universe.to_be_bytes();
+    Ipv4Addr::new(239, 255, hi, lo)
+}
+
+/// Generate a random component identifier, a version 4 UUID.
+fn generate_cid() -> [u8; 16] {
+    Uuid::new_v4().into_bytes()
+}
+
+/// Format a component identifier in the usual UUID form.
+pub(crate) fn format_cid(cid: &[u8; 16]) -> String {
+    Uuid::from_bytes(*cid).hyphenated().to_string()
+}
+
+fn default_udp_port() -> u16 {
+    SACN_PORT
+}
+
+fn default_max_sources() -> usize {
+    DEFAULT_MAX_SOURCES
+}
+
+fn default_source_name() -> String {
+    "rust-dmx".to_string()
+}
+
+fn default_priority() -> u8 {
+    DEFAULT_PRIORITY
+}
+
+/// Send one sACN universe, by multicast or to a list of unicast receivers.
+///
+/// The port's CID is generated when it is created and saved with it, so
+/// receivers keep seeing the same source after the port is reloaded.
+#[derive(Serialize, Deserialize)]
+pub struct SacnDmxPort {
+    universe: u16,
+    #[serde(default = "default_source_name")]
+    source_name: String,
+    #[serde(default = "generate_cid")]
+    cid: [u8; 16],
+    /// Receivers only merge the sources sending at the highest priority.
+    #[serde(default = "default_priority")]
+    priority: u8,
+    /// Send to each of these receivers instead of multicasting, if not empty.
+    #[serde(default)]
+    destinations: Vec<Ipv4Addr>,
+    #[serde(default = "default_udp_port")]
+    udp_port: u16,
+    #[serde(skip)]
+    socket: Option<UdpSocket>,
+    #[serde(skip)]
+    sequence: u8,
+    #[serde(skip)]
+    buffer: Vec<u8>,
+}
+
+impl SacnDmxPort {
+    /// Create a port that sends to a universe from 1 to 63999.
+    pub fn new(universe: u16) -> Self {
+        Self {
+            universe,
+            source_name: default_source_name(),
+            cid: generate_cid(),
+            priority: DEFAULT_PRIORITY,
+            destinations: Vec::new(),
+            udp_port: SACN_PORT,
+            socket: None,
+            sequence: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Identify the source to receivers by this name instead.
+    pub fn with_source_name(mut self, name: impl Into<String>) -> Self {
+        self.source_name = name.into();
+        self
+    }
+
+    /// Send at a priority from 0 to 200 instead of the default of 100, so that
+    /// receivers prefer or ignore this source when others send the same universe.
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.set_priority(priority);
+        self
+    }
+
+    /// Change the priority, from 0 to 200, that packets are sent at.
+    pub fn set_priority(&mut self, priority: u8) {
+        self.priority = priority.min(MAX_PRIORITY);
+    }
+
+    /// Send every packet to each of these receivers instead of multicasting,
+    /// for networks that block multicast.
+    pub fn with_unicast(mut self, destinations: Vec<Ipv4Addr>) -> Self {
+        self.destinations = destinations;
+        self
+    }
+
+    /// Send to receivers listening on udp_port instead of the standard sACN port.
+    pub fn with_udp_port(mut self, udp_port: u16) -> Self {
+        self.udp_port = udp_port;
+        self
+    }
+
+    /// Return the CID this port sends as, formatted the way `SacnInputPort`
+    /// reports sources.
+    pub fn source_id(&self) -> String {
+        format_cid(&self.cid)
+    }
+
+    /// Send a data packet with the next sequence number.
+    /// Every unicast receiver is sent to even if some fail; the first failure
+    /// is returned.
+    fn send(&mut self, options: u8, start_code: u8, levels: &[u8]) -> Result<(), WriteError> {
+        let socket = self.socket.as_ref().ok_or(WriteError::Disconnected)?;
+        self.sequence = self.sequence.wrapping_add(1);
+        let packet = DataPacket {
+            cid: self.cid,
+            source_name: self.source_name.clone(),
+            // A hand-edited config may hold an out of range priority.
+            priority: self.priority.min(MAX_PRIORITY),
+            sequence: self.sequence,
+            options,
+            universe: self.universe,
+            start_code,
+            levels: &levels[..levels.len().min(DMX_UNIVERSE_SIZE)],
+        };
+        self.buffer.resize(MAX_DATA_PACKET_SIZE, 0);
+        let len = encode_data_packet(&packet, &mut self.buffer)
+            .expect("levels were truncated to fit the buffer");
+        let packet = &self.buffer[..len];
+        if self.destinations.is_empty() {
+            socket
+                .send_to(packet, (multicast_group(self.universe), self.udp_port))
+                .map_err(anyhow::Error::from)?;
+            return Ok(());
+        }
+        let mut result = Ok(());
+        for &destination in &self.destinations {
+            if let Err(err) = socket.send_to(packet, (destination, self.udp_port)) {
+                if result.is_ok() {
+                    result = Err(anyhow!("failed to send sACN to {destination}: {err}").into());
+                }
+            }
+        }
+        result
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for SacnDmxPort {
+    /// sACN ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.socket.is_some() {
+            return Ok(());
+        }
+        if !UNIVERSES.contains(&self.universe) {
+            return Err(anyhow!("sACN universe {} is out of range", self.universe).into());
+        }
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .map_err(|err| anyhow!("failed to bind sACN socket: {err}"))?;
+        self.socket = Some(socket);
+        Ok(())
+    }
+
+    /// Tell receivers the stream has ended, so they stop waiting for it.
+    fn close(&mut self) {
+        for _ in 0..TERMINATION_PACKETS {
+            if self.send(STREAM_TERMINATED, 0, &[]).is_err() {
+                break;
+            }
+        }
+        self.socket = None;
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.send(0, 0, frame)
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.send(0, start_code, data)
+    }
+}
+
+impl fmt::Display for SacnDmxPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sACN universe {}", self.universe)?;
+        if !self.destinations.is_empty() {
+            let destinations: Vec<_> = self.destinations.iter().map(Ipv4Addr::to_string).collect();
+            write!(f, " to {}", destinations.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// How an sACN input port delivers frames when a universe has more than one source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SacnMergeMode {
+    /// Deliver the highest-takes-precedence merge of every source at the
+    /// highest priority currently sending to the universe.
+    #[default]
+    Merged,
+    /// Deliver each source's frames as they arrive, identified by the source's CID.
+    PerSource,
+}
+
+/// A source sending to a universe.
+#[derive(Debug, Clone)]
+pub struct SacnSource {
+    /// The source's component identifier.
+    pub cid: [u8; 16],
+    /// The source's user-assigned name.
+    pub name: String,
+    /// The priority of the source's most recent packet.
+    pub priority: u8,
+    /// Packets that were discarded because they arrived out of order.
+    pub out_of_order: usize,
+    /// Packets that never arrived, judging by gaps in the sequence numbers.
+    pub missed: usize,
+    sequence: u8,
+    last_seen: Instant,
+    levels: Vec<u8>,
+}
+
+/// Receive one or more sACN universes.
+///
+/// Sources are tracked separately for each universe by their CID. A source
+/// that stops sending, or that terminates its stream, no longer contributes
+/// to the merge. Preview data and alternate start codes are ignored.
+///
+/// Up to max_sources sources are tracked on each universe. Packets from any
+/// further source are discarded, and the universe is reported by
+/// `sources_exceeded` until a tracked source goes away.
+///
+/// The port manages the multicast group memberships of its universes. A group
+/// that can't be joined, such as while the network interface is down, is
+/// reported and left out; call `rejoin` once the network is back.
+#[derive(Serialize, Deserialize)]
+pub struct SacnInputPort {
+    universes: Vec<u16>,
+    #[serde(default)]
+    mode: SacnMergeMode,
+    #[serde(default = "default_udp_port")]
+    udp_port: u16,
+    /// The address of the local interface to join groups on, if not left to the OS.
+    #[serde(default)]
+    interface: Option<Ipv4Addr>,
+    #[serde(default = "default_max_sources")]
+    max_sources: usize,
+    #[serde(skip)]
+    socket: Option<UdpSocket>,
+    #[serde(skip)]
+    sources: BTreeMap<u16, Vec<SacnSource>>,
+    #[serde(skip)]
+    exceeded: BTreeSet<u16>,
+    #[serde(skip)]
+    joined: BTreeSet<u16>,
+}
+
+impl SacnInputPort {
+    /// Create a port that receives the given universes.
+    /// The port doesn't join their multicast groups until opened.
+    pub fn new(universes: Vec<u16>, mode: SacnMergeMode) -> Self {
+        Self {
+            universes,
+            mode,
+            udp_port: SACN_PORT,
+            interface: None,
+            max_sources: DEFAULT_MAX_SOURCES,
+            socket: None,
+            sources: BTreeMap::new(),
+            exceeded: BTreeSet::new(),
+            joined: BTreeSet::new(),
+        }
+    }
+
+    /// Return the universes whose multicast groups are currently joined.
+    pub fn joined_universes(&self) -> impl Iterator<Item = u16> + '_ {
+        self.joined.iter().copied()
+    }
+
+    /// Start receiving a universe, joining its group if the port is open.
+    pub fn add_universe(&mut self, universe: u16) {
+        if !self.universes.contains(&universe) {
+            self.universes.push(universe);
+        }
+        self.join_missing();
+    }
+
+    /// Listen on udp_port instead of the standard sACN port.
+    pub fn with_udp_port(mut self, udp_port: u16) -> Self {
+        self.udp_port = udp_port;
+        self
+    }
+
+    /// Join groups on the local interface with address interface, such as to
+    /// receive from a show network when the machine is also on another LAN.
+    pub fn with_interface(mut self, interface: Ipv4Addr) -> Self {
+        self.interface = Some(interface);
+        self
+    }
+
+    /// Track up to max_sources sources on each universe instead of 16.
+    pub fn with_max_sources(mut self, max_sources: usize) -> Self {
+        self.max_sources = max_sources;
+        self
+    }
+
+    fn group_interface(&self) -> Ipv4Addr {
+        self.interface.unwrap_or(Ipv4Addr::UNSPECIFIED)
+    }
+
+    /// Stop receiving a universe, leaving its group if it was joined.
+    pub fn remove_universe(&mut self, universe: u16) {
+        self.universes.retain(|&u| u != universe);
+        self.sources.remove(&universe);
+        self.exceeded.remove(&universe);
+        if !self.joined.remove(&universe) {
+            return;
+        }
+        if let Some(socket) = &self.socket {
+            if let Err(err) =
+                socket.leave_multicast_v4(&multicast_group(universe), &self.group_interface())
+            {
+                warn!("Failed to leave sACN universe {universe}: {err}.");
+            }
+        }
+    }
+
+    /// Leave and rejoin the group of every universe, such as after the
+    /// network interface changed or came back up.
+    pub fn rejoin(&mut self) {
+        if let Some(socket) = &self.socket {
+            for &universe in &self.joined {
+                // The membership may already be gone along with the old interface.
+                let _ =
+                    socket.leave_multicast_v4(&multicast_group(universe), &self.group_interface());
+            }
+        }
+        self.joined.clear();
+        self.join_missing();
+    }
+
+    /// Join the group of every universe that isn't joined yet, if the port is open.
+    fn join_missing(&mut self) {
+        let interface = self.group_interface();
+        let Some(socket) = &self.socket else {
+            return;
+        };
+        for &universe in &self.universes {
+            if self.joined.contains(&universe) {
+                continue;
+            }
+            match socket.join_multicast_v4(&multicast_group(universe), &interface) {
+                Ok(()) => {
+                    self.joined.insert(universe);
+                }
+                Err(err) => warn!("Failed to join sACN universe {universe}: {err}."),
+            }
+        }
+    }
+
+    /// Return the sources currently sending to a universe.
+    pub fn sources(&self, universe: u16) -> &[SacnSource] {
+        self.sources
+            .get(&universe)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Return the source that owns a universe: the one at the highest
+    /// priority, or of several at that priority, the one that has been sending
+    /// longest. In `Merged` mode the others at that priority are merged in too.
+    pub fn owner(&self, universe: u16) -> Option<&SacnSource> {
+        let sources = self.sources(universe);
+        let priority = sources.iter().map(|s| s.priority).max()?;
+        sources.iter().find(|s| s.priority == priority)
+    }
+
+    /// Return true if a universe has more sources than the port tracks, so
+    /// that some are being ignored.
+    pub fn sources_exceeded(&self, universe: u16) -> bool {
+        self.exceeded.contains(&universe)
+    }
+
+    /// Update the universe's sources with a packet, and return the frame to
+    /// deliver, if any.
+    fn receive(&mut self, packet: DataPacket, now: Instant) -> Option<InputFrame> {
+        let universe = packet.universe;
+        let sources = self.sources.entry(universe).or_default();
+        sources.retain(|source| now - source.last_seen < SOURCE_TIMEOUT);
+        if sources.len() < self.max_sources {
+            self.exceeded.remove(&universe);
+        }
+        let index = match sources.iter().position(|s| s.cid == packet.cid) {
+            Some(index) => {
+                let source = &mut sources[index];
+                let delta = packet.sequence.wrapping_sub(source.sequence) as i8;
+                if delta <= 0 && delta > -SEQUENCE_WINDOW {
+                    source.out_of_order += 1;
+                    return None;
+                }
+                if delta > 1 {
+                    source.missed += delta as usize - 1;
+                }
+                index
+            }
+            None if sources.len() >= self.max_sources => {
+                if self.exceeded.insert(universe) {
+                    warn!(
+                        "sACN universe {universe} has more than {} sources; ignoring {}.",
+                        self.max_sources,
+                        format_cid(&packet.cid)
+                    );
+                }
+                return None;
+            }
+            None => {
+                sources.push(SacnSource {
+                    cid: packet.cid,
+                    name: String::new(),
+                    priority: 0,
+                    out_of_order: 0,
+                    missed: 0,
+                    sequence: 0,
+                    last_seen: now,
+                    levels: Vec::new(),
+                });
+                sources.len() - 1
+            }
+        };
+        if packet.options & STREAM_TERMINATED != 0 {
+            sources.remove(index);
+            return match self.mode {
+                SacnMergeMode::Merged if !sources.is_empty() => Some(InputFrame {
+                    universe,
+                    source: None,
+                    levels: merge(sources),
+                }),
+                _ => None,
+            };
+        }
+        let source = &mut sources[index];
+        source.name = packet.source_name;
+        source.priority = packet.priority;
+        source.sequence = packet.sequence;
+        source.last_seen = now;
+        source.levels.clear();
+        source.levels.extend_from_slice(packet.levels);
+        Some(match self.mode {
+            SacnMergeMode::PerSource => InputFrame {
+                universe,
+                source: Some(format_cid(&source.cid)),
+                levels: source.levels.clone(),
+            },
+            SacnMergeMode::Merged => InputFrame {
+                universe,
+                source: None,
+                levels: merge(sources),
+            },
+        })
+    }
+}
+
+/// Merge the levels of every source at the highest priority, highest level
+/// taking precedence.
+fn merge(sources: &[SacnSource]) -> Vec<u8> {
+    let priority = sources.iter().map(|s| s.priority).max().unwrap_or_default();
+    let mut levels = Vec::new();
+    for source in sources.iter().filter(|s| s.priority == priority) {
+        if levels.len() < source.levels.len() {
+            levels.resize(source.levels.len(), 0);
+        }
+        for (merged, &level) in levels.iter_mut().zip(&source.levels) {
+            *merged = (*merged).max(level);
+        }
+    }
+    levels
+}
+
+#[typetag::serde]
+impl DmxInputPort for SacnInputPort {
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.socket.is_some() {
+            return Ok(());
+        }
+        let udp_port = self.udp_port;
+        let socket = bind_reusable(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, udp_port))
+            .map_err(|err| anyhow!("failed to bind sACN port {udp_port}: {err}"))?;
+        self.socket = Some(socket);
+        self.join_missing();
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.socket = None;
+        self.sources.clear();
+        self.exceeded.clear();
+        self.joined.clear();
+    }
+
+    fn read(&mut self, timeout: Duration) -> Result<Option<InputFrame>, ReadError> {
+        let deadline = Instant::now() + timeout;
+        let mut buf = [0; MAX_DATA_PACKET_SIZE];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            let socket = self.socket.as_ref().ok_or(ReadError::Disconnected)?;
+            socket
+                .set_read_timeout(Some(deadline - now))
+                .map_err(anyhow::Error::from)?;
+            let len = match socket.recv(&mut buf) {
+                Ok(len) => len,
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None);
+                }
+                Err(err) => return Err(anyhow::Error::from(err).into()),
+            };
+            let Some(packet) = parse_data_packet(&buf[..len]) else {
+                continue;
+            };
+            if packet.start_code != 0
+                || packet.options & PREVIEW_DATA != 0
+                || !self.universes.contains(&packet.universe)
+            {
+                continue;
+            }
+            if let Some(frame) = self.receive(packet, Instant::now()) {
+                return Ok(Some(frame));
+            }
+        }
+    }
+}
+
+impl fmt::Display for SacnInputPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let universes: Vec<_> = self.universes.iter().map(u16::to_string).collect();
+        write!(f, "sACN input universe {}", universes.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Build a data packet for universe 1 byte by byte, as laid out in E1.31.
+    fn data_packet(cid: u8, priority: u8, sequence: u8, options: u8, levels: &[u8]) -> Vec<u8> {
+        let len = 126 + levels.len();
+        let flags_and_length = |offset: usize| (0x7000 | (len - offset) as u16).to_be_bytes();
+        let mut packet = vec![0; 126];
+        packet[..4].copy_from_slice(&[0x00, 0x10, 0x00, 0x00]);
+        packet[4..16].copy_from_slice(b"ASC-E1.17\0\0\0");
+        packet[16..18].copy_from_slice(&flags_and_length(16));
+        packet[18..22].copy_from_slice(&[0, 0, 0, 0x04]);
+        packet[22..38].copy_from_slice(&[cid; 16]);
+        packet[38..40].copy_from_slice(&flags_and_length(38));
+        packet[40..44].copy_from_slice(&[0, 0, 0, 0x02]);
+        packet[44..48].copy_from_slice(b"test");
+        packet[108] = priority;
+        packet[111] = sequence;
+        packet[112] = options;
+        packet[113..115].copy_from_slice(&[0, 1]);
+        packet[115..117].copy_from_slice(&flags_and_length(115));
+        packet[117] = 0x02;
+        packet[118] = 0xa1;
+        packet[119..123].copy_from_slice(&[0, 0, 0, 1]);
+        packet[123..125].copy_from_slice(&(levels.len() as u16 + 1).to_be_bytes());
+        packet.extend_from_slice(levels);
+        packet
+    }
+
+    fn receive(port: &mut SacnInputPort, packet: &[u8]) -> Option<Vec<u8>> {
+        let packet = parse_data_packet(packet).unwrap();
+        port.receive(packet, Instant::now())
+            .map(|frame| frame.levels)
+    }
+
+    #[test]
+    fn test_parse_data_packet() {
+        let buf = data_packet(7, 100, 3, 0, &[1, 2, 3]);
+        let packet = parse_data_packet(&buf).unwrap();
+        assert_eq!([7; 16], packet.cid);
+        assert_eq!("test", packet.source_name);
+        assert_eq!(
+            (100, 3, 1, 0),
+            (
+                packet.priority,
+                packet.sequence,
+                packet.universe,
+                packet.start_code
+            )
+        );
+        assert_eq!(&[1, 2, 3], packet.levels);
+        assert!(parse_data_packet(&buf[..100]).is_none());
+    }
+
+    #[test]
+    fn test_encode_data_packet() {
+        let packet = DataPacket {
+            cid: [7; 16],
+            source_name: "test".to_string(),
+            priority: 100,
+            sequence: 3,
+            options: PREVIEW_DATA,
+            universe: 1,
+            start_code: 0,
+            levels: &[1, 2, 3],
+        };
+        let mut buf = [0; 200];
+        let len = encode_data_packet(&packet, &mut buf).unwrap();
+        assert_eq!(
+            data_packet(7, 100, 3, PREVIEW_DATA, &[1, 2, 3]),
+            &buf[..len]
+        );
+        assert!(encode_data_packet(&packet, &mut buf[..128]).is_none());
+    }
+
+    #[test]
+    fn test_generates_v4_uuids() {
+        let cid = generate_cid();
+        assert_eq!(
+            Some(uuid::Version::Random),
+            Uuid::from_bytes(cid).get_version()
+        );
+        assert_ne!(cid, generate_cid());
+        assert_eq!(
+            "00010203-0405-0607-0809-0a0b0c0d0e0f",
+            format_cid(&std::array::from_fn(|i| i as u8))
+        );
+    }
+
+    #[test]
+    fn test_merges_highest_priority_sources() {
+        let mut port = SacnInputPort::new(vec![1], SacnMergeMode::Merged);
+        assert_eq!(
+            Some(vec![10, 0]),
+            receive(&mut port, &data_packet(1, 100, 0, 0, &[10, 0]))
+        );
+        assert_eq!(
+            Some(vec![10, 20]),
+            receive(&mut port, &data_packet(2, 100, 0, 0, &[5, 20]))
+        );
+        assert_eq!(
+            Some(vec![1]),
+            receive(&mut port, &data_packet(3, 150, 0, 0, &[1]))
+        );
+        // Terminating the high priority stream hands the universe back.
+        assert_eq!(
+            Some(vec![10, 20]),
+            receive(&mut port, &data_packet(3, 150, 1, STREAM_TERMINATED, &[]))
+        );
+    }
+
+    #[test]
+    fn test_reports_owner() {
+        let mut port = SacnInputPort::new(vec![1], SacnMergeMode::PerSource);
+        let owner = |port: &SacnInputPort| port.owner(1).map(|s| s.cid[0]);
+        assert_eq!(None, owner(&port));
+        receive(&mut port, &data_packet(1, 100, 0, 0, &[1]));
+        receive(&mut port, &data_packet(2, 100, 0, 0, &[2]));
+        assert_eq!(Some(1), owner(&port));
+        receive(&mut port, &data_packet(3, 150, 0, 0, &[3]));
+        assert_eq!(Some(3), owner(&port));
+        receive(&mut port, &data_packet(3, 150, 1, STREAM_TERMINATED, &[]));
+        assert_eq!(Some(1), owner(&port));
+    }
+
+    #[test]
+    fn test_detects_sources_exceeded() {
+        let mut port = SacnInputPort::new(vec![1], SacnMergeMode::PerSource).with_max_sources(2);
+        assert!(receive(&mut port, &data_packet(1, 100, 0, 0, &[1])).is_some());
+        assert!(receive(&mut port, &data_packet(2, 100, 0, 0, &[2])).is_some());
+        assert!(!port.sources_exceeded(1));
+        assert!(receive(&mut port, &data_packet(3, 100, 0, 0, &[3])).is_none());
+        assert!(port.sources_exceeded(1));
+        // Tracked sources keep being heard.
+        assert!(receive(&mut port, &data_packet(1, 100, 1, 0, &[1])).is_some());
+        // Once one goes, there's room again.
+        receive(&mut port, &data_packet(2, 100, 1, STREAM_TERMINATED, &[]));
+        assert!(receive(&mut port, &data_packet(3, 100, 1, 0, &[3])).is_some());
+        assert!(!port.sources_exceeded(1));
+    }
+
+    #[test]
+    fn test_discards_out_of_order_packets() {
+        let mut port = SacnInputPort::new(vec![1], SacnMergeMode::PerSource);
+        assert!(receive(&mut port, &data_packet(1, 100, 10, 0, &[1])).is_some());
+        assert!(receive(&mut port, &data_packet(1, 100, 9, 0, &[2])).is_none());
+        assert!(receive(&mut port, &data_packet(1, 100, 13, 0, &[3])).is_some());
+        let source = &port.sources(1)[0];
+        assert_eq!((1, 2), (source.out_of_order, source.missed));
+    }
+
+    #[test]
+    fn test_manages_group_memberships() -> Result<(), Box<dyn std::error::Error>> {
+        // Join on the loopback interface and use a non-standard port, to stay
+        // off the network and out of the way of other sACN software.
+        let udp_port = SACN_PORT + 2;
+        let mut input = SacnInputPort::new(vec![1, 2], SacnMergeMode::Merged)
+            .with_udp_port(udp_port)
+            .with_interface(Ipv4Addr::LOCALHOST);
+        let joined = |input: &SacnInputPort| input.joined_universes().collect::<Vec<_>>();
+        assert!(joined(&input).is_empty());
+        DmxInputPort::open(&mut input)?;
+        assert_eq!(vec![1, 2], joined(&input));
+
+        input.remove_universe(1);
+        input.add_universe(3);
+        assert_eq!(vec![2, 3], joined(&input));
+        input.rejoin();
+        assert_eq!(vec![2, 3], joined(&input));
+
+        let source = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+        socket2::SockRef::from(&source).set_multicast_if_v4(&Ipv4Addr::LOCALHOST)?;
+        let mut packet = data_packet(7, 100, 1, 0, &[3]);
+        packet[113..115].copy_from_slice(&3u16.to_be_bytes());
+        source.send_to(&packet, (multicast_group(3), udp_port))?;
+        let frame = input.read(Duration::from_secs(1))?.unwrap();
+        assert_eq!((3, vec![3]), (frame.universe, frame.levels));
+
+        DmxInputPort::close(&mut input);
+        assert!(joined(&input).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unicast_to_input() -> Result<(), Box<dyn std::error::Error>> {
+        // Use a non-standard port to stay out of the way of other sACN software.
+        let udp_port = SACN_PORT + 1;
+        let mut input = SacnInputPort::new(vec![1], SacnMergeMode::Merged).with_udp_port(udp_port);
+        DmxInputPort::open(&mut input)?;
+        let mut output = SacnDmxPort::new(1)
+            .with_unicast(vec![Ipv4Addr::LOCALHOST])
+            .with_udp_port(udp_port);
+        DmxPort::open(&mut output)?;
+        output.write(&[1, 2, 3])?;
+        let frame = input.read(Duration::from_secs(1))?.unwrap();
+        assert_eq!(vec![1, 2, 3], frame.levels);
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+//! A port that enforces safety limits on specific channels.
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{system_clock, Clock, DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError};
+
+/// A limit on what may be sent to one channel.
+///
+/// Channels are indexes into the frame, counting from 0, as everywhere a
+/// channel is passed to or returned from this crate's API; DMX channel 1 on a
+/// fixture is channel 0 here. Only text meant for people, such as MQTT topics
+/// and log messages, counts channels from 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SafetyRule {
+    /// Never send the channel, counting from 0, above max.
+    Cap { channel: usize, max: u8 },
+    /// Force the channel to zero unless it has been armed with `SafetyPort::arm`.
+    RequireArmed { channel: usize },
+    /// Force the channel to zero unless `SafetyPort::feed_deadman` has been
+    /// called within the timeout.
+    Deadman { channel: usize, timeout: Duration },
+}
+
+/// Enforce safety rules on every frame before it is written to the inner port.
+///
+/// Use this when channels drive pyrotechnics, lasers, motion, or anything else
+/// where a stray full-level write is dangerous. The rules are serialized with
+/// the port, but the armed channels and the deadman are not: a port always
+/// starts out disarmed, including after it is reopened.
+///
+/// Wrap the output port directly, inside any curve, master, or transform, so
+/// the rules apply to the levels that actually go out.
+#[derive(Serialize, Deserialize)]
+pub struct SafetyPort {
+    inner: Box<dyn DmxPort>,
+    rules: Vec<SafetyRule>,
+    #[serde(skip)]
+    armed: HashSet<usize>,
+    #[serde(skip)]
+    last_fed: Option<Instant>,
+    #[serde(skip)]
+    buffer: Vec<u8>,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+}
+
+impl SafetyPort {
+    /// Wrap inner, enforcing rules on every frame.
+    pub fn new(inner: Box<dyn DmxPort>, rules: Vec<SafetyRule>) -> Self {
+        Self {
+            inner,
+            rules,
+            armed: HashSet::new(),
+            last_fed: None,
+            buffer: Vec::new(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Time the deadman using clock instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Allow a channel, counting from 0, with a RequireArmed rule to be sent.
+    pub fn arm(&mut self, channel: usize) {
+        self.armed.insert(channel);
+    }
+
+    /// Force a channel, counting from 0, with a RequireArmed rule back to zero.
+    pub fn disarm(&mut self, channel: usize) {
+        self.armed.remove(&channel);
+    }
+
+    /// Force every channel with a RequireArmed rule back to zero.
+    pub fn disarm_all(&mut self) {
+        self.armed.clear();
+    }
+
+    /// Keep channels with a Deadman rule live for their timeouts.
+    pub fn feed_deadman(&mut self) {
+        self.last_fed = Some(self.clock.now());
+    }
+
+    /// Unwrap this port into the inner port.
+    pub fn into_inner(self) -> Box<dyn DmxPort> {
+        self.inner
+    }
+
+    fn apply_rules(&self, frame: &mut [u8], now: Instant) {
+        for rule in &self.rules {
+            let (channel, limit) = match *rule {
+                SafetyRule::Cap { channel, max } => (channel, max),
+                SafetyRule::RequireArmed { channel } if !self.armed.contains(&channel) => {
+                    (channel, 0)
+                }
+                SafetyRule::Deadman { channel, timeout }
+                    if self.last_fed.is_none_or(|fed| now - fed >= timeout) =>
+                {
+                    (channel, 0)
+                }
+                _ => continue,
+            };
+            if let Some(level) = frame.get_mut(channel) {
+                *level = (*level).min(limit);
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for SafetyPort {
+    /// Safety ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.disarm_all();
+        self.last_fed = None;
+        self.inner.open()
+    }
+
+    fn close(&mut self) {
+        self.disarm_all();
+        self.last_fed = None;
+        self.inner.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        // Reuse the buffer to avoid allocating on every frame.
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        buffer.extend_from_slice(frame);
+        self.apply_rules(&mut buffer, self.clock.now());
+        let result = self.inner.write(&buffer);
+        self.buffer = buffer;
+        result
+    }
+
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_blackout(frame)
+    }
+
+    /// Alternate start code packets aren't levels, so the rules don't apply.
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.inner.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.inner.max_fps()
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.migrate(from_version);
+    }
+}
+
+impl fmt::Display for SafetyPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (with {} safety rules)", self.inner, self.rules.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ManualClock, OfflineDmxPort};
+
+    #[test]
+    fn test_apply_rules() {
+        let clock = Arc::new(ManualClock::new());
+        let mut port = SafetyPort::new(
+            Box::new(OfflineDmxPort),
+            vec![
+                SafetyRule::Cap {
+                    channel: 0,
+                    max: 100,
+                },
+                SafetyRule::RequireArmed { channel: 1 },
+                SafetyRule::Deadman {
+                    channel: 2,
+                    timeout: Duration::from_secs(1),
+                },
+            ],
+        )
+        .with_clock(clock.clone());
+        let mut frame = [255; 4];
+        port.apply_rules(&mut frame, clock.now());
+        assert_eq!([100, 0, 0, 255], frame);
+
+        port.arm(1);
+        port.feed_deadman();
+        let mut frame = [255; 4];
+        port.apply_rules(&mut frame, clock.now());
+        assert_eq!([100, 255, 255, 255], frame);
+
+        clock.advance(Duration::from_secs(2));
+        let mut frame = [255; 4];
+        port.apply_rules(&mut frame, clock.now());
+        assert_eq!([100, 255, 0, 255], frame);
+    }
+}
@@ -0,0 +1,833 @@
+//! Drive a port from a background thread at a fixed refresh rate.
+use log::{debug, warn};
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{system_clock, Clock, DmxPort, Frame, Wakeup, WriteError};
+
+/// Never reduce the refresh rate below this many frames per second.
+const MIN_FPS: f64 = 1.0;
+
+/// A write must fit within this fraction of the frame interval to be sustainable.
+const HEADROOM: f64 = 0.9;
+
+/// Weight given to each new write latency sample in the moving average.
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Number of writes to observe before adapting the rate, so a slow first write
+/// after opening the port doesn't trigger a reduction.
+const MIN_LATENCY_SAMPLES: usize = 10;
+
+/// Weight given to the newest write interval in the output rate estimate.
+const FPS_SMOOTHING: f64 = 0.1;
+
+/// Every sender in the process, so they can all be blacked out at once.
+static SENDERS: Mutex<Vec<Weak<Shared>>> = Mutex::new(Vec::new());
+
+/// True while the process-wide blackout is latched.
+static BLACKOUT: AtomicBool = AtomicBool::new(false);
+
+/// Configuration for a background sender.
+#[derive(Debug, Clone)]
+pub struct SenderConfig {
+    /// Target output rate in frames per second.
+    pub fps: f64,
+    /// If true, lower the target rate when the port can't keep up with it.
+    pub adaptive: bool,
+    /// How to handle frames that arrive faster than they can be written.
+    pub policy: QueuePolicy,
+}
+
+/// The rate a sender targets unless configured otherwise.
+const DEFAULT_FPS: f64 = 40.0;
+
+impl SenderConfig {
+    /// Return the default configuration, at a rate port can sustain: the
+    /// default rate, or the port's `max_fps` if that is lower.
+    pub fn for_port(port: &dyn DmxPort) -> Self {
+        Self::default().limited_to_port(port)
+    }
+
+    /// Return this configuration with its rate lowered to port's `max_fps`,
+    /// if that is lower.
+    pub fn limited_to_port(self, port: &dyn DmxPort) -> Self {
+        Self {
+            fps: port
+                .max_fps()
+                .map_or(self.fps, |max_fps| self.fps.min(max_fps.max(MIN_FPS))),
+            ..self
+        }
+    }
+}
+
+impl Default for SenderConfig {
+    fn default() -> Self {
+        Self {
+            fps: DEFAULT_FPS,
+            adaptive: true,
+            policy: QueuePolicy::SendAll,
+        }
+    }
+}
+
+/// How a sender handles frames that arrive faster than the port is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Queue every frame and write them all in order.
+    SendAll,
+    /// Drop intermediate frames; only the newest pending frame is written.
+    /// Dropped frames are counted in the sender metrics.
+    LatestOnly,
+    /// Queue up to depth frames; sending to a full queue waits for room.
+    Block { depth: usize },
+    /// Queue up to depth frames; sending to a full queue drops the oldest
+    /// queued frame to make room.
+    DropOldest { depth: usize },
+    /// Queue up to depth frames; frames sent to a full queue are dropped.
+    DropNewest { depth: usize },
+}
+
+/// Counters describing a sender's output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SenderMetrics {
+    /// Number of frames written to the port, including refreshes of an unchanged frame.
+    pub frames_written: u64,
+    /// Number of frames that were never written because a newer frame replaced them.
+    pub frames_skipped: u64,
+    /// Number of writes that returned an error.
+    pub write_errors: u64,
+    /// Number of frames sent while a bounded queue was full. Depending on the
+    /// policy, a frame was dropped or the caller waited for room.
+    pub queue_overflows: u64,
+}
+
+/// Write frames to a port from a dedicated thread at a steady refresh rate.
+///
+/// Frames passed to `send` are queued and written one per frame interval. When
+/// the queue is empty, the most recent frame is re-sent, keeping the DMX line
+/// refreshed even if the application only sends on change.
+///
+/// The sender measures how long each write actually takes. If the port can't
+/// sustain the target rate, the rate is lowered to one it can, rather than
+/// falling behind and bursting to catch up. The rate is also kept within the
+/// port's `max_fps`, which is checked after every write, so changing the
+/// port's own output rate takes effect without restarting the sender.
+pub struct BackgroundSender {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<Box<dyn DmxPort>>>,
+}
+
+struct Shared {
+    /// The Display form of the port, for logging.
+    port: String,
+    state: Mutex<State>,
+    /// Woken when there is something new for the output thread to act on.
+    wakeup: Wakeup,
+    /// Notified when frames are taken off the queue.
+    space: Condvar,
+    /// Notified when a different frame is written.
+    written: Condvar,
+}
+
+struct State {
+    queue: VecDeque<Vec<u8>>,
+    policy: QueuePolicy,
+    /// The rate the application asked for.
+    requested_fps: f64,
+    /// The rate frames are scheduled at, which the port's limit or its
+    /// measured latency may hold below the requested rate.
+    fps: f64,
+    /// The fastest rate the port can output at, if it is limited.
+    max_fps: Option<f64>,
+    metrics: SenderMetrics,
+    /// When the most recent write started.
+    last_write: Option<Instant>,
+    /// Smoothed interval between writes, in seconds.
+    write_interval: Option<f64>,
+    /// The most recently written frame that the port accepted, and how many
+    /// times it has changed.
+    written: Frame,
+    written_version: u64,
+    /// If true, write zeros instead of the application's frames.
+    blackout: bool,
+    stop: bool,
+    /// The output thread has exited, such as after writing to the port
+    /// panicked, so nothing will take frames off the queue.
+    exited: bool,
+}
+
+impl BackgroundSender {
+    /// Start sending to port on a new thread.
+    /// The port should already be open.
+    pub fn spawn(port: Box<dyn DmxPort>, config: SenderConfig) -> Self {
+        Self::spawn_with_clock(port, config, system_clock())
+    }
+
+    /// Start sending to port on a new thread, scheduling frames using clock.
+    pub fn spawn_with_clock(
+        port: Box<dyn DmxPort>,
+        config: SenderConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let max_fps = port.max_fps();
+        let port_name = port.to_string();
+        let fps = limit_fps(config.fps, max_fps, &port_name);
+        let shared = Arc::new(Shared {
+            port: port_name,
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                policy: config.policy,
+                requested_fps: config.fps,
+                fps,
+                max_fps,
+                metrics: SenderMetrics::default(),
+                last_write: None,
+                write_interval: None,
+                written: Frame::default(),
+                written_version: 0,
+                blackout: BLACKOUT.load(Ordering::SeqCst),
+                stop: false,
+                exited: false,
+            }),
+            wakeup: Wakeup::new(),
+            space: Condvar::new(),
+            written: Condvar::new(),
+        });
+        {
+            let mut senders = SENDERS.lock().unwrap();
+            senders.retain(|sender| sender.strong_count() > 0);
+            senders.push(Arc::downgrade(&shared));
+        }
+        let thread_shared = shared.clone();
+        let thread = thread::spawn(move || {
+            let _exited = Exited(&thread_shared);
+            run(port, config.adaptive, &thread_shared, &*clock)
+        });
+        Self {
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    /// Queue a frame for output, handling a full queue according to the policy.
+    /// Return an error if the output thread has died, such as because writing
+    /// to the port panicked, rather than queueing frames nothing will write.
+    pub fn send(&self, frame: &[u8]) -> Result<(), WriteError> {
+        let mut state = self.shared.lock();
+        if state.exited {
+            return Err(WriteError::Disconnected);
+        }
+        match state.policy {
+            QueuePolicy::SendAll => (),
+            QueuePolicy::LatestOnly => {
+                state.metrics.frames_skipped += state.queue.len() as u64;
+                state.queue.clear();
+            }
+            QueuePolicy::Block { depth } => {
+                if state.queue.len() >= depth.max(1) {
+                    state.metrics.queue_overflows += 1;
+                }
+                while state.queue.len() >= depth.max(1) && !state.exited {
+                    state = self.shared.space.wait(state).unwrap();
+                }
+                if state.exited {
+                    return Err(WriteError::Disconnected);
+                }
+            }
+            QueuePolicy::DropOldest { depth } => {
+                if state.queue.len() >= depth.max(1) {
+                    state.metrics.queue_overflows += 1;
+                }
+                while state.queue.len() >= depth.max(1) {
+                    state.queue.pop_front();
+                }
+            }
+            QueuePolicy::DropNewest { depth } => {
+                if state.queue.len() >= depth.max(1) {
+                    state.metrics.queue_overflows += 1;
+                    return Ok(());
+                }
+            }
+        }
+        state.queue.push_back(frame.to_vec());
+        drop(state);
+        self.shared.wakeup.wake();
+        Ok(())
+    }
+
+    /// Return a snapshot of the output counters.
+    pub fn metrics(&self) -> SenderMetrics {
+        self.shared.lock().metrics
+    }
+
+    /// Observe the frames this sender writes to its port, without being in the
+    /// write path.
+    pub fn subscribe(&self) -> FrameWatch {
+        FrameWatch {
+            shared: self.shared.clone(),
+            seen: 0,
+        }
+    }
+
+    /// Return the current target rate in frames per second.
+    /// This may be lower than the configured rate if the port couldn't sustain it.
+    pub fn fps(&self) -> f64 {
+        self.shared.lock().fps
+    }
+
+    /// Return the smoothed rate frames are actually being written at, or 0
+    /// before two frames have been written.
+    pub fn output_fps(&self) -> f64 {
+        match self.shared.lock().write_interval {
+            Some(interval) if interval > 0.0 => 1.0 / interval,
+            _ => 0.0,
+        }
+    }
+
+    /// Set a new target rate in frames per second.
+    /// The rate is capped at the fastest the port can output at.
+    pub fn set_fps(&self, fps: f64) {
+        let mut state = self.shared.lock();
+        state.requested_fps = fps;
+        state.fps = limit_fps(fps, state.max_fps, &self.shared.port);
+        drop(state);
+        self.shared.wakeup.wake();
+    }
+
+    /// Stop the output thread and return the port. If writing to the port
+    /// panicked, the port is lost, and the panic's payload is returned instead
+    /// so the caller can log it or pass it on with `std::panic::resume_unwind`.
+    pub fn stop(mut self) -> thread::Result<Box<dyn DmxPort>> {
+        self.join().expect("sender thread is only joined once")
+    }
+
+    fn join(&mut self) -> Option<thread::Result<Box<dyn DmxPort>>> {
+        let thread = self.thread.take()?;
+        self.shared.lock().stop = true;
+        self.shared.wakeup.wake();
+        Some(thread.join())
+    }
+}
+
+/// The port and the rate it is being written at, such as
+/// "Enttec DMX USB PRO EN123456 - 39.7 fps".
+impl fmt::Display for BackgroundSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {:.1} fps", self.shared.port, self.output_fps())
+    }
+}
+
+/// Dropping a sender stops its output thread. A panic on that thread is
+/// logged rather than raised again, since the drop may itself be part of
+/// unwinding from another panic; use `stop` to receive it.
+impl Drop for BackgroundSender {
+    fn drop(&mut self) {
+        if let Some(Err(err)) = self.join() {
+            warn!(
+                "The output thread for {} panicked: {}.",
+                self.shared.port,
+                panic_message(&*err)
+            );
+        }
+    }
+}
+
+/// Return the message a panic was raised with, if it has one.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("no message")
+}
+
+/// Watch the frames a background sender writes, such as for a visualizer.
+///
+/// Like a watch channel, only the most recent frame is kept; an observer that
+/// falls behind skips straight to it. Refreshes of an unchanged frame are not
+/// reported as changes, and neither are frames the port failed to write.
+#[derive(Clone)]
+pub struct FrameWatch {
+    shared: Arc<Shared>,
+    seen: u64,
+}
+
+impl FrameWatch {
+    /// Return the most recently written frame, which is empty before the
+    /// first write.
+    pub fn latest(&mut self) -> Frame {
+        let state = self.shared.lock();
+        self.seen = state.written_version;
+        state.written.clone()
+    }
+
+    /// Wait up to timeout for a frame other than the one last returned to be
+    /// written, and return it. Return None if none was written in time.
+    pub fn changed(&mut self, timeout: Duration) -> Option<Frame> {
+        let state = self.shared.lock();
+        let (state, _) = self
+            .shared
+            .written
+            .wait_timeout_while(state, timeout, |state| state.written_version == self.seen)
+            .unwrap();
+        if state.written_version == self.seen {
+            return None;
+        }
+        self.seen = state.written_version;
+        Some(state.written.clone())
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Marks the output thread as exited when dropped, even if it panicked, so
+/// senders waiting for room in the queue don't wait forever.
+struct Exited<'a>(&'a Shared);
+
+impl Drop for Exited<'_> {
+    fn drop(&mut self) {
+        self.0.lock().exited = true;
+        self.0.space.notify_all();
+    }
+}
+
+/// Clamp a requested rate to the range a port can output at, warning if the
+/// request is faster than the port's limit.
+fn limit_fps(fps: f64, max_fps: Option<f64>, port: &str) -> f64 {
+    match max_fps {
+        Some(max_fps) if fps > max_fps => {
+            warn!(
+                "{} can output at most {:.1} fps; requested {:.1} fps.",
+                port, max_fps, fps
+            );
+            max_fps.max(MIN_FPS)
+        }
+        _ => fps.max(MIN_FPS),
+    }
+}
+
+/// Latch every sender in the process into writing zeros, or release them.
+pub(crate) fn set_blackout(blackout: bool) {
+    BLACKOUT.store(blackout, Ordering::SeqCst);
+    for shared in SENDERS.lock().unwrap().iter().filter_map(Weak::upgrade) {
+        shared.lock().blackout = blackout;
+        shared.wakeup.wake();
+    }
+}
+
+/// Run the output loop until asked to stop.
+fn run(
+    mut port: Box<dyn DmxPort>,
+    adaptive: bool,
+    shared: &Shared,
+    clock: &dyn Clock,
+) -> Box<dyn DmxPort> {
+    let mut frame = None;
+    let mut latency = LatencyEstimate::default();
+    let mut deadline = clock.now();
+    let zeros = vec![0; port.frame_size_limits().max];
+    let mut blacked_out = false;
+    loop {
+        let blackout = {
+            let mut state = shared.lock();
+            // Sleep until the next frame is due, waking early if asked to stop
+            // and acting on a blackout or its release immediately.
+            loop {
+                if state.stop {
+                    return port;
+                }
+                if state.blackout != blacked_out {
+                    break;
+                }
+                let now = clock.now();
+                let pending = frame.is_some() || !state.queue.is_empty() || state.blackout;
+                if !pending {
+                    // Nothing to send yet, so the schedule starts from
+                    // whenever the first frame arrives.
+                    deadline = now;
+                } else if now >= deadline {
+                    break;
+                }
+                drop(state);
+                clock.wait_until(pending.then_some(deadline), &shared.wakeup);
+                state = shared.lock();
+            }
+            let blackout = state.blackout;
+            if blackout {
+                // Hold on to the newest frame for when the blackout is released.
+                let skipped = state.queue.len().saturating_sub(1);
+                state.metrics.frames_skipped += skipped as u64;
+                if let Some(next) = state.queue.pop_back() {
+                    frame = Some(next);
+                }
+                state.queue.clear();
+            } else if let Some(next) = state.queue.pop_front() {
+                frame = Some(next);
+            }
+            blackout
+        };
+        shared.space.notify_all();
+        blacked_out = blackout;
+        let frame = match &frame {
+            _ if blackout => &zeros,
+            Some(frame) => frame,
+            // Nothing to send until the first frame arrives.
+            None => continue,
+        };
+
+        let start = clock.now();
+        let result = if blackout {
+            port.write_blackout(frame)
+        } else {
+            port.write(frame)
+        };
+        let elapsed = clock.now() - start;
+        latency.update(elapsed);
+
+        let mut state = shared.lock();
+        state.metrics.frames_written += 1;
+        if let Some(last_write) = state.last_write {
+            let interval = (start - last_write).as_secs_f64();
+            state.write_interval = Some(match state.write_interval {
+                Some(smoothed) => smoothed + FPS_SMOOTHING * (interval - smoothed),
+                None => interval,
+            });
+        }
+        state.last_write = Some(start);
+        // Observers see what reached the port, so a failed write isn't
+        // reported.
+        if result.is_ok() && state.written[..] != frame[..] {
+            state.written = frame[..].into();
+            state.written_version += 1;
+            shared.written.notify_all();
+        }
+        if let Err(err) = result {
+            state.metrics.write_errors += 1;
+            debug!("Background write to {} failed: {}.", port, err);
+        }
+        let max_fps = port.max_fps();
+        if max_fps != state.max_fps {
+            // The port's own output rate changed, such as through its setters.
+            state.max_fps = max_fps;
+            state.fps = limit_fps(state.requested_fps, max_fps, &shared.port);
+        }
+        if adaptive {
+            if let Some(sustainable) = latency.sustainable_fps() {
+                if sustainable < state.fps {
+                    warn!(
+                        "{} can't sustain {:.1} fps (writes take {:.1} ms); reducing to {:.1} fps.",
+                        port,
+                        state.fps,
+                        latency.mean.as_secs_f64() * 1000.0,
+                        sustainable
+                    );
+                    state.fps = sustainable;
+                }
+            }
+        }
+        let interval = Duration::from_secs_f64(1.0 / state.fps);
+        drop(state);
+
+        // Schedule from the previous deadline to keep a steady rate, but never
+        // try to catch up on missed frames by bursting.
+        deadline = (deadline + interval).max(start + elapsed);
+    }
+}
+
+/// An exponentially weighted moving average of write latency.
+#[derive(Default)]
+struct LatencyEstimate {
+    mean: Duration,
+    samples: usize,
+}
+
+impl LatencyEstimate {
+    fn update(&mut self, sample: Duration) {
+        self.mean = if self.samples == 0 {
+            sample
+        } else {
+            self.mean.mul_f64(1.0 - LATENCY_SMOOTHING) + sample.mul_f64(LATENCY_SMOOTHING)
+        };
+        self.samples += 1;
+    }
+
+    /// Return the fastest rate that leaves headroom for the measured latency,
+    /// or None if there isn't enough data yet.
+    fn sustainable_fps(&self) -> Option<f64> {
+        if self.samples < MIN_LATENCY_SAMPLES || self.mean.is_zero() {
+            return None;
+        }
+        Some((HEADROOM / self.mean.as_secs_f64()).max(MIN_FPS))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+    use crate::ManualClock;
+
+    /// Spawn a sender for port with policy, scheduled by clock.
+    fn spawn(port: &TestPort, policy: QueuePolicy, clock: &Arc<ManualClock>) -> BackgroundSender {
+        BackgroundSender::spawn_with_clock(
+            Box::new(port.clone()),
+            SenderConfig {
+                policy,
+                ..Default::default()
+            },
+            clock.clone(),
+        )
+    }
+
+    /// Let the sender write its next frame.
+    fn next_frame(clock: &ManualClock) {
+        clock.advance(clock.wait_for_deadline() - clock.now());
+    }
+
+    #[test]
+    fn test_limit_fps() {
+        assert_eq!(100.0, limit_fps(100.0, None, "test"));
+        assert_eq!(30.0, limit_fps(30.0, Some(44.0), "test"));
+        assert_eq!(44.0, limit_fps(100.0, Some(44.0), "test"));
+        assert_eq!(MIN_FPS, limit_fps(0.0, None, "test"));
+        assert_eq!(MIN_FPS, limit_fps(100.0, Some(0.1), "test"));
+    }
+
+    #[test]
+    fn test_default_rate_suits_port() {
+        let port = TestPort::default();
+        assert_eq!(DEFAULT_FPS, SenderConfig::for_port(&port).fps);
+        port.set_max_fps(Some(25.0));
+        assert_eq!(25.0, SenderConfig::for_port(&port).fps);
+        port.set_max_fps(Some(100.0));
+        assert_eq!(DEFAULT_FPS, SenderConfig::for_port(&port).fps);
+    }
+
+    #[test]
+    fn test_limits_configured_rate_to_port() {
+        let port = TestPort::default();
+        let config = SenderConfig {
+            fps: 60.0,
+            adaptive: false,
+            policy: QueuePolicy::LatestOnly,
+        };
+        assert_eq!(60.0, config.clone().limited_to_port(&port).fps);
+        port.set_max_fps(Some(44.0));
+        let limited = config.limited_to_port(&port);
+        assert_eq!(44.0, limited.fps);
+        assert!(!limited.adaptive);
+        assert_eq!(QueuePolicy::LatestOnly, limited.policy);
+    }
+
+    #[test]
+    fn test_reduces_rate_for_slow_port() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        port.set_delay(clock.clone(), Duration::from_millis(20));
+        let sender = BackgroundSender::spawn_with_clock(
+            Box::new(port.clone()),
+            SenderConfig {
+                fps: 200.0,
+                ..Default::default()
+            },
+            clock.clone(),
+        );
+        sender.send(&[0]).unwrap();
+        // Writes back to back until the rate is lowered to one with headroom
+        // for the 20ms writes, then waits between them.
+        assert!(port.wait_for_writes(MIN_LATENCY_SAMPLES, Duration::from_secs(1)));
+        clock.wait_for_deadline();
+        let fps = sender.fps();
+        assert!(
+            (fps - HEADROOM / 0.02).abs() < 1e-6,
+            "unexpected rate {fps}"
+        );
+    }
+
+    #[test]
+    fn test_schedules_with_clock() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        let sender = BackgroundSender::spawn_with_clock(
+            Box::new(port.clone()),
+            SenderConfig {
+                fps: 100.0,
+                ..Default::default()
+            },
+            clock.clone(),
+        );
+        // Nothing is scheduled until the first frame arrives, however long
+        // that takes.
+        clock.advance(Duration::from_secs(1));
+        sender.send(&[0]).unwrap();
+        assert!(port.wait_for_writes(1, Duration::from_secs(1)));
+        assert_eq!(
+            clock.now() + Duration::from_millis(10),
+            clock.wait_for_deadline()
+        );
+        assert_eq!(1, port.writes());
+
+        clock.advance(Duration::from_millis(10));
+        assert!(port.wait_for_writes(2, Duration::from_secs(1)));
+        assert_eq!(
+            clock.now() + Duration::from_millis(10),
+            clock.wait_for_deadline()
+        );
+        assert_eq!(2, port.writes());
+        assert_eq!("test - 100.0 fps", sender.to_string());
+    }
+
+    #[test]
+    fn test_follows_port_rate_limit() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        let sender = BackgroundSender::spawn_with_clock(
+            Box::new(port.clone()),
+            SenderConfig {
+                fps: 100.0,
+                adaptive: false,
+                ..Default::default()
+            },
+            clock.clone(),
+        );
+        // Wait for a write, and return how long until the next is due.
+        let next_interval = |writes| {
+            assert!(port.wait_for_writes(writes, Duration::from_secs(1)));
+            clock.wait_for_deadline() - clock.now()
+        };
+        sender.send(&[0]).unwrap();
+        assert_eq!(Duration::from_millis(10), next_interval(1));
+
+        // Lowering the port's limit after spawning slows the sender down...
+        port.set_max_fps(Some(25.0));
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(Duration::from_millis(40), next_interval(2));
+        assert_eq!(25.0, sender.fps());
+
+        // ...and lifting it restores the requested rate.
+        port.set_max_fps(None);
+        clock.advance(Duration::from_millis(40));
+        assert_eq!(Duration::from_millis(10), next_interval(3));
+        assert_eq!(100.0, sender.fps());
+    }
+
+    #[test]
+    fn test_watch_only_sees_written_frames() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        let sender = spawn(&port, QueuePolicy::SendAll, &clock);
+        let mut watch = sender.subscribe();
+        port.set_broken(true);
+        sender.send(&[1]).unwrap();
+        // The first frame is written as soon as it is sent.
+        clock.wait_for_deadline();
+        assert_eq!(1, sender.metrics().write_errors);
+        assert!(watch.latest().is_empty());
+
+        port.set_broken(false);
+        next_frame(&clock);
+        let frame = watch.changed(Duration::from_secs(1)).unwrap();
+        assert_eq!([1], frame[..]);
+    }
+
+    #[test]
+    fn test_latest_only_skips_intermediate_frames() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        let sender = spawn(&port, QueuePolicy::LatestOnly, &clock);
+        // Let the first write go out, then pile up frames behind it.
+        sender.send(&[0]).unwrap();
+        assert!(port.wait_for_writes(1, Duration::from_secs(1)));
+        for val in 1..5 {
+            sender.send(&[val]).unwrap();
+        }
+        assert_eq!(3, sender.metrics().frames_skipped);
+        next_frame(&clock);
+        assert!(port.wait_for_writes(2, Duration::from_secs(1)));
+        assert_eq!(vec![vec![0], vec![4]], port.frames());
+    }
+
+    #[test]
+    fn test_drop_newest_counts_overflows() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        let sender = spawn(&port, QueuePolicy::DropNewest { depth: 2 }, &clock);
+        sender.send(&[0]).unwrap();
+        assert!(port.wait_for_writes(1, Duration::from_secs(1)));
+        for val in 1..5 {
+            sender.send(&[val]).unwrap();
+        }
+        assert_eq!(2, sender.metrics().queue_overflows);
+        for writes in 2..4 {
+            next_frame(&clock);
+            assert!(port.wait_for_writes(writes, Duration::from_secs(1)));
+        }
+        assert_eq!(vec![vec![0], vec![1], vec![2]], port.frames());
+    }
+
+    #[test]
+    fn test_drop_oldest_keeps_newest_frames() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        let sender = spawn(&port, QueuePolicy::DropOldest { depth: 2 }, &clock);
+        sender.send(&[0]).unwrap();
+        assert!(port.wait_for_writes(1, Duration::from_secs(1)));
+        for val in 1..5 {
+            sender.send(&[val]).unwrap();
+        }
+        assert_eq!(2, sender.metrics().queue_overflows);
+        for writes in 2..4 {
+            next_frame(&clock);
+            assert!(port.wait_for_writes(writes, Duration::from_secs(1)));
+        }
+        assert_eq!(vec![vec![0], vec![3], vec![4]], port.frames());
+    }
+
+    #[test]
+    fn test_block_waits_for_room() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        let sender = spawn(&port, QueuePolicy::Block { depth: 1 }, &clock);
+        sender.send(&[0]).unwrap();
+        assert!(port.wait_for_writes(1, Duration::from_secs(1)));
+        sender.send(&[1]).unwrap();
+        thread::scope(|scope| {
+            let blocked = scope.spawn(|| sender.send(&[2]));
+            while sender.metrics().queue_overflows == 0 {
+                thread::yield_now();
+            }
+            assert!(!blocked.is_finished());
+            // Writing the queued frame makes room for the blocked one.
+            next_frame(&clock);
+            blocked.join().unwrap().unwrap();
+        });
+        next_frame(&clock);
+        assert!(port.wait_for_writes(3, Duration::from_secs(1)));
+        assert_eq!(vec![vec![0], vec![1], vec![2]], port.frames());
+    }
+
+    #[test]
+    fn test_block_fails_once_sender_died() {
+        let clock = Arc::new(ManualClock::new());
+        let port = TestPort::default();
+        port.set_panics(true);
+        let sender = spawn(&port, QueuePolicy::Block { depth: 1 }, &clock);
+        // Whether or not the first frame is queued before the port panics,
+        // the second finds the queue full with nothing left to empty it.
+        sender.send(&[0]).unwrap();
+        let result = sender.send(&[1]).and_then(|()| sender.send(&[2]));
+        assert!(matches!(result, Err(WriteError::Disconnected)));
+        // Stopping the sender hands over the port's panic instead of the port.
+        let err = sender.stop().err().expect("the port panicked");
+        assert_eq!("test panicked on purpose", panic_message(&*err));
+    }
+}
@@ -0,0 +1,158 @@
+//! A port that blacks out its universe when it is closed or dropped.
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError, DMX_UNIVERSE_SIZE};
+
+/// Write an all-zeros universe to the inner port before it closes, so lights
+/// don't freeze at their last levels when the application exits.
+///
+/// The zeros are written with `DmxPort::write_blackout`, so wrapped rate
+/// limits, curves, and transforms can't drop or change them.
+///
+/// The blackout is sent when the port is closed, or when it is dropped while
+/// still open, so it also covers an application that exits without closing
+/// its ports.
+#[derive(Serialize, Deserialize)]
+pub struct BlackoutOnClosePort {
+    inner: Box<dyn DmxPort>,
+    #[serde(skip)]
+    is_open: bool,
+}
+
+impl BlackoutOnClosePort {
+    /// Wrap inner, blacking it out when closed.
+    pub fn new(inner: Box<dyn DmxPort>) -> Self {
+        Self {
+            inner,
+            is_open: false,
+        }
+    }
+
+    /// Unwrap this port into the inner port, without blacking it out.
+    pub fn into_inner(mut self) -> Box<dyn DmxPort> {
+        // Drop still runs on the emptied wrapper, so leave nothing for it to do.
+        self.is_open = false;
+        std::mem::replace(&mut self.inner, Box::new(crate::OfflineDmxPort))
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for BlackoutOnClosePort {
+    /// Blackout ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.inner.open()?;
+        self.is_open = true;
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        if self.is_open {
+            if let Err(err) = self.inner.write_blackout(&[0; DMX_UNIVERSE_SIZE]) {
+                warn!("Failed to black out {} on close: {}.", self.inner, err);
+            }
+            self.is_open = false;
+        }
+        self.inner.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.inner.write(frame)
+    }
+
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_blackout(frame)
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.inner.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.inner.max_fps()
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.migrate(from_version);
+    }
+}
+
+impl Drop for BlackoutOnClosePort {
+    fn drop(&mut self) {
+        if self.is_open {
+            self.close();
+        }
+    }
+}
+
+impl fmt::Display for BlackoutOnClosePort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (blackout on close)", self.inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+    use crate::{CurvePort, RateLimitPort};
+
+    #[test]
+    fn test_blacks_out_on_close_and_drop() {
+        let inner = TestPort::default();
+        let mut port = BlackoutOnClosePort::new(Box::new(inner.clone()));
+
+        // Never opened, so nothing to black out.
+        port.close();
+        assert!(inner.frames().is_empty());
+
+        port.open().unwrap();
+        port.write(&[255; 3]).unwrap();
+        port.close();
+        assert!(!inner.is_open());
+        assert_eq!(
+            vec![vec![255; 3], vec![0; DMX_UNIVERSE_SIZE]],
+            inner.frames()
+        );
+
+        inner.clear();
+        port.open().unwrap();
+        drop(port);
+        assert_eq!(vec![vec![0; DMX_UNIVERSE_SIZE]], inner.frames());
+    }
+
+    #[test]
+    fn test_blackout_bypasses_wrappers() {
+        let inner = TestPort::default();
+        let mut inverted = [0; 256];
+        for (level, inverse) in inverted.iter_mut().enumerate() {
+            *inverse = 255 - level as u8;
+        }
+        let limited = RateLimitPort::new(Box::new(inner.clone()), 1.0).unwrap();
+        let curved = CurvePort::new(Box::new(limited), inverted);
+        let mut port = BlackoutOnClosePort::new(Box::new(curved));
+        port.open().unwrap();
+        port.write(&[255; 3]).unwrap();
+        // Written too soon after the last frame for the rate limit, and
+        // inverted by the curve, a plain write would leave the lights up.
+        port.close();
+        assert_eq!(vec![vec![0; 3], vec![0; DMX_UNIVERSE_SIZE]], inner.frames());
+    }
+}
@@ -0,0 +1,84 @@
+//! A port that streams frames to HTTP clients as Server-Sent Events.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::net::TcpStream;
+
+use crate::http::{RequestHead, StreamServer};
+use crate::{DmxPort, OpenError, PortListing, WriteError};
+
+/// Stream every frame written to this port to subscribed HTTP clients.
+///
+/// Clients send a GET request to any path on the listening address and
+/// receive a `text/event-stream` response in which each frame is one event
+/// whose data is a JSON array of levels, so a browser can subscribe with
+/// `new EventSource(url)`. Clients that fall behind are disconnected.
+#[derive(Serialize, Deserialize)]
+pub struct SseDmxPort {
+    /// Address to listen for clients on, such as "0.0.0.0:8081".
+    addr: String,
+    #[serde(skip)]
+    server: Option<StreamServer>,
+}
+
+impl SseDmxPort {
+    /// Create an SSE port that will listen on addr once opened.
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            server: None,
+        }
+    }
+
+    /// Return the number of currently subscribed clients.
+    pub fn client_count(&self) -> usize {
+        self.server
+            .as_ref()
+            .map(StreamServer::client_count)
+            .unwrap_or_default()
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for SseDmxPort {
+    /// SSE ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.server.is_some() {
+            return Ok(());
+        }
+        let server = StreamServer::start(&self.addr, "SSE", subscribe)
+            .map_err(|err| OpenError::Other(err.into()))?;
+        self.server = Some(server);
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.server = None;
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let server = self.server.as_ref().ok_or(WriteError::Disconnected)?;
+        let levels: Vec<String> = frame.iter().map(u8::to_string).collect();
+        server.broadcast(format!("data: [{}]\n\n", levels.join(",")).as_bytes());
+        Ok(())
+    }
+}
+
+impl fmt::Display for SseDmxPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SSE {}", self.addr)
+    }
+}
+
+/// Start an event stream response.
+fn subscribe(mut stream: &TcpStream, _: &RequestHead) -> anyhow::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\n\r\n"
+    )?;
+    Ok(())
+}
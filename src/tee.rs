@@ -0,0 +1,100 @@
+//! A port that copies every frame to a secondary sink.
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError};
+
+/// Forward frames to an inner port while also copying them to a secondary sink.
+///
+/// The inner port is the production output; its result is what `write` returns.
+/// Failures on the sink are logged and otherwise ignored, so a broken logging
+/// path can never take down the show.
+#[derive(Serialize, Deserialize)]
+pub struct TeePort {
+    inner: Box<dyn DmxPort>,
+    sink: Box<dyn DmxPort>,
+}
+
+impl TeePort {
+    /// Wrap inner, copying every frame written to it into sink.
+    pub fn new(inner: Box<dyn DmxPort>, sink: Box<dyn DmxPort>) -> Self {
+        Self { inner, sink }
+    }
+
+    /// Unwrap this port into the inner port and the sink.
+    pub fn into_parts(self) -> (Box<dyn DmxPort>, Box<dyn DmxPort>) {
+        (self.inner, self.sink)
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for TeePort {
+    /// Tee ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        if let Err(err) = self.sink.open() {
+            warn!("Failed to open tee sink {}: {}.", self.sink, err);
+        }
+        self.inner.open()
+    }
+
+    fn close(&mut self) {
+        self.inner.close();
+        self.sink.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        if let Err(err) = self.sink.write(frame) {
+            warn!("Failed to write to tee sink {}: {}.", self.sink, err);
+        }
+        self.inner.write(frame)
+    }
+
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        if let Err(err) = self.sink.write_blackout(frame) {
+            warn!("Failed to black out tee sink {}: {}.", self.sink, err);
+        }
+        self.inner.write_blackout(frame)
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        if let Err(err) = self.sink.write_alternate(start_code, data) {
+            warn!("Failed to write to tee sink {}: {}.", self.sink, err);
+        }
+        self.inner.write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        if let Err(err) = self.sink.sync() {
+            warn!("Failed to sync tee sink {}: {}.", self.sink, err);
+        }
+        self.inner.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.inner.max_fps()
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.migrate(from_version);
+        self.sink.migrate(from_version);
+    }
+}
+
+impl fmt::Display for TeePort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (tee to {})", self.inner, self.sink)
+    }
+}
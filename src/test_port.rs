@@ -0,0 +1,186 @@
+//! A port for tests that records everything sent to it.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::{DmxPort, ManualClock, OpenError, PortListing, WriteError};
+
+/// Something sent to a TestPort.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Sent {
+    Frame(Vec<u8>),
+    Alternate(u8, Vec<u8>),
+    Sync,
+}
+
+#[derive(Default)]
+struct Log {
+    sent: Vec<Sent>,
+    open: bool,
+    /// While true, writes fail as if the port were unplugged.
+    broken: bool,
+    /// How long each write takes, on a clock.
+    delay: Option<(Arc<ManualClock>, Duration)>,
+    /// While true, writes panic, as a buggy port might.
+    panics: bool,
+    /// The rate limit the port reports.
+    max_fps: Option<f64>,
+}
+
+/// A port that records what is sent to it. Clones share the same record, so a
+/// test can keep a clone to inspect a port it handed to a wrapper.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub(crate) struct TestPort {
+    name: String,
+    #[serde(skip)]
+    log: Arc<(Mutex<Log>, Condvar)>,
+}
+
+impl TestPort {
+    /// Create a port shown as name.
+    pub(crate) fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn log(&self) -> MutexGuard<'_, Log> {
+        self.log.0.lock().unwrap()
+    }
+
+    /// Make writes fail as if the port were unplugged, or work again.
+    pub(crate) fn set_broken(&self, broken: bool) {
+        self.log().broken = broken;
+    }
+
+    /// Make every write take delay, by advancing clock.
+    pub(crate) fn set_delay(&self, clock: Arc<ManualClock>, delay: Duration) {
+        self.log().delay = Some((clock, delay));
+    }
+
+    /// Make writes panic, or work again.
+    pub(crate) fn set_panics(&self, panics: bool) {
+        self.log().panics = panics;
+    }
+
+    /// Report max_fps as the fastest rate the port can output at.
+    pub(crate) fn set_max_fps(&self, max_fps: Option<f64>) {
+        self.log().max_fps = max_fps;
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.log().open
+    }
+
+    /// Return everything sent, in order.
+    pub(crate) fn sent(&self) -> Vec<Sent> {
+        self.log().sent.clone()
+    }
+
+    /// Return the frames written, in order.
+    pub(crate) fn frames(&self) -> Vec<Vec<u8>> {
+        self.log()
+            .sent
+            .iter()
+            .filter_map(|sent| match sent {
+                Sent::Frame(frame) => Some(frame.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Return the number of frames written.
+    pub(crate) fn writes(&self) -> usize {
+        self.frames().len()
+    }
+
+    /// Forget everything sent so far.
+    pub(crate) fn clear(&self) {
+        self.log().sent.clear();
+    }
+
+    /// Wait up to timeout for at least count frames to have been written.
+    /// Return true if they were.
+    pub(crate) fn wait_for_writes(&self, count: usize, timeout: Duration) -> bool {
+        let (log, written) = &*self.log;
+        let log = log.lock().unwrap();
+        let count_frames = |log: &Log| {
+            log.sent
+                .iter()
+                .filter(|sent| matches!(sent, Sent::Frame(_)))
+                .count()
+        };
+        let (log, _) = written
+            .wait_timeout_while(log, timeout, |log| count_frames(log) < count)
+            .unwrap();
+        count_frames(&log) >= count
+    }
+
+    fn record(&self, sent: Sent) -> Result<(), WriteError> {
+        let (delay, panics) = {
+            let log = self.log();
+            (log.delay.clone(), log.panics)
+        };
+        if panics {
+            panic!("{self} panicked on purpose");
+        }
+        if let Some((clock, delay)) = delay {
+            clock.advance(delay);
+        }
+        let mut log = self.log();
+        if log.broken {
+            return Err(WriteError::Disconnected);
+        }
+        log.sent.push(sent);
+        self.log.1.notify_all();
+        Ok(())
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for TestPort {
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.log().open = true;
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.log().open = false;
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.record(Sent::Frame(frame.to_vec()))
+    }
+
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.record(Sent::Alternate(start_code, data.to_vec()))
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.record(Sent::Sync)
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.log().max_fps
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.frames().pop()
+    }
+}
+
+impl fmt::Display for TestPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "test")
+        } else {
+            write!(f, "{}", self.name)
+        }
+    }
+}
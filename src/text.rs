@@ -0,0 +1,35 @@
+//! DMX512 text packets, which carry labels and messages for receivers that
+//! display them.
+
+/// The alternate start code of a text packet.
+pub const TEXT_START_CODE: u8 = 0x17;
+
+/// Encode the slots of a text packet, to be sent with `DmxPort::write_alternate`
+/// and TEXT_START_CODE.
+///
+/// Receivers can show several pages of text; page selects which one this is.
+/// A receiver wraps the text at characters_per_line, or not at all if it is 0.
+/// Characters that aren't ASCII are replaced with '?', and text longer than
+/// fits in a universe is cut off.
+pub fn text_packet(page: u8, characters_per_line: u8, text: &str) -> Vec<u8> {
+    let mut slots = vec![page, characters_per_line];
+    slots.extend(
+        text.chars()
+            .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+            // Leave room for the null terminator.
+            .take(crate::DMX_UNIVERSE_SIZE - 3),
+    );
+    slots.push(0);
+    slots
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_text_packet() {
+        assert_eq!(b"\x01\x14Stage ?\0".to_vec(), text_packet(1, 20, "Stage ½"));
+        assert_eq!(512, text_packet(0, 0, &"x".repeat(600)).len());
+    }
+}
@@ -0,0 +1,125 @@
+//! A port that applies a caller-provided transform to each frame before writing it.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{DmxPort, FrameSizeLimits, OpenError, PortListing, WriteError};
+
+/// A function that modifies a frame in place just before it is transmitted.
+pub type FrameTransform = Box<dyn Fn(&mut [u8]) + Send>;
+
+/// Run a transform over every frame just before it is written to the inner port.
+///
+/// This is a lightweight way to make one-off tweaks, such as forcing a channel to
+/// full. The transform itself cannot be serialized; a deserialized port passes
+/// frames through unchanged until a transform is installed with `set_transform`.
+#[derive(Serialize, Deserialize)]
+pub struct TransformPort {
+    inner: Box<dyn DmxPort>,
+    #[serde(skip)]
+    transform: Option<FrameTransform>,
+    #[serde(skip)]
+    buffer: Vec<u8>,
+}
+
+impl TransformPort {
+    /// Wrap inner, applying transform to every frame.
+    pub fn new(inner: Box<dyn DmxPort>, transform: impl Fn(&mut [u8]) + Send + 'static) -> Self {
+        Self {
+            inner,
+            transform: Some(Box::new(transform)),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Install a new transform, replacing the existing one.
+    pub fn set_transform(&mut self, transform: impl Fn(&mut [u8]) + Send + 'static) {
+        self.transform = Some(Box::new(transform));
+    }
+
+    /// Remove the transform; frames will be passed through unchanged.
+    pub fn clear_transform(&mut self) {
+        self.transform = None;
+    }
+
+    /// Unwrap this port into the inner port.
+    pub fn into_inner(self) -> Box<dyn DmxPort> {
+        self.inner
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for TransformPort {
+    /// Transform ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        self.inner.open()
+    }
+
+    fn close(&mut self) {
+        self.inner.close();
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let Some(transform) = &self.transform else {
+            return self.inner.write(frame);
+        };
+        // Reuse the buffer to avoid allocating on every frame.
+        self.buffer.clear();
+        self.buffer.extend_from_slice(frame);
+        transform(&mut self.buffer);
+        self.inner.write(&self.buffer)
+    }
+
+    fn write_blackout(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_blackout(frame)
+    }
+
+    /// Alternate start code packets aren't levels, so they aren't transformed.
+    fn write_alternate(&mut self, start_code: u8, data: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_alternate(start_code, data)
+    }
+
+    fn sync(&mut self) -> Result<(), WriteError> {
+        self.inner.sync()
+    }
+
+    fn frame_size_limits(&self) -> FrameSizeLimits {
+        self.inner.frame_size_limits()
+    }
+
+    fn max_fps(&self) -> Option<f64> {
+        self.inner.max_fps()
+    }
+
+    fn captured(&self) -> Option<Vec<u8>> {
+        self.inner.captured()
+    }
+
+    fn migrate(&mut self, from_version: u32) {
+        self.inner.migrate(from_version);
+    }
+}
+
+impl fmt::Display for TransformPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (transformed)", self.inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::TestPort;
+
+    #[test]
+    fn test_blackout_bypasses_transform() {
+        let inner = TestPort::default();
+        let mut port = TransformPort::new(Box::new(inner.clone()), |frame| frame[0] = 255);
+        port.write(&[0, 0]).unwrap();
+        port.write_blackout(&[0, 0]).unwrap();
+        assert_eq!(vec![vec![255, 0], vec![0, 0]], inner.frames());
+    }
+}
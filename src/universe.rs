@@ -0,0 +1,244 @@
+//! Own the levels of several universes and write them all out together.
+use anyhow::bail;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{DmxPort, OpenError, WriteError, DMX_UNIVERSE_SIZE};
+
+/// Send a range of channels of a logical universe to an output, starting at
+/// some channel of that output. Channels are numbered from 0.
+///
+/// For example, sending channels 1 to 256 of logical universe 3 to output 2
+/// starting at its channel 257 is
+/// `Patch { universe: 3, start: 0, count: 256, output: 2, output_start: 256 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Patch {
+    /// The logical universe the channels come from.
+    pub universe: usize,
+    /// The first channel of the range in the logical universe.
+    pub start: usize,
+    /// The number of channels in the range.
+    pub count: usize,
+    /// The output the channels are sent to.
+    pub output: usize,
+    /// The channel of the output the range starts at.
+    pub output_start: usize,
+}
+
+/// The levels of several logical universes, patched across output ports.
+///
+/// Applications set levels with `universe_mut` and then send every output
+/// with one call to `flush`. Patches split a logical universe across several
+/// outputs or merge several into one, so one address space can span
+/// heterogeneous hardware. Output channels that nothing is patched to are sent
+/// as zero; where patches overlap, the one added last wins.
+///
+/// Ports that support synchronization, such as Art-Net nodes with ArtSync, are
+/// synced once every output has been written, so all outputs change on the
+/// same frame.
+#[derive(Default)]
+pub struct UniverseManager {
+    universes: Vec<[u8; DMX_UNIVERSE_SIZE]>,
+    outputs: Vec<Box<dyn DmxPort>>,
+    patches: Vec<Patch>,
+    /// The frame being assembled for an output, reused between flushes.
+    frame: Vec<u8>,
+}
+
+impl UniverseManager {
+    /// Create a manager without any universes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a universe, starting dark, that is sent whole to port. Return the
+    /// index of the universe.
+    pub fn add_universe(&mut self, port: Box<dyn DmxPort>) -> usize {
+        let universe = self.add_logical_universe();
+        let output = self.add_output(port);
+        self.patches.push(Patch {
+            universe,
+            start: 0,
+            count: DMX_UNIVERSE_SIZE,
+            output,
+            output_start: 0,
+        });
+        universe
+    }
+
+    /// Add a universe, starting dark, that isn't sent anywhere until it is
+    /// patched. Return its index.
+    pub fn add_logical_universe(&mut self) -> usize {
+        self.universes.push([0; DMX_UNIVERSE_SIZE]);
+        self.universes.len() - 1
+    }
+
+    /// Add an output that universes can be patched to. Return its index.
+    pub fn add_output(&mut self, port: Box<dyn DmxPort>) -> usize {
+        self.outputs.push(port);
+        self.outputs.len() - 1
+    }
+
+    /// Add a patch. Return an error if it refers to a universe or output that
+    /// doesn't exist, or to channels beyond the end of a universe.
+    pub fn patch(&mut self, patch: Patch) -> anyhow::Result<()> {
+        if patch.universe >= self.universes.len() {
+            bail!("there is no universe {}", patch.universe);
+        }
+        if patch.output >= self.outputs.len() {
+            bail!("there is no output {}", patch.output);
+        }
+        let fits = |start: usize| {
+            start
+                .checked_add(patch.count)
+                .is_some_and(|end| end <= DMX_UNIVERSE_SIZE)
+        };
+        if !fits(patch.start) || !fits(patch.output_start) {
+            bail!("{patch:?} extends beyond the end of a universe");
+        }
+        self.patches.push(patch);
+        Ok(())
+    }
+
+    /// Return the patches, in the order they were added.
+    pub fn patches(&self) -> &[Patch] {
+        &self.patches
+    }
+
+    /// Remove every patch, including those added by `add_universe`.
+    pub fn clear_patches(&mut self) {
+        self.patches.clear();
+    }
+
+    /// Return the number of universes.
+    pub fn universe_count(&self) -> usize {
+        self.universes.len()
+    }
+
+    /// Return the levels of a universe, or None if there is no such universe.
+    pub fn universe(&self, universe: usize) -> Option<&[u8; DMX_UNIVERSE_SIZE]> {
+        self.universes.get(universe)
+    }
+
+    /// Return the levels of a universe to change, or None if there is no such
+    /// universe. Changes are sent by the next `flush`.
+    pub fn universe_mut(&mut self, universe: usize) -> Option<&mut [u8; DMX_UNIVERSE_SIZE]> {
+        self.universes.get_mut(universe)
+    }
+
+    /// Return the number of outputs.
+    pub fn output_count(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Return the port of an output, or None if there is no such output.
+    pub fn output_mut(&mut self, output: usize) -> Option<&mut Box<dyn DmxPort>> {
+        self.outputs.get_mut(output)
+    }
+
+    /// Open every output. Return the output index and error of each port that
+    /// failed to open.
+    pub fn open(&mut self) -> Vec<(usize, OpenError)> {
+        crate::open_all(&mut self.outputs)
+    }
+
+    /// Close every output.
+    pub fn close(&mut self) {
+        crate::close_all(&mut self.outputs);
+    }
+
+    /// Write every output, then sync the outputs that were written.
+    /// Every output is attempted even if some fail; return the output index
+    /// and error of each write or sync that failed.
+    pub fn flush(&mut self) -> Vec<(usize, WriteError)> {
+        let mut errors = Vec::new();
+        let mut written = Vec::with_capacity(self.outputs.len());
+        for (i, port) in self.outputs.iter_mut().enumerate() {
+            self.frame.clear();
+            self.frame.resize(DMX_UNIVERSE_SIZE, 0);
+            for patch in self.patches.iter().filter(|patch| patch.output == i) {
+                let levels = &self.universes[patch.universe][patch.start..][..patch.count];
+                self.frame[patch.output_start..][..patch.count].copy_from_slice(levels);
+            }
+            match port.write(&self.frame) {
+                Ok(()) => written.push(i),
+                Err(err) => {
+                    warn!("Failed to write output {} to {}: {}.", i, port, err);
+                    errors.push((i, err));
+                }
+            }
+        }
+        for i in written {
+            let port = &mut self.outputs[i];
+            if let Err(err) = port.sync() {
+                warn!("Failed to sync output {} on {}: {}.", i, port, err);
+                errors.push((i, err));
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_port::{Sent, TestPort};
+
+    #[test]
+    fn test_flush_writes_then_syncs_every_universe() {
+        let mut manager = UniverseManager::new();
+        let ports: Vec<_> = (0..2)
+            .map(|_| {
+                let port = TestPort::default();
+                manager.add_universe(Box::new(port.clone()));
+                port
+            })
+            .collect();
+        assert!(manager.open().is_empty());
+        manager.universe_mut(1).unwrap()[0] = 255;
+        assert!(manager.universe_mut(2).is_none());
+
+        assert!(manager.flush().is_empty());
+        let mut expected = vec![0; DMX_UNIVERSE_SIZE];
+        assert_eq!(
+            vec![Sent::Frame(expected.clone()), Sent::Sync],
+            ports[0].sent()
+        );
+        expected[0] = 255;
+        assert_eq!(vec![Sent::Frame(expected), Sent::Sync], ports[1].sent());
+    }
+
+    #[test]
+    fn test_patch_splits_and_merges_universes() {
+        let mut manager = UniverseManager::new();
+        let a = manager.add_logical_universe();
+        let b = manager.add_logical_universe();
+        let port = TestPort::default();
+        let output = manager.add_output(Box::new(port.clone()));
+        let patch = |universe, start, output_start| Patch {
+            universe,
+            start,
+            count: 256,
+            output,
+            output_start,
+        };
+        manager.patch(patch(a, 0, 256)).unwrap();
+        manager.patch(patch(b, 256, 0)).unwrap();
+        assert!(manager.patch(patch(b, 257, 0)).is_err());
+        assert!(manager.patch(patch(2, 0, 0)).is_err());
+        let overflowing = Patch {
+            count: usize::MAX,
+            ..patch(a, 1, 0)
+        };
+        assert!(manager.patch(overflowing).is_err());
+
+        manager.universe_mut(a).unwrap()[0] = 1;
+        manager.universe_mut(b).unwrap()[256] = 2;
+        manager.universe_mut(b).unwrap()[0] = 3;
+        assert!(manager.flush().is_empty());
+        let mut expected = vec![0; DMX_UNIVERSE_SIZE];
+        expected[256] = 1;
+        expected[0] = 2;
+        assert_eq!(vec![Sent::Frame(expected), Sent::Sync], port.sent());
+    }
+}
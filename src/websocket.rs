@@ -0,0 +1,165 @@
+//! A port that pushes frames to connected WebSocket clients.
+use anyhow::anyhow;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::fmt;
+use std::io::Write;
+use std::net::TcpStream;
+
+use crate::http::{RequestHead, StreamServer};
+use crate::{DmxPort, OpenError, PortListing, WriteError};
+
+/// Magic value from RFC 6455 used to compute the handshake accept key.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+// WebSocket frame opcodes.
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+
+/// How frames are encoded for WebSocket clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebSocketFormat {
+    /// One binary message per frame containing the raw channel levels.
+    #[default]
+    Binary,
+    /// One text message per frame containing a JSON array of levels.
+    Json,
+}
+
+/// Serve every frame written to this port to all connected WebSocket clients.
+///
+/// Clients connect to `ws://{addr}/` using any path. Incoming messages from
+/// clients are ignored; clients that fall behind are disconnected.
+#[derive(Serialize, Deserialize)]
+pub struct WebSocketPort {
+    /// Address to listen for clients on, such as "0.0.0.0:9001".
+    addr: String,
+    format: WebSocketFormat,
+    #[serde(skip)]
+    server: Option<StreamServer>,
+}
+
+impl WebSocketPort {
+    /// Create a WebSocket port that will listen on addr once opened.
+    pub fn new(addr: impl Into<String>, format: WebSocketFormat) -> Self {
+        Self {
+            addr: addr.into(),
+            format,
+            server: None,
+        }
+    }
+
+    /// Return the number of currently connected clients.
+    pub fn client_count(&self) -> usize {
+        self.server
+            .as_ref()
+            .map(StreamServer::client_count)
+            .unwrap_or_default()
+    }
+
+    fn encode(&self, frame: &[u8]) -> Vec<u8> {
+        match self.format {
+            WebSocketFormat::Binary => encode_message(OPCODE_BINARY, frame),
+            WebSocketFormat::Json => {
+                let levels: Vec<String> = frame.iter().map(u8::to_string).collect();
+                encode_message(OPCODE_TEXT, format!("[{}]", levels.join(",")).as_bytes())
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl DmxPort for WebSocketPort {
+    /// WebSocket ports are constructed explicitly, never discovered.
+    fn available_ports() -> anyhow::Result<PortListing> {
+        Ok(Vec::new())
+    }
+
+    fn open(&mut self) -> Result<(), OpenError> {
+        if self.server.is_some() {
+            return Ok(());
+        }
+        let server = StreamServer::start(&self.addr, "WebSocket", handshake)
+            .map_err(|err| OpenError::Other(err.into()))?;
+        self.server = Some(server);
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        self.server = None;
+    }
+
+    fn write(&mut self, frame: &[u8]) -> Result<(), WriteError> {
+        let message = self.encode(frame);
+        let server = self.server.as_ref().ok_or(WriteError::Disconnected)?;
+        server.broadcast(&message);
+        Ok(())
+    }
+}
+
+impl fmt::Display for WebSocketPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WebSocket {}", self.addr)
+    }
+}
+
+/// Complete the server side of the opening handshake.
+fn handshake(mut stream: &TcpStream, head: &RequestHead) -> anyhow::Result<()> {
+    let key = head
+        .header("sec-websocket-key")
+        .ok_or_else(|| anyhow!("not a WebSocket upgrade request"))?;
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )?;
+    Ok(())
+}
+
+/// Encode an unmasked, unfragmented server-to-client message.
+fn encode_message(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(payload.len() + 10);
+    message.push(0x80 | opcode);
+    match payload.len() {
+        len @ 0..=125 => message.push(len as u8),
+        len @ 126..=0xFFFF => {
+            message.push(126);
+            message.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            message.push(127);
+            message.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Compute the Sec-WebSocket-Accept value for a client's key.
+fn accept_key(key: &str) -> String {
+    STANDARD.encode(Sha1::digest(format!("{key}{HANDSHAKE_GUID}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455 section 1.3.
+        assert_eq!(
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+            accept_key("dGhlIHNhbXBsZSBub25jZQ==")
+        );
+    }
+
+    #[test]
+    fn test_encode_message() {
+        assert_eq!(vec![0x82, 2, 1, 2], encode_message(OPCODE_BINARY, &[1, 2]));
+        let long = encode_message(OPCODE_BINARY, &[0; 512]);
+        assert_eq!(&[0x82, 126, 2, 0], &long[..4]);
+        assert_eq!(516, long.len());
+    }
+}